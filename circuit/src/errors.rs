@@ -4,6 +4,7 @@ pub enum CircuitEvalError {
     UninitializedValue(usize),
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, thiserror::Error)]
 pub enum CircuitLoadError {
     #[error("encountered error while parsing circuit")]
@@ -38,6 +38,17 @@ pub struct CircuitInput {
     pub value: Block,
 }
 
+/// Liveness of the wires of a gate, for evaluating a circuit with only its live wires in memory.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GateLiveness {
+    /// The gate is the last one reading its left input
+    pub lin_last: bool,
+    /// The gate is the last one reading its right input, always `false` for INV gates
+    pub rin_last: bool,
+    /// The output of the gate is read by a later gate
+    pub out_used: bool,
+}
+
 /// Define a circuit
 pub struct Circuit {
     /// Number of gates
@@ -72,6 +83,38 @@ impl Circuit {
         }
     }
 
+    /// Return the liveness of the wires of each gate, computed from the current gates in one
+    /// backward scan. It is not cached, so the gates may still change between two calls.
+    pub fn liveness(&self) -> Vec<GateLiveness> {
+        let mut read = vec![false; self.nwires];
+        let mut liveness = vec![GateLiveness::default(); self.gates.len()];
+        // A wire is last read by the last gate reading it, so scan the gates backwards. The
+        // right input comes first, as it is read after the left one.
+        for (gate, live) in self.gates.iter().zip(liveness.iter_mut()).rev() {
+            let (lin_id, rin_id, out_id) = match *gate {
+                Gate::Xor {
+                    lin_id,
+                    rin_id,
+                    out_id,
+                    ..
+                }
+                | Gate::And {
+                    lin_id,
+                    rin_id,
+                    out_id,
+                    ..
+                } => (lin_id, Some(rin_id), out_id),
+                Gate::Inv { lin_id, out_id, .. } => (lin_id, None, out_id),
+            };
+            live.out_used = read[out_id];
+            if let Some(rin_id) = rin_id {
+                live.rin_last = !std::mem::replace(&mut read[rin_id], true);
+            }
+            live.lin_last = !std::mem::replace(&mut read[lin_id], true);
+        }
+        liveness
+    }
+
     /// Evaluate the circuit in plaintext with the provided inputs
     pub fn eval(&self, inputs: Vec<CircuitInput>) -> Result<Vec<Block>, CircuitEvalError> {
         let mut wires: Vec<Option<Block>> = vec![None; self.nwires];
@@ -3,4 +3,6 @@ pub mod gate;
 pub mod load;
 
 pub use gate::*;
+// `load` only has inherent impls for now, but its items are re-exported like the ones of `gate`.
+#[allow(unused_imports)]
 pub use load::*;
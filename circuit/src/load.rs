@@ -283,6 +283,39 @@ mod tests {
         assert_eq!(output, res);
     }
 
+    #[test]
+    fn test_liveness_adder64() {
+        let circ = Circuit::load("circuit_files/bristol/adder64.txt").unwrap();
+
+        // Every wire read by a gate has exactly one last use, and every gate output but the
+        // outputs of the circuit is read.
+        let liveness = circ.liveness();
+        let mut last_uses = vec![0; circ.nwires];
+        for (gate, live) in circ.gates.iter().zip(liveness) {
+            match *gate {
+                Gate::Xor {
+                    lin_id,
+                    rin_id,
+                    out_id,
+                    ..
+                }
+                | Gate::And {
+                    lin_id,
+                    rin_id,
+                    out_id,
+                    ..
+                } => {
+                    last_uses[lin_id] += live.lin_last as usize;
+                    last_uses[rin_id] += live.rin_last as usize;
+                    assert!(live.out_used || out_id >= circ.nwires - circ.noutput_wires);
+                }
+                Gate::Inv { .. } => unreachable!(),
+            }
+        }
+        assert!(last_uses.iter().all(|&n| n <= 1));
+        assert_eq!(last_uses[..128], [1; 128]);
+    }
+
     #[test]
     fn test_aes() {
         let circ = Circuit::load("circuit_files/bristol/aes_128.txt").unwrap();
@@ -38,9 +38,51 @@ fn bench_tccr_hash(c: &mut Criterion) {
     });
 }
 
+fn bench_tccr_hash8(c: &mut Criterion) {
+    c.bench_function("AesHash::tccr_hash8", |b| {
+        let hash = AesHash::new(rand::random::<Block>());
+        let x = rand::random::<[Block; 8]>();
+        let i = rand::random::<[Block; 8]>();
+        b.iter(|| {
+            let z = hash.tccr_hash8(i, x);
+            criterion::black_box(z)
+        });
+    });
+}
+
+fn bench_tccr_hash_x8(c: &mut Criterion) {
+    // The baseline of `tccr_hash8`, with the blocks hashed one by one.
+    c.bench_function("AesHash::tccr_hash x8", |b| {
+        let hash = AesHash::new(rand::random::<Block>());
+        let x = rand::random::<[Block; 8]>();
+        let i = rand::random::<[Block; 8]>();
+        b.iter(|| {
+            let mut z = [Block::default(); 8];
+            for ((z, i), x) in z.iter_mut().zip(i.iter()).zip(x.iter()) {
+                *z = hash.tccr_hash(*i, *x);
+            }
+            criterion::black_box(z)
+        });
+    });
+}
+
+fn bench_tccr_hash_many4(c: &mut Criterion) {
+    // Four blocks, e.g., the hashes of a half-gate AND gate, are hashed one by one.
+    c.bench_function("AesHash::tccr_hash_many 4", |b| {
+        let hash = AesHash::new(rand::random::<Block>());
+        let x = rand::random::<[Block; 4]>();
+        let i = rand::random::<[Block; 4]>();
+        b.iter(|| {
+            let mut z = [Block::default(); 4];
+            hash.tccr_hash_many(&i, &x, &mut z);
+            criterion::black_box(z)
+        });
+    });
+}
+
 criterion_group! {
     name = aeshash;
     config = Criterion::default().warm_up_time(Duration::from_millis(100));
-    targets = bench_cr_hash, bench_ccr_hash, bench_tccr_hash
+    targets = bench_cr_hash, bench_ccr_hash, bench_tccr_hash, bench_tccr_hash8, bench_tccr_hash_x8, bench_tccr_hash_many4
 }
 criterion_main!(aeshash);
@@ -0,0 +1,21 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use crypto_core::transpose::transpose;
+use std::time::Duration;
+
+fn bench_transpose(c: &mut Criterion) {
+    c.bench_function("transpose::128x2^16", |b| {
+        let ncols = 1 << 16;
+        let m: Vec<u8> = (0..128 * ncols / 8).map(|_| rand::random::<u8>()).collect();
+        b.iter(|| {
+            let t = transpose(&m, 128, ncols);
+            criterion::black_box(t)
+        });
+    });
+}
+
+criterion_group! {
+    name = transpose_bench;
+    config = Criterion::default().warm_up_time(Duration::from_millis(100));
+    targets = bench_transpose
+}
+criterion_main!(transpose_bench);
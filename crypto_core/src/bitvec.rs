@@ -0,0 +1,410 @@
+//! Packed bit vector.
+//!
+//! Bit `i` is stored in byte `i / 8` at position `i % 8`, i.e., the bits of a byte are in
+//! little-endian order as in `pack_bits`. The same order is used for the conversions to
+//! blocks, where bit `i` is bit `i % 128` of `u128::from(blocks[i / 128])`, and to integers,
+//! where bit `i` is `(x >> i) & 1`. The unused bits of the last byte are always zero.
+
+use std::fmt;
+use std::ops::{BitXor, BitXorAssign, Index};
+
+use rand::Rng;
+use zeroize::Zeroize;
+
+use crate::Block;
+
+/// A vector of bits packed into bytes.
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct BitVec {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl BitVec {
+    /// New an empty bit vector.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// New an empty bit vector with room for `n` bits.
+    #[inline]
+    pub fn with_capacity(n: usize) -> Self {
+        Self {
+            bytes: Vec::with_capacity(n.div_ceil(8)),
+            len: 0,
+        }
+    }
+
+    /// New a bit vector of `len` zeros.
+    #[inline]
+    pub fn zeros(len: usize) -> Self {
+        Self {
+            bytes: vec![0u8; len.div_ceil(8)],
+            len,
+        }
+    }
+
+    /// New a uniformly random bit vector of `len` bits.
+    pub fn random<R: Rng>(rng: &mut R, len: usize) -> Self {
+        let mut bytes = vec![0u8; len.div_ceil(8)];
+        rng.fill_bytes(&mut bytes);
+        Self::from_bytes(bytes, len)
+    }
+
+    /// New a bit vector of `len` bits from its packed bytes, which must hold at least `len`
+    /// bits. The extra bits are discarded.
+    pub fn from_bytes(mut bytes: Vec<u8>, len: usize) -> Self {
+        let nbytes = len.div_ceil(8);
+        assert!(bytes.len() >= nbytes, "not enough bytes for {} bits", len);
+        bytes.truncate(nbytes);
+        let mut bv = Self { bytes, len };
+        bv.clear_padding();
+        bv
+    }
+
+    /// New a bit vector of the first `len` bits of `blocks`.
+    pub fn from_blocks(blocks: &[Block], len: usize) -> Self {
+        assert!(
+            blocks.len() * 128 >= len,
+            "not enough blocks for {} bits",
+            len
+        );
+        let bytes = blocks
+            .iter()
+            .flat_map(|b| u128::from(*b).to_le_bytes())
+            .collect();
+        Self::from_bytes(bytes, len)
+    }
+
+    /// New a bit vector of the `len` least significant bits of `x`.
+    pub fn from_u128(x: u128, len: usize) -> Self {
+        assert!(len <= 128, "an u128 has only 128 bits");
+        Self::from_bytes(x.to_le_bytes().to_vec(), len)
+    }
+
+    /// New a bit vector of the `len` least significant bits of `x`.
+    #[inline]
+    pub fn from_u64(x: u64, len: usize) -> Self {
+        assert!(len <= 64, "an u64 has only 64 bits");
+        Self::from_u128(x as u128, len)
+    }
+
+    /// Return the number of bits.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return true if there are no bits.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Return bit `i`.
+    #[inline]
+    pub fn get(&self, i: usize) -> bool {
+        assert!(
+            i < self.len,
+            "index {} out of range for {} bits",
+            i,
+            self.len
+        );
+        (self.bytes[i / 8] >> (i % 8)) & 1 == 1
+    }
+
+    /// Set bit `i` to `b`.
+    #[inline]
+    pub fn set(&mut self, i: usize, b: bool) {
+        assert!(
+            i < self.len,
+            "index {} out of range for {} bits",
+            i,
+            self.len
+        );
+        let mask = 1 << (i % 8);
+        if b {
+            self.bytes[i / 8] |= mask;
+        } else {
+            self.bytes[i / 8] &= !mask;
+        }
+    }
+
+    /// Append bit `b`.
+    #[inline]
+    pub fn push(&mut self, b: bool) {
+        if self.len % 8 == 0 {
+            self.bytes.push(0);
+        }
+        self.len += 1;
+        self.set(self.len - 1, b);
+    }
+
+    /// Shorten the vector to `len` bits, or do nothing if it is not longer.
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len {
+            self.len = len;
+            self.bytes.truncate(len.div_ceil(8));
+            self.clear_padding();
+        }
+    }
+
+    /// Remove all bits.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.bytes.clear();
+        self.len = 0;
+    }
+
+    /// Append the bits of `other`.
+    pub fn extend_from_bitvec(&mut self, other: &BitVec) {
+        if self.len % 8 == 0 {
+            self.bytes.extend_from_slice(&other.bytes);
+            self.len += other.len;
+        } else {
+            self.extend(other.iter());
+        }
+    }
+
+    /// Return the number of ones.
+    #[inline]
+    pub fn count_ones(&self) -> usize {
+        self.bytes.iter().map(|b| b.count_ones() as usize).sum()
+    }
+
+    /// Iterate over the bits.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_> {
+        Iter { bv: self, i: 0 }
+    }
+
+    /// Return the packed bytes.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Return the packed bytes.
+    #[inline]
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Return the bits packed into blocks, padded with zeros.
+    pub fn to_blocks(&self) -> Vec<Block> {
+        self.bytes
+            .chunks(16)
+            .map(|chunk| {
+                let mut bytes = [0u8; 16];
+                bytes[..chunk.len()].copy_from_slice(chunk);
+                Block::from(u128::from_le_bytes(bytes))
+            })
+            .collect()
+    }
+
+    /// Return the bits as an integer. The vector must have at most 128 bits.
+    pub fn to_u128(&self) -> u128 {
+        assert!(self.len <= 128, "too many bits for an u128");
+        let mut bytes = [0u8; 16];
+        bytes[..self.bytes.len()].copy_from_slice(&self.bytes);
+        u128::from_le_bytes(bytes)
+    }
+
+    /// Return the bits as an integer. The vector must have at most 64 bits.
+    #[inline]
+    pub fn to_u64(&self) -> u64 {
+        assert!(self.len <= 64, "too many bits for an u64");
+        self.to_u128() as u64
+    }
+
+    /// Return one `bool` per bit.
+    #[inline]
+    pub fn to_vec(&self) -> Vec<bool> {
+        self.iter().collect()
+    }
+
+    #[inline]
+    fn clear_padding(&mut self) {
+        if self.len % 8 != 0 {
+            let last = self.bytes.len() - 1;
+            self.bytes[last] &= (1 << (self.len % 8)) - 1;
+        }
+    }
+}
+
+/// Zeroizing a bit vector clears its bits and leaves it empty, e.g., for secret choice bits.
+impl Zeroize for BitVec {
+    fn zeroize(&mut self) {
+        self.bytes.zeroize();
+        self.len = 0;
+    }
+}
+
+impl fmt::Debug for BitVec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BitVec(")?;
+        for b in self.iter() {
+            write!(f, "{}", b as u8)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl Index<usize> for BitVec {
+    type Output = bool;
+
+    #[inline]
+    fn index(&self, i: usize) -> &bool {
+        if self.get(i) {
+            &true
+        } else {
+            &false
+        }
+    }
+}
+
+impl BitXorAssign<&BitVec> for BitVec {
+    #[inline]
+    fn bitxor_assign(&mut self, rhs: &BitVec) {
+        assert_eq!(self.len, rhs.len, "bit vectors of different lengths");
+        for (a, b) in self.bytes.iter_mut().zip(rhs.bytes.iter()) {
+            *a ^= *b;
+        }
+    }
+}
+
+impl BitXor for &BitVec {
+    type Output = BitVec;
+
+    #[inline]
+    fn bitxor(self, rhs: &BitVec) -> BitVec {
+        let mut out = self.clone();
+        out ^= rhs;
+        out
+    }
+}
+
+impl FromIterator<bool> for BitVec {
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> Self {
+        let mut bv = BitVec::new();
+        bv.extend(iter);
+        bv
+    }
+}
+
+impl Extend<bool> for BitVec {
+    fn extend<I: IntoIterator<Item = bool>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.bytes.reserve(iter.size_hint().0.div_ceil(8));
+        for b in iter {
+            self.push(b);
+        }
+    }
+}
+
+impl From<&[bool]> for BitVec {
+    #[inline]
+    fn from(bits: &[bool]) -> Self {
+        bits.iter().copied().collect()
+    }
+}
+
+impl From<Vec<bool>> for BitVec {
+    #[inline]
+    fn from(bits: Vec<bool>) -> Self {
+        bits.into_iter().collect()
+    }
+}
+
+impl From<&BitVec> for Vec<bool> {
+    #[inline]
+    fn from(bv: &BitVec) -> Self {
+        bv.to_vec()
+    }
+}
+
+/// Iterator over the bits of a `BitVec`.
+#[derive(Clone, Debug)]
+pub struct Iter<'a> {
+    bv: &'a BitVec,
+    i: usize,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = bool;
+
+    #[inline]
+    fn next(&mut self) -> Option<bool> {
+        if self.i < self.bv.len {
+            self.i += 1;
+            Some(self.bv.get(self.i - 1))
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.bv.len - self.i;
+        (n, Some(n))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+impl<'a> IntoIterator for &'a BitVec {
+    type Item = bool;
+    type IntoIter = Iter<'a>;
+
+    #[inline]
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::pack_bits;
+
+    #[test]
+    fn bitvec_test() {
+        let mut rng = rand::thread_rng();
+        let bits: Vec<bool> = (0..300).map(|_| rng.gen()).collect();
+        let bv = BitVec::from(bits.clone());
+        assert_eq!(bv.len(), 300);
+        assert_eq!(bv.to_vec(), bits);
+        assert_eq!(bv.as_bytes(), pack_bits(&bits).as_slice());
+        assert!((0..300).all(|i| bv[i] == bits[i]));
+        assert_eq!(bv.count_ones(), bits.iter().filter(|b| **b).count());
+
+        // Bytes and blocks.
+        assert_eq!(BitVec::from_bytes(bv.as_bytes().to_vec(), 300), bv);
+        let blocks = bv.to_blocks();
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(BitVec::from_blocks(&blocks, 300), bv);
+        let block = rand::random::<Block>();
+        let x = u128::from(block);
+        let bv_block = BitVec::from_blocks(&[block], 128);
+        assert!((0..128).all(|i| bv_block[i] == ((x >> i) & 1 == 1)));
+        assert_eq!(bv_block.to_u128(), x);
+
+        // Integers, the extra bits are discarded.
+        let bv_int = BitVec::from_u64(0b1011_0110, 5);
+        assert_eq!(bv_int.to_vec(), vec![false, true, true, false, true]);
+        assert_eq!(bv_int.to_u64(), 0b1_0110);
+
+        // Xor, truncation and concatenation.
+        let other = BitVec::random(&mut rng, 300);
+        let x = &bv ^ &other;
+        assert!((0..300).all(|i| x[i] == (bv[i] ^ other[i])));
+        let mut head = bv.clone();
+        head.truncate(13);
+        assert_eq!(head.to_vec(), bits[..13]);
+        head.extend_from_bitvec(&other);
+        assert_eq!(head.len(), 313);
+        assert!((0..300).all(|i| head[13 + i] == other[i]));
+        assert_eq!(head.as_bytes().len(), 40);
+    }
+}
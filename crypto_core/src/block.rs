@@ -1,27 +1,35 @@
 //! Defines a block as a 128-bit value, and implements block-related functions.
 //! The code is derived from Swanky https://github.com/GaloisInc/swanky
-use std::{
-    arch::x86_64::*,
-    hash::{Hash, Hasher},
-};
+//!
+//! On x86-64 a block is an SSE register. On other targets, or with the `portable` feature, a
+//! block is a `u128` and the carry-less multiplication is computed in software.
+use std::hash::{Hash, Hasher};
+
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+use zeroize::DefaultIsZeroes;
+
+#[cfg(all(target_arch = "x86_64", not(feature = "portable")))]
+use std::arch::x86_64::__m128i;
+
+#[cfg(all(target_arch = "x86_64", not(feature = "portable")))]
+mod sse;
+#[cfg(all(target_arch = "x86_64", not(feature = "portable")))]
+use sse::{ONES, ZERO};
+
+#[cfg(any(not(target_arch = "x86_64"), feature = "portable"))]
+mod portable;
+#[cfg(any(not(target_arch = "x86_64"), feature = "portable"))]
+use portable::{ONES, ZERO};
 
 /// A 128-bit chunk.
+#[cfg(all(target_arch = "x86_64", not(feature = "portable")))]
 #[derive(Clone, Copy)]
 pub struct Block(pub __m128i);
 
-union __U128 {
-    vector: __m128i,
-    bytes: u128,
-}
-
-const ZERO: __m128i = unsafe { (__U128 { bytes: 0 }).vector };
-const ONE: __m128i = unsafe { (__U128 { bytes: 1 }).vector };
-const ONES: __m128i = unsafe {
-    (__U128 {
-        bytes: 0xFFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF,
-    })
-    .vector
-};
+/// A 128-bit chunk.
+#[cfg(any(not(target_arch = "x86_64"), feature = "portable"))]
+#[derive(Clone, Copy)]
+pub struct Block(pub u128);
 
 pub const ZERO_BLOCK: Block = Block(ZERO);
 pub const ONES_BLOCK: Block = Block(ONES);
@@ -39,80 +47,84 @@ impl Block {
         self.as_mut().as_mut_ptr()
     }
 
-    /// Carryless multiplication.
-    ///
-    /// This code is adapted from the EMP toolkit's implementation.
+    /// Multiplication in GF(2^128) modulo `x^128 + x^7 + x^2 + x + 1`, with the least
+    /// significant bit first, i.e., bit `i` is the coefficient of `x^i`. This is the bit order
+    /// of POLYVAL.
     #[inline]
-    pub fn clmul(self, rhs: Self) -> (Self, Self) {
-        unsafe {
-            let x = self.0;
-            let y = rhs.0;
-            let zero = _mm_clmulepi64_si128(x, y, 0x00);
-            let one = _mm_clmulepi64_si128(x, y, 0x10);
-            let two = _mm_clmulepi64_si128(x, y, 0x01);
-            let three = _mm_clmulepi64_si128(x, y, 0x11);
-            let tmp = _mm_xor_si128(one, two);
-            let ll = _mm_slli_si128(tmp, 8);
-            let rl = _mm_srli_si128(tmp, 8);
-            let x = _mm_xor_si128(zero, ll);
-            let y = _mm_xor_si128(three, rl);
-            (Block(x), Block(y))
-        }
+    pub fn gf_mul(self, rhs: Self) -> Self {
+        let (lo, hi) = self.clmul(rhs);
+        Block::gf_reduce(lo, hi)
     }
 
-    /// Return the least significant bit.
+    /// Multiplication in GF(2^128) with the bit order of GCM, where the most significant bit
+    /// of the first byte is the coefficient of `x^0` (cf. NIST SP 800-38D, §6.3).
     #[inline]
-    pub fn lsb(&self) -> bool {
-        unsafe { _mm_extract_epi8(_mm_and_si128(self.0, ONE), 0) == 1 }
+    pub fn gf_mul_gcm(self, rhs: Self) -> Self {
+        self.reflect().gf_mul(rhs.reflect()).reflect()
     }
-    /// Set the least significant bit.
-    #[inline]
-    pub fn set_lsb(&self) -> Block {
-        unsafe { Block(_mm_or_si128(self.0, ONE)) }
-    }
-    /// Flip all bits.
+
+    /// The POLYVAL multiplication `a * b * x^-128` of RFC 8452, computed from the GCM field
+    /// with `dot(a, b) = rev(rev(a) * x * rev(b))` (cf. RFC 8452, Appendix A).
     #[inline]
-    pub fn flip(&self) -> Self {
-        unsafe { Block(_mm_xor_si128(self.0, ONES)) }
+    pub fn gf_mul_polyval(self, rhs: Self) -> Self {
+        let rev = |x: Block| Block::from(u128::from(x).reverse_bits());
+        rev(rev(self).gf_mul(Block::from(2u128)).gf_mul(rev(rhs)))
     }
 
-    /// Try to create a `Block` from a slice of bytes. The slice must have exactly 16 bytes.
+    /// Exponentiation in GF(2^128), with the bit order of `gf_mul`.
     #[inline]
-    pub fn try_from_slice(bytes_slice: &[u8]) -> Option<Self> {
-        if bytes_slice.len() != 16 {
-            return None;
+    pub fn gf_pow(self, exp: u128) -> Self {
+        let mut res = Block::from(1u128);
+        let mut base = self;
+        let mut exp = exp;
+        while exp != 0 {
+            if exp & 1 == 1 {
+                res = res.gf_mul(base);
+            }
+            base = base.gf_mul(base);
+            exp >>= 1;
         }
-        let mut bytes = [0; 16];
-        bytes[..16].clone_from_slice(&bytes_slice[..16]);
-        Some(Block::from(bytes))
+        res
     }
 
+    /// Inversion in GF(2^128), with the bit order of `gf_mul`, computed as `x^(2^128 - 2)`.
+    /// The inverse of zero is zero.
     #[inline]
-    pub fn hash_point(tweak: u128, point: &RistrettoPoint) -> Self {
-        let k = point.compress();
-        let aes = Aes256::new(&GenericArray::from_slice(k.as_bytes()));
-        let blk: [u8; 16] = unsafe { std::mem::transmute(tweak) };
-        let mut blk = GenericArray::from(blk);
-        aes.encrypt_block(&mut blk);
-        Block::try_from_slice(blk.as_slice()).unwrap()
-    }
-    
-}
+    pub fn gf_inv(self) -> Self {
+        self.gf_pow(u128::MAX - 1)
+    }
 
-impl Default for Block {
+    /// Inner product `sum_i a_i * b_i` in GF(2^128), with the bit order of `gf_mul`. The
+    /// carry-less products are accumulated unreduced and reduced only once.
     #[inline]
-    fn default() -> Self {
-        unsafe { Block(_mm_setzero_si128()) }
+    pub fn gf_inner_product(a: &[Block], b: &[Block]) -> Self {
+        assert_eq!(a.len(), b.len());
+        let (lo, hi) = a.iter().zip(b.iter()).fold(
+            (Block::default(), Block::default()),
+            |(lo, hi), (x, y)| {
+                let (l, h) = x.clmul(*y);
+                (lo ^ l, hi ^ h)
+            },
+        );
+        Block::gf_reduce(lo, hi)
+    }
+
+    /// Convert between the bit order of GCM and the bit order of `gf_mul` by reversing the bits
+    /// of each byte.
+    #[inline]
+    pub(crate) fn reflect(self) -> Self {
+        Block::from(u128::from(self).swap_bytes().reverse_bits())
     }
-}
 
-impl PartialEq for Block {
+    /// Try to create a `Block` from a slice of bytes. The slice must have exactly 16 bytes.
     #[inline]
-    fn eq(&self, other: &Block) -> bool {
-        unsafe {
-            let neq = _mm_xor_si128(self.0, other.0);
-            _mm_test_all_zeros(neq, neq) != 0
+    pub fn try_from_slice(bytes_slice: &[u8]) -> Option<Self> {
+        if bytes_slice.len() != 16 {
+            return None;
         }
+        let mut bytes = [0; 16];
+        bytes[..16].clone_from_slice(&bytes_slice[..16]);
+        Some(Block::from(bytes))
     }
 }
 
@@ -126,7 +138,7 @@ impl Ord for Block {
 
 impl PartialOrd for Block {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(u128::from(*self).cmp(&u128::from(*other)))
+        Some(self.cmp(other))
     }
 }
 
@@ -144,51 +156,6 @@ impl AsMut<[u8]> for Block {
     }
 }
 
-impl std::ops::BitAnd for Block {
-    type Output = Block;
-    #[inline]
-    fn bitand(self, rhs: Self) -> Self {
-        unsafe { Block(_mm_and_si128(self.0, rhs.0)) }
-    }
-}
-
-impl std::ops::BitAndAssign for Block {
-    #[inline]
-    fn bitand_assign(&mut self, rhs: Self) {
-        unsafe { self.0 = _mm_and_si128(self.0, rhs.0) }
-    }
-}
-
-impl std::ops::BitOr for Block {
-    type Output = Block;
-    #[inline]
-    fn bitor(self, rhs: Self) -> Self {
-        unsafe { Block(_mm_or_si128(self.0, rhs.0)) }
-    }
-}
-
-impl std::ops::BitOrAssign for Block {
-    #[inline]
-    fn bitor_assign(&mut self, rhs: Self) {
-        unsafe { self.0 = _mm_or_si128(self.0, rhs.0) }
-    }
-}
-
-impl std::ops::BitXor for Block {
-    type Output = Block;
-    #[inline]
-    fn bitxor(self, rhs: Self) -> Self {
-        unsafe { Block(_mm_xor_si128(self.0, rhs.0)) }
-    }
-}
-
-impl std::ops::BitXorAssign for Block {
-    #[inline]
-    fn bitxor_assign(&mut self, rhs: Self) {
-        unsafe { self.0 = _mm_xor_si128(self.0, rhs.0) }
-    }
-}
-
 impl std::fmt::Debug for Block {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         let block: [u8; 16] = (*self).into();
@@ -232,20 +199,6 @@ impl From<u128> for Block {
     }
 }
 
-impl From<Block> for __m128i {
-    #[inline]
-    fn from(m: Block) -> __m128i {
-        m.0
-    }
-}
-
-impl From<__m128i> for Block {
-    #[inline]
-    fn from(m: __m128i) -> Self {
-        Block(m)
-    }
-}
-
 impl From<Block> for [u8; 16] {
     #[inline]
     fn from(m: Block) -> [u8; 16] {
@@ -283,9 +236,29 @@ impl Hash for Block {
     }
 }
 
-use aes::Aes256;
-use cipher::{generic_array::GenericArray, BlockEncrypt, KeyInit};
-use curve25519_dalek::ristretto::RistrettoPoint;
+/// Constant-time equality, to be used instead of `==` on secret blocks.
+impl ConstantTimeEq for Block {
+    #[inline]
+    fn ct_eq(&self, other: &Self) -> Choice {
+        u128::from(*self).ct_eq(&u128::from(*other))
+    }
+}
+
+/// Constant-time selection, i.e., `b` if `choice` is set and `a` otherwise.
+impl ConditionallySelectable for Block {
+    #[inline]
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Block::from(u128::conditional_select(
+            &u128::from(*a),
+            &u128::from(*b),
+            choice,
+        ))
+    }
+}
+
+/// Zeroizing a block sets it to `ZERO_BLOCK`.
+impl DefaultIsZeroes for Block {}
+
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 #[derive(Serialize, Deserialize)]
@@ -344,12 +317,26 @@ mod tests {
         assert_eq!(x, z);
     }
 
+    #[test]
+    fn test_ct() {
+        let x = rand::random::<Block>();
+        let y = x ^ Block::from(1u128 << 77);
+        assert!(bool::from(x.ct_eq(&x)));
+        assert!(!bool::from(x.ct_eq(&y)));
+        assert_eq!(Block::conditional_select(&x, &y, Choice::from(0)), x);
+        assert_eq!(Block::conditional_select(&x, &y, Choice::from(1)), y);
+
+        let mut z = x;
+        zeroize::Zeroize::zeroize(&mut z);
+        assert_eq!(z, ZERO_BLOCK);
+    }
+
     #[test]
     fn test_lsb() {
         let x = rand::random::<Block>();
-        let x = x | Block(ONE);
+        let x = x | Block::from(1u128);
         assert!(x.lsb());
-        let x = x ^ Block(ONE);
+        let x = x ^ Block::from(1u128);
         assert!(!x.lsb());
     }
 
@@ -366,4 +353,74 @@ mod tests {
         let x_ = u128::from(Block::from(x));
         assert_eq!(x, x_);
     }
+
+    #[test]
+    fn test_sigma() {
+        let x = rand::random::<u128>();
+        let (lo, hi) = (x as u64, (x >> 64) as u64);
+        let expected = hi as u128 | ((lo ^ hi) as u128) << 64;
+        assert_eq!(u128::from(Block::from(x).sigma()), expected);
+    }
+
+    fn hex_block(s: &str) -> Block {
+        let bytes: Vec<u8> = (0..32)
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect();
+        Block::try_from_slice(&bytes).unwrap()
+    }
+
+    #[test]
+    fn test_gf_mul_gcm() {
+        // NIST GCM test case 2 (McGrew and Viega, "The Galois/Counter Mode of Operation")
+        let h = hex_block("66e94bd4ef8a2c3b884cfa59ca342b2e");
+        let c = hex_block("0388dace60b6a392f328c2b971b2fe78");
+        let len = hex_block("00000000000000000000000000000080");
+        let x1 = c.gf_mul_gcm(h);
+        assert_eq!(x1, hex_block("5e2ec746917062882c85b0685353deb7"));
+        let x2 = (x1 ^ len).gf_mul_gcm(h);
+        assert_eq!(x2, hex_block("f38cbb1ad69223dcc3457ae5b6b0f885"));
+    }
+
+    #[test]
+    fn test_gf_mul_polyval() {
+        // RFC 8452, Appendix A
+        let h = hex_block("25629347589242761d31f826ba4b757b");
+        let x1 = hex_block("4f4f95668c83dfb6401762bb2d01a262");
+        let x2 = hex_block("d1a24ddd2721d006bbe45f20d3c9f362");
+        let s = (x1.gf_mul_polyval(h) ^ x2).gf_mul_polyval(h);
+        assert_eq!(s, hex_block("f7a3b47b846119fae5b7866cf5e5b77e"));
+    }
+
+    #[test]
+    fn test_gf_arith() {
+        let a = rand::random::<Block>();
+        let b = rand::random::<Block>();
+        let c = rand::random::<Block>();
+        let one = Block::from(1u128);
+
+        assert_eq!(a.gf_mul(b), b.gf_mul(a));
+        assert_eq!(a.gf_mul(b ^ c), a.gf_mul(b) ^ a.gf_mul(c));
+        // x^127 * x = x^128 = x^7 + x^2 + x + 1
+        assert_eq!(
+            Block::from(1u128 << 127).gf_mul(Block::from(2u128)),
+            Block::from(0x87u128)
+        );
+        assert_eq!(
+            Block::from(0x0388dace60b6a392f328c2b971b2fe78u128)
+                .gf_mul(Block::from(0x66e94bd4ef8a2c3b884cfa59ca342b2eu128)),
+            Block::from(0x519fa38ac731568e9c1eb21731167f1cu128)
+        );
+        assert_eq!(a.gf_pow(3), a.gf_mul(a).gf_mul(a));
+        assert_eq!(a.gf_mul(a.gf_inv()), one);
+        assert_eq!(Block::default().gf_inv(), Block::default());
+
+        let xs: Vec<Block> = (0..33).map(|_| rand::random::<Block>()).collect();
+        let ys: Vec<Block> = (0..33).map(|_| rand::random::<Block>()).collect();
+        let expected = xs
+            .iter()
+            .zip(ys.iter())
+            .fold(Block::default(), |acc, (x, y)| acc ^ x.gf_mul(*y));
+        assert_eq!(Block::gf_inner_product(&xs, &ys), expected);
+    }
 }
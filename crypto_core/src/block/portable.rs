@@ -0,0 +1,124 @@
+//! Portable backend of `Block`, for targets without SSE and for testing with the `portable`
+//! feature.
+
+use super::Block;
+
+pub(super) const ZERO: u128 = 0;
+pub(super) const ONES: u128 = u128::MAX;
+
+/// Carry-less product of two 64-bit values.
+#[inline]
+fn clmul64(x: u64, y: u64) -> u128 {
+    let x = x as u128;
+    (0..64).fold(0u128, |acc, i| {
+        let mask = 0u128.wrapping_sub(((y >> i) & 1) as u128);
+        acc ^ ((x << i) & mask)
+    })
+}
+
+impl Block {
+    /// Carryless multiplication.
+    #[inline]
+    pub fn clmul(self, rhs: Self) -> (Self, Self) {
+        let (x0, x1) = (self.0 as u64, (self.0 >> 64) as u64);
+        let (y0, y1) = (rhs.0 as u64, (rhs.0 >> 64) as u64);
+        let zero = clmul64(x0, y0);
+        let three = clmul64(x1, y1);
+        let tmp = clmul64(x0, y1) ^ clmul64(x1, y0);
+        (Block(zero ^ (tmp << 64)), Block(three ^ (tmp >> 64)))
+    }
+
+    /// Reduce the 256-bit carry-less product `(lo, hi)` returned by `clmul` modulo the GCM
+    /// polynomial `x^128 + x^7 + x^2 + x + 1`. Bit `i` of a block is the coefficient of `x^i`.
+    #[inline]
+    pub fn gf_reduce(lo: Self, hi: Self) -> Self {
+        // x^128 = x^7 + x^2 + x + 1
+        let t0 = clmul64(hi.0 as u64, 0x87);
+        let t1 = clmul64((hi.0 >> 64) as u64, 0x87);
+        // The high 64 bits of `t1` overflow x^128 once more, by at most 7 bits.
+        let t2 = clmul64((t1 >> 64) as u64, 0x87);
+        Block(lo.0 ^ t0 ^ (t1 << 64) ^ t2)
+    }
+
+    /// Return the least significant bit.
+    #[inline]
+    pub fn lsb(&self) -> bool {
+        self.0 & 1 == 1
+    }
+    /// Set the least significant bit.
+    #[inline]
+    pub fn set_lsb(&self) -> Block {
+        Block(self.0 | 1)
+    }
+    /// Flip all bits.
+    #[inline]
+    pub fn flip(&self) -> Self {
+        Block(!self.0)
+    }
+
+    /// The linear orthomorphism `σ` of the circular correlation-robust hash `ccr_hash`.
+    #[inline]
+    pub(crate) fn sigma(self) -> Self {
+        let (lo, hi) = (self.0 as u64, (self.0 >> 64) as u64);
+        Block(hi as u128 | ((lo ^ hi) as u128) << 64)
+    }
+}
+
+impl Default for Block {
+    #[inline]
+    fn default() -> Self {
+        Block(ZERO)
+    }
+}
+
+impl PartialEq for Block {
+    #[inline]
+    fn eq(&self, other: &Block) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl std::ops::BitAnd for Block {
+    type Output = Block;
+    #[inline]
+    fn bitand(self, rhs: Self) -> Self {
+        Block(self.0 & rhs.0)
+    }
+}
+
+impl std::ops::BitAndAssign for Block {
+    #[inline]
+    fn bitand_assign(&mut self, rhs: Self) {
+        self.0 &= rhs.0
+    }
+}
+
+impl std::ops::BitOr for Block {
+    type Output = Block;
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self {
+        Block(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for Block {
+    #[inline]
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0
+    }
+}
+
+impl std::ops::BitXor for Block {
+    type Output = Block;
+    #[inline]
+    fn bitxor(self, rhs: Self) -> Self {
+        Block(self.0 ^ rhs.0)
+    }
+}
+
+impl std::ops::BitXorAssign for Block {
+    #[inline]
+    fn bitxor_assign(&mut self, rhs: Self) {
+        self.0 ^= rhs.0
+    }
+}
@@ -0,0 +1,163 @@
+//! SSE backend of `Block`.
+
+use std::arch::x86_64::*;
+
+use super::Block;
+
+union __U128 {
+    vector: __m128i,
+    bytes: u128,
+}
+
+pub(super) const ZERO: __m128i = unsafe { (__U128 { bytes: 0 }).vector };
+const ONE: __m128i = unsafe { (__U128 { bytes: 1 }).vector };
+pub(super) const ONES: __m128i = unsafe {
+    (__U128 {
+        bytes: 0xFFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF,
+    })
+    .vector
+};
+
+impl Block {
+    /// Carryless multiplication.
+    ///
+    /// This code is adapted from the EMP toolkit's implementation.
+    #[inline]
+    pub fn clmul(self, rhs: Self) -> (Self, Self) {
+        unsafe {
+            let x = self.0;
+            let y = rhs.0;
+            let zero = _mm_clmulepi64_si128(x, y, 0x00);
+            let one = _mm_clmulepi64_si128(x, y, 0x10);
+            let two = _mm_clmulepi64_si128(x, y, 0x01);
+            let three = _mm_clmulepi64_si128(x, y, 0x11);
+            let tmp = _mm_xor_si128(one, two);
+            let ll = _mm_slli_si128(tmp, 8);
+            let rl = _mm_srli_si128(tmp, 8);
+            let x = _mm_xor_si128(zero, ll);
+            let y = _mm_xor_si128(three, rl);
+            (Block(x), Block(y))
+        }
+    }
+
+    /// Reduce the 256-bit carry-less product `(lo, hi)` returned by `clmul` modulo the GCM
+    /// polynomial `x^128 + x^7 + x^2 + x + 1`. Bit `i` of a block is the coefficient of `x^i`.
+    #[inline]
+    pub fn gf_reduce(lo: Self, hi: Self) -> Self {
+        unsafe {
+            // x^128 = x^7 + x^2 + x + 1
+            let poly = _mm_set_epi64x(0, 0x87);
+            let t0 = _mm_clmulepi64_si128(hi.0, poly, 0x00);
+            let t1 = _mm_clmulepi64_si128(hi.0, poly, 0x01);
+            // The high 64 bits of `t1` overflow x^128 once more, by at most 7 bits.
+            let t2 = _mm_clmulepi64_si128(_mm_srli_si128(t1, 8), poly, 0x00);
+            let x = _mm_xor_si128(lo.0, t0);
+            let x = _mm_xor_si128(x, _mm_slli_si128(t1, 8));
+            Block(_mm_xor_si128(x, t2))
+        }
+    }
+
+    /// Return the least significant bit.
+    #[inline]
+    pub fn lsb(&self) -> bool {
+        unsafe { _mm_extract_epi8(_mm_and_si128(self.0, ONE), 0) == 1 }
+    }
+    /// Set the least significant bit.
+    #[inline]
+    pub fn set_lsb(&self) -> Block {
+        unsafe { Block(_mm_or_si128(self.0, ONE)) }
+    }
+    /// Flip all bits.
+    #[inline]
+    pub fn flip(&self) -> Self {
+        unsafe { Block(_mm_xor_si128(self.0, ONES)) }
+    }
+
+    /// The linear orthomorphism `σ` of the circular correlation-robust hash `ccr_hash`.
+    #[inline]
+    pub(crate) fn sigma(self) -> Self {
+        unsafe {
+            Block(_mm_xor_si128(
+                _mm_shuffle_epi32(self.0, 78),
+                #[allow(overflowing_literals)]
+                _mm_and_si128(self.0, _mm_set_epi64x(0xFFFF_FFFF_FFFF_FFFF, 0x00)),
+            ))
+        }
+    }
+}
+
+impl Default for Block {
+    #[inline]
+    fn default() -> Self {
+        unsafe { Block(_mm_setzero_si128()) }
+    }
+}
+
+impl PartialEq for Block {
+    #[inline]
+    fn eq(&self, other: &Block) -> bool {
+        unsafe {
+            let neq = _mm_xor_si128(self.0, other.0);
+            _mm_test_all_zeros(neq, neq) != 0
+        }
+    }
+}
+
+impl std::ops::BitAnd for Block {
+    type Output = Block;
+    #[inline]
+    fn bitand(self, rhs: Self) -> Self {
+        unsafe { Block(_mm_and_si128(self.0, rhs.0)) }
+    }
+}
+
+impl std::ops::BitAndAssign for Block {
+    #[inline]
+    fn bitand_assign(&mut self, rhs: Self) {
+        unsafe { self.0 = _mm_and_si128(self.0, rhs.0) }
+    }
+}
+
+impl std::ops::BitOr for Block {
+    type Output = Block;
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self {
+        unsafe { Block(_mm_or_si128(self.0, rhs.0)) }
+    }
+}
+
+impl std::ops::BitOrAssign for Block {
+    #[inline]
+    fn bitor_assign(&mut self, rhs: Self) {
+        unsafe { self.0 = _mm_or_si128(self.0, rhs.0) }
+    }
+}
+
+impl std::ops::BitXor for Block {
+    type Output = Block;
+    #[inline]
+    fn bitxor(self, rhs: Self) -> Self {
+        unsafe { Block(_mm_xor_si128(self.0, rhs.0)) }
+    }
+}
+
+impl std::ops::BitXorAssign for Block {
+    #[inline]
+    fn bitxor_assign(&mut self, rhs: Self) {
+        unsafe { self.0 = _mm_xor_si128(self.0, rhs.0) }
+    }
+}
+
+impl From<Block> for __m128i {
+    #[inline]
+    fn from(m: Block) -> __m128i {
+        m.0
+    }
+}
+
+impl From<__m128i> for Block {
+    #[inline]
+    fn from(m: __m128i) -> Self {
+        Block(m)
+    }
+}
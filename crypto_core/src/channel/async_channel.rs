@@ -0,0 +1,364 @@
+//! Asynchronous channels.
+//!
+//! `AsyncAbstractChannel` mirrors `AbstractChannel` with futures, for services running on an
+//! async executor. The protocols are written once against `AbstractChannel`: they run on an
+//! async channel through `BlockingChannel`, e.g., on a blocking thread of the executor, and a
+//! blocking channel is used from async code through `AsyncAdapter`.
+//!
+//! With the `tokio` feature, `AsyncNetChannel` implements `AsyncAbstractChannel` over any
+//! tokio stream, e.g., a `tokio::net::TcpStream`.
+
+use std::{
+    future::Future,
+    io::{Error, ErrorKind, Result},
+    pin::pin,
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+};
+
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+#[cfg(feature = "tokio")]
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+};
+
+use crate::{AbstractChannel, BitVec, Block};
+
+/// A trait for asynchronous channels, with the same helpers as `AbstractChannel`.
+pub trait AsyncAbstractChannel: Send {
+    /// Write bytes slice to the channel.
+    fn write_bytes(&mut self, bytes: &[u8]) -> impl Future<Output = Result<()>> + Send;
+    /// Read bytes slice from the channel.
+    fn read_bytes(&mut self, bytes: &mut [u8]) -> impl Future<Output = Result<()>> + Send;
+    /// Flush the channel.
+    fn flush(&mut self) -> impl Future<Output = Result<()>> + Send;
+
+    /// Write a `bool` to the channel.
+    fn write_bool(&mut self, b: bool) -> impl Future<Output = Result<()>> + Send {
+        async move { self.write_bytes(&[b as u8]).await }
+    }
+
+    /// Write a `BitVec` to the channel, packed into bytes.
+    fn write_bits(&mut self, bits: &BitVec) -> impl Future<Output = Result<()>> + Send {
+        async move { self.write_bytes(bits.as_bytes()).await }
+    }
+
+    /// Read a `bool` from the channel.
+    fn read_bool(&mut self) -> impl Future<Output = Result<bool>> + Send {
+        async move {
+            let mut data = [0u8; 1];
+            self.read_bytes(&mut data).await?;
+            Ok(data[0] != 0)
+        }
+    }
+
+    /// Read a `BitVec` of `size` bits from the channel.
+    fn read_bits(&mut self, size: usize) -> impl Future<Output = Result<BitVec>> + Send {
+        async move {
+            let mut bytes = vec![0u8; size.div_ceil(8)];
+            self.read_bytes(&mut bytes).await?;
+            Ok(BitVec::from_bytes(bytes, size))
+        }
+    }
+
+    /// Write a `Block` to the channel.
+    fn write_block(&mut self, blk: &Block) -> impl Future<Output = Result<()>> + Send {
+        async move { self.write_bytes(blk.as_ref()).await }
+    }
+
+    /// Read a `Block` from the channel.
+    fn read_block(&mut self) -> impl Future<Output = Result<Block>> + Send {
+        async move {
+            let mut blk = Block::default();
+            self.read_bytes(blk.as_mut()).await?;
+            Ok(blk)
+        }
+    }
+
+    /// Write a Ristretto point to the channel.
+    fn write_point(&mut self, point: &RistrettoPoint) -> impl Future<Output = Result<()>> + Send {
+        async move { self.write_bytes(point.compress().as_bytes()).await }
+    }
+
+    /// Read a Ristretto point from the channel.
+    fn read_point(&mut self) -> impl Future<Output = Result<RistrettoPoint>> + Send {
+        async move {
+            let mut data = [0u8; 32];
+            self.read_bytes(&mut data).await?;
+            CompressedRistretto::from_slice(&data)
+                .decompress()
+                .ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::InvalidData,
+                        "unable to decompress Ristretto point",
+                    )
+                })
+        }
+    }
+}
+
+/// Size of the write buffer of `AsyncNetChannel`, beyond which it is written out before `flush`.
+#[cfg(feature = "tokio")]
+const WRITE_BUFFER_SIZE: usize = 1 << 16;
+
+/// An asynchronous channel over a tokio stream, by default a TCP stream.
+#[cfg(feature = "tokio")]
+pub struct AsyncNetChannel<S = TcpStream> {
+    stream: S,
+    write_buffer: Vec<u8>,
+
+    read_bytes_size: usize,
+    write_bytes_size: usize,
+    flush_num: usize,
+}
+
+#[cfg(feature = "tokio")]
+impl AsyncNetChannel {
+    /// Connect to `addr`.
+    pub async fn connect<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        let stream = TcpStream::connect(addr).await?;
+        stream.set_nodelay(true)?;
+        Ok(Self::from_stream(stream))
+    }
+
+    /// Accept a connection on `listener`.
+    pub async fn accept(listener: &TcpListener) -> Result<Self> {
+        let (stream, _) = listener.accept().await?;
+        stream.set_nodelay(true)?;
+        Ok(Self::from_stream(stream))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> AsyncNetChannel<S> {
+    /// New a channel over a connected `stream`.
+    pub fn from_stream(stream: S) -> Self {
+        Self {
+            stream,
+            write_buffer: Vec::new(),
+            read_bytes_size: 0,
+            write_bytes_size: 0,
+            flush_num: 0,
+        }
+    }
+
+    /// Return the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Return `write_bytes_size`
+    pub fn write_bytes_size(&self) -> usize {
+        self.write_bytes_size
+    }
+
+    /// Return `read_bytes_size`
+    pub fn read_bytes_size(&self) -> usize {
+        self.read_bytes_size
+    }
+
+    /// Return `flush_num`
+    pub fn flush_num(&self) -> usize {
+        self.flush_num
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> AsyncAbstractChannel for AsyncNetChannel<S> {
+    async fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.write_buffer.extend_from_slice(bytes);
+        self.write_bytes_size += bytes.len();
+        if self.write_buffer.len() >= WRITE_BUFFER_SIZE {
+            self.stream.write_all(&self.write_buffer).await?;
+            self.write_buffer.clear();
+        }
+        Ok(())
+    }
+
+    async fn read_bytes(&mut self, bytes: &mut [u8]) -> Result<()> {
+        self.stream.read_exact(bytes).await?;
+        self.read_bytes_size += bytes.len();
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        self.stream.write_all(&self.write_buffer).await?;
+        self.write_buffer.clear();
+        self.stream.flush().await?;
+        self.flush_num += 1;
+        Ok(())
+    }
+}
+
+/// Run `future` to completion on the current thread.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+/// A blocking channel over an asynchronous one, so that the protocols run on it unchanged.
+/// Each operation blocks the current thread, which therefore must not be a thread of the async
+/// executor, e.g., use `spawn_blocking` of the runtime. A tokio stream must be driven by a
+/// runtime, which is the case on the threads of `spawn_blocking`.
+pub struct BlockingChannel<C> {
+    inner: C,
+}
+
+impl<C: AsyncAbstractChannel> BlockingChannel<C> {
+    pub fn new(inner: C) -> Self {
+        Self { inner }
+    }
+
+    /// Return the underlying asynchronous channel.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+impl<C: AsyncAbstractChannel> AbstractChannel for BlockingChannel<C> {
+    #[inline(always)]
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        block_on(self.inner.write_bytes(bytes))
+    }
+
+    #[inline(always)]
+    fn read_bytes(&mut self, bytes: &mut [u8]) -> Result<()> {
+        block_on(self.inner.read_bytes(bytes))
+    }
+
+    #[inline(always)]
+    fn flush(&mut self) -> Result<()> {
+        block_on(self.inner.flush())
+    }
+}
+
+/// An asynchronous channel over a blocking one, e.g., a local channel in tests. Each
+/// operation completes when it is first polled.
+pub struct AsyncAdapter<C> {
+    inner: C,
+}
+
+impl<C: AbstractChannel + Send> AsyncAdapter<C> {
+    pub fn new(inner: C) -> Self {
+        Self { inner }
+    }
+
+    /// Return the underlying blocking channel.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+impl<C: AbstractChannel + Send> AsyncAbstractChannel for AsyncAdapter<C> {
+    async fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.inner.write_bytes(bytes)
+    }
+
+    async fn read_bytes(&mut self, bytes: &mut [u8]) -> Result<()> {
+        self.inner.read_bytes(bytes)
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use rand::random;
+
+    use super::*;
+    use crate::local_channel_pair;
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn async_net_channel_test() {
+        use crate::{
+            cointoss::{cointoss_receive, cointoss_send},
+            AesRng,
+        };
+        use curve25519_dalek::{constants, scalar::Scalar};
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let send_bytes = vec![7u8; 3 * WRITE_BUFFER_SIZE];
+        let send_bits = BitVec::random(&mut rand::thread_rng(), 10);
+        let send_block = random::<Block>();
+        let send_point = Scalar::from(random::<u128>()) * constants::RISTRETTO_BASEPOINT_POINT;
+
+        let (bytes, bits) = (send_bytes.clone(), send_bits.clone());
+        let handle = tokio::spawn(async move {
+            let mut channel = AsyncNetChannel::connect(addr).await.unwrap();
+            channel.write_bytes(&bytes).await.unwrap();
+            channel.write_bool(true).await.unwrap();
+            channel.write_bits(&bits).await.unwrap();
+            channel.write_block(&send_block).await.unwrap();
+            channel.write_point(&send_point).await.unwrap();
+            channel.flush().await.unwrap();
+        });
+
+        let mut channel = AsyncNetChannel::accept(&listener).await.unwrap();
+        let mut recv_bytes = vec![0u8; send_bytes.len()];
+        channel.read_bytes(&mut recv_bytes).await.unwrap();
+        assert_eq!(recv_bytes, send_bytes);
+        assert!(channel.read_bool().await.unwrap());
+        assert_eq!(channel.read_bits(10).await.unwrap(), send_bits);
+        assert_eq!(channel.read_block().await.unwrap(), send_block);
+        assert_eq!(channel.read_point().await.unwrap(), send_point);
+        handle.await.unwrap();
+
+        // A blocking protocol over tokio channels, on blocking threads of the runtime.
+        let handle = tokio::spawn(async move {
+            let channel = AsyncNetChannel::connect(addr).await.unwrap();
+            tokio::task::spawn_blocking(move || {
+                cointoss_send(&mut BlockingChannel::new(channel), &mut AesRng::new()).unwrap()
+            })
+            .await
+            .unwrap()
+        });
+        let channel = AsyncNetChannel::accept(&listener).await.unwrap();
+        let seed = tokio::task::spawn_blocking(move || {
+            cointoss_receive(&mut BlockingChannel::new(channel), &mut AesRng::new()).unwrap()
+        })
+        .await
+        .unwrap();
+        assert_eq!(seed, handle.await.unwrap());
+    }
+
+    #[test]
+    fn channel_adapter_test() {
+        // Asynchronous code over a blocking channel, and back.
+        let (sender, receiver) = local_channel_pair();
+        let block = random::<Block>();
+        let handle = thread::spawn(move || {
+            let mut sender = AsyncAdapter::new(sender);
+            block_on(async {
+                sender.write_block(&block).await.unwrap();
+                sender.flush().await.unwrap();
+            })
+        });
+        let mut receiver = BlockingChannel::new(AsyncAdapter::new(receiver));
+        assert_eq!(receiver.read_block().unwrap(), block);
+        handle.join().unwrap();
+    }
+}
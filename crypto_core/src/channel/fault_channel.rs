@@ -0,0 +1,290 @@
+//! Fault-injecting channels for robustness tests.
+//!
+//! `FaultChannel` wraps a channel and injects faults at chosen offsets of the stream of bytes it
+//! writes or of the stream of bytes it reads. The offsets are counted in the original streams,
+//! i.e., before any fault is applied, and a fault at an offset already passed applies at once.
+
+use std::{
+    collections::VecDeque,
+    io::{Error, ErrorKind, Result},
+    thread,
+    time::Duration,
+};
+
+use crate::AbstractChannel;
+
+/// A fault injected at an offset of a stream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// End the stream at the offset. For writes, the connection is closed and the peer reads the
+    /// end of the stream, while this party only notices on its next read. For reads, the reads
+    /// from the offset on fail with `ErrorKind::UnexpectedEof`.
+    Truncate,
+    /// Xor the byte at the offset with a mask.
+    Corrupt(u8),
+    /// Remove `n` bytes from the offset on.
+    Drop(usize),
+    /// Deliver the bytes from the offset on after a delay.
+    Delay(Duration),
+    /// Close the connection at the offset. The operation reaching it, and all later ones, fail
+    /// with `ErrorKind::ConnectionAborted`, and the peer reads the end of the stream.
+    Close,
+}
+
+/// A channel injecting faults into an inner channel.
+///
+/// Closing the connection drops the inner channel, so the peer only reads the end of the stream
+/// if this channel held the last handle to the connection.
+pub struct FaultChannel<C> {
+    inner: Option<C>,
+    write_faults: VecDeque<(usize, Fault)>,
+    read_faults: VecDeque<(usize, Fault)>,
+    write_pos: usize,
+    read_pos: usize,
+    write_truncated: bool,
+    read_truncated: bool,
+    closed: bool,
+}
+
+impl<C: AbstractChannel> FaultChannel<C> {
+    /// New a channel forwarding to `inner` without faults.
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner: Some(inner),
+            write_faults: VecDeque::new(),
+            read_faults: VecDeque::new(),
+            write_pos: 0,
+            read_pos: 0,
+            write_truncated: false,
+            read_truncated: false,
+            closed: false,
+        }
+    }
+
+    /// Inject `fault` at `offset` of the written stream.
+    pub fn fault_write(mut self, offset: usize, fault: Fault) -> Self {
+        insert_fault(&mut self.write_faults, offset, fault);
+        self
+    }
+
+    /// Inject `fault` at `offset` of the read stream.
+    pub fn fault_read(mut self, offset: usize, fault: Fault) -> Self {
+        insert_fault(&mut self.read_faults, offset, fault);
+        self
+    }
+
+    /// Return the number of bytes written, including the ones removed by faults.
+    #[inline]
+    pub fn write_pos(&self) -> usize {
+        self.write_pos
+    }
+
+    /// Return the number of bytes read, including the ones removed by faults.
+    #[inline]
+    pub fn read_pos(&self) -> usize {
+        self.read_pos
+    }
+
+    fn inner(&mut self) -> Result<&mut C> {
+        if self.closed {
+            return Err(Error::new(
+                ErrorKind::ConnectionAborted,
+                "the connection is closed",
+            ));
+        }
+        self.inner
+            .as_mut()
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "the connection is truncated"))
+    }
+
+    fn close(&mut self) -> Error {
+        self.inner = None;
+        self.closed = true;
+        Error::new(ErrorKind::ConnectionAborted, "the connection is closed")
+    }
+
+    /// Return the next fault within `len` bytes of `pos`, with its distance to `pos`.
+    fn next_fault(
+        faults: &mut VecDeque<(usize, Fault)>,
+        pos: usize,
+        len: usize,
+    ) -> Option<(usize, Fault)> {
+        match faults.front() {
+            Some(&(offset, fault)) if offset < pos + len => {
+                faults.pop_front();
+                Some((offset.saturating_sub(pos), fault))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn insert_fault(faults: &mut VecDeque<(usize, Fault)>, offset: usize, fault: Fault) {
+    let i = faults.partition_point(|(o, _)| *o <= offset);
+    faults.insert(i, (offset, fault));
+}
+
+impl<C: AbstractChannel> AbstractChannel for FaultChannel<C> {
+    fn write_bytes(&mut self, mut bytes: &[u8]) -> Result<()> {
+        if self.closed {
+            return Err(Error::new(
+                ErrorKind::ConnectionAborted,
+                "the connection is closed",
+            ));
+        }
+        while !bytes.is_empty() {
+            if self.write_truncated {
+                self.write_pos += bytes.len();
+                return Ok(());
+            }
+            let Some((n, fault)) =
+                Self::next_fault(&mut self.write_faults, self.write_pos, bytes.len())
+            else {
+                self.inner()?.write_bytes(bytes)?;
+                self.write_pos += bytes.len();
+                return Ok(());
+            };
+            self.inner()?.write_bytes(&bytes[..n])?;
+            self.write_pos += n;
+            bytes = &bytes[n..];
+            match fault {
+                Fault::Truncate => {
+                    self.inner()?.flush()?;
+                    self.inner = None;
+                    self.write_truncated = true;
+                }
+                Fault::Corrupt(mask) => {
+                    self.inner()?.write_bytes(&[bytes[0] ^ mask])?;
+                    self.write_pos += 1;
+                    bytes = &bytes[1..];
+                }
+                Fault::Drop(n) => {
+                    let k = n.min(bytes.len());
+                    self.write_pos += k;
+                    bytes = &bytes[k..];
+                    if k < n {
+                        insert_fault(&mut self.write_faults, self.write_pos, Fault::Drop(n - k));
+                    }
+                }
+                Fault::Delay(delay) => {
+                    self.inner()?.flush()?;
+                    thread::sleep(delay);
+                }
+                Fault::Close => {
+                    self.inner()?.flush()?;
+                    return Err(self.close());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn read_bytes(&mut self, mut bytes: &mut [u8]) -> Result<()> {
+        self.inner()?;
+        while !bytes.is_empty() {
+            if self.read_truncated {
+                return Err(Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "the read stream is truncated",
+                ));
+            }
+            let Some((n, fault)) =
+                Self::next_fault(&mut self.read_faults, self.read_pos, bytes.len())
+            else {
+                self.inner()?.read_bytes(bytes)?;
+                self.read_pos += bytes.len();
+                return Ok(());
+            };
+            let (head, tail) = bytes.split_at_mut(n);
+            self.inner()?.read_bytes(head)?;
+            self.read_pos += n;
+            bytes = tail;
+            match fault {
+                Fault::Truncate => self.read_truncated = true,
+                Fault::Corrupt(mask) => {
+                    self.inner()?.read_bytes(&mut bytes[..1])?;
+                    bytes[0] ^= mask;
+                    self.read_pos += 1;
+                    bytes = &mut bytes[1..];
+                }
+                Fault::Drop(n) => {
+                    self.inner()?.read_bytes(&mut vec![0u8; n])?;
+                    self.read_pos += n;
+                }
+                Fault::Delay(delay) => thread::sleep(delay),
+                Fault::Close => return Err(self.close()),
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        match self.inner {
+            Some(ref mut inner) => inner.flush(),
+            None if self.closed => Err(Error::new(
+                ErrorKind::ConnectionAborted,
+                "the connection is closed",
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::{local_channel_pair, Block};
+
+    #[test]
+    fn fault_channel_test() {
+        let (sender, receiver) = local_channel_pair();
+        let mut sender = FaultChannel::new(sender)
+            .fault_write(3, Fault::Corrupt(0xff))
+            .fault_write(10, Fault::Drop(20))
+            .fault_write(40, Fault::Delay(Duration::from_millis(1)))
+            .fault_write(64, Fault::Truncate);
+        let mut receiver = FaultChannel::new(receiver)
+            .fault_read(16, Fault::Drop(4))
+            .fault_read(44, Fault::Truncate);
+
+        let data: Vec<u8> = (0..100).collect();
+        sender.write_bytes(&data[..50]).unwrap();
+        sender.write_bytes(&data[50..]).unwrap();
+        sender.flush().unwrap();
+        assert_eq!(sender.write_pos(), 100);
+        assert_eq!(
+            sender.read_block().unwrap_err().kind(),
+            ErrorKind::UnexpectedEof
+        );
+
+        // Sent: 0..3, !3, 4..10, 30..64, of which the receiver drops 4 bytes at offset 16.
+        let mut expected: Vec<u8> = (0..10).chain(30..64).collect();
+        expected[3] ^= 0xff;
+        expected.drain(16..20);
+        let mut recv = vec![0u8; expected.len()];
+        receiver.read_bytes(&mut recv).unwrap();
+        assert_eq!(recv, expected);
+        assert_eq!(
+            receiver.read_bool().unwrap_err().kind(),
+            ErrorKind::UnexpectedEof
+        );
+
+        // Closing fails this side at once and ends the stream of the peer.
+        let (a, b) = local_channel_pair();
+        let mut a = FaultChannel::new(a).fault_write(16, Fault::Close);
+        let mut b = FaultChannel::new(b);
+        let handle = thread::spawn(move || {
+            let x = b.read_block();
+            (x.map(|_| ()), b.read_block().map(|_| ()))
+        });
+        a.write_block(&Block::default()).unwrap();
+        let err = a.write_block(&Block::default()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ConnectionAborted);
+        assert_eq!(a.flush().unwrap_err().kind(), ErrorKind::ConnectionAborted);
+        let (first, second) = handle.join().unwrap();
+        assert!(first.is_ok());
+        assert_eq!(second.unwrap_err().kind(), ErrorKind::UnexpectedEof);
+    }
+}
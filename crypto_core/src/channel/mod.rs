@@ -1,18 +1,23 @@
+pub mod async_channel;
+pub mod fault_channel;
 pub mod local_channel;
+pub mod mux_channel;
 pub mod net_channel;
+pub mod sim_channel;
+pub mod transcript_channel;
 
+pub use async_channel::*;
+pub use fault_channel::*;
 pub use local_channel::*;
+pub use mux_channel::*;
 pub use net_channel::*;
+pub use sim_channel::*;
+pub use transcript_channel::*;
 
-use crate::{
-    utils::{pack_bits, unpack_bits},
-    Block,
-};
+use crate::{BitVec, Block};
 use curve25519_dalek::ristretto::{RistrettoPoint, CompressedRistretto};
 use std::{
-    cell::RefCell,
     io::{Read, Result, Write},
-    rc::Rc,
     sync::{Arc, Mutex},
 };
 
@@ -31,11 +36,10 @@ pub trait AbstractChannel {
         self.write_bytes(&[b as u8])
     }
 
-    /// Write a `bool` slice to the channel.
+    /// Write a `BitVec` to the channel, packed into bytes.
     #[inline(always)]
-    fn write_bools(&mut self, bits: &[bool]) -> Result<()> {
-        let bit_vec = pack_bits(bits);
-        self.write_bytes(&bit_vec)
+    fn write_bits(&mut self, bits: &BitVec) -> Result<()> {
+        self.write_bytes(bits.as_bytes())
     }
 
     /// Read a `bool` from the channel.
@@ -46,12 +50,12 @@ pub trait AbstractChannel {
         Ok(data[0] != 0)
     }
 
-    /// Read a `bool` vector from the channel.
+    /// Read a `BitVec` of `size` bits from the channel.
     #[inline(always)]
-    fn read_bools(&mut self, size: usize) -> Result<Vec<bool>> {
-        let mut bit_vec = vec![0u8; (size - 1) / 8 + 1];
-        self.read_bytes(&mut bit_vec)?;
-        Ok(unpack_bits(&bit_vec, size))
+    fn read_bits(&mut self, size: usize) -> Result<BitVec> {
+        let mut bytes = vec![0u8; size.div_ceil(8)];
+        self.read_bytes(&mut bytes)?;
+        Ok(BitVec::from_bytes(bytes, size))
     }
 
     /// Write a `Block` to the channel.
@@ -96,8 +100,8 @@ pub trait AbstractChannel {
 
 /// A standard channel that implements `IOChannel`
 pub struct StdChannel<R, W> {
-    reader: Rc<RefCell<R>>,
-    writer: Rc<RefCell<W>>,
+    reader: R,
+    writer: W,
 
     read_bytes_size: usize,
     write_bytes_size: usize,
@@ -107,9 +111,6 @@ pub struct StdChannel<R, W> {
 impl<R: Read, W: Write> StdChannel<R, W> {
     /// New a `StdChannel`
     pub fn new(reader: R, writer: W) -> Self {
-        let reader = Rc::new(RefCell::new(reader));
-        let writer = Rc::new(RefCell::new(writer));
-
         Self {
             reader,
             writer,
@@ -119,14 +120,9 @@ impl<R: Read, W: Write> StdChannel<R, W> {
         }
     }
 
-    /// Return a reader object wrapped in `Rc<RefCell>`
-    pub fn reader(self) -> Rc<RefCell<R>> {
-        self.reader
-    }
-
-    /// Return a writer object wrapped in `Rc<RefCell>`
-    pub fn writer(self) -> Rc<RefCell<W>> {
-        self.writer
+    /// Return the reader and the writer
+    pub fn into_inner(self) -> (R, W) {
+        (self.reader, self.writer)
     }
 
     /// Return `write_bytes_size`
@@ -148,21 +144,21 @@ impl<R: Read, W: Write> StdChannel<R, W> {
 impl<R: Read, W: Write> AbstractChannel for StdChannel<R, W> {
     #[inline(always)]
     fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
-        self.writer.borrow_mut().write_all(bytes)?;
+        self.writer.write_all(bytes)?;
         self.write_bytes_size += bytes.len();
         Ok(())
     }
 
     #[inline(always)]
-    fn read_bytes(&mut self, mut bytes: &mut [u8]) -> Result<()> {
-        self.reader.borrow_mut().read_exact(&mut bytes)?;
+    fn read_bytes(&mut self, bytes: &mut [u8]) -> Result<()> {
+        self.reader.read_exact(bytes)?;
         self.read_bytes_size += bytes.len();
         Ok(())
     }
 
     #[inline(always)]
     fn flush(&mut self) -> Result<()> {
-        self.writer.borrow_mut().flush()?;
+        self.writer.flush()?;
         self.flush_num += 1;
         Ok(())
     }
@@ -228,8 +224,8 @@ impl<R: Read, W: Write> AbstractChannel for SynChannel<R, W> {
     }
 
     #[inline(always)]
-    fn read_bytes(&mut self, mut bytes: &mut [u8]) -> Result<()> {
-        self.reader.lock().unwrap().read_exact(&mut bytes)?;
+    fn read_bytes(&mut self, bytes: &mut [u8]) -> Result<()> {
+        self.reader.lock().unwrap().read_exact(bytes)?;
         self.read_bytes_size += bytes.len();
         Ok(())
     }
@@ -250,7 +246,7 @@ mod tests {
         thread,
     };
 
-    use crate::{local_channel_pair, AbstractChannel, Block, StdChannel};
+    use crate::{local_channel_pair, AbstractChannel, BitVec, Block, StdChannel};
     use curve25519_dalek::{constants, scalar::Scalar};
     use rand::random;
 
@@ -259,11 +255,12 @@ mod tests {
         let (tx, rx) = UnixStream::pair().unwrap();
         let send_bytes = random::<[u8; 10]>();
         let send_bool = random::<bool>();
-        let send_bools = random::<[bool; 10]>();
+        let send_bits = BitVec::from(&random::<[bool; 10]>()[..]);
         let send_block = random::<Block>();
         let x = Scalar::from(random::<u128>());
         let send_point = x * constants::RISTRETTO_BASEPOINT_POINT;
 
+        let bits = send_bits.clone();
         let handle = thread::spawn(move || {
             let reader = BufReader::new(tx.try_clone().unwrap());
             let writer = BufWriter::new(tx);
@@ -271,7 +268,7 @@ mod tests {
 
             channel.write_bytes(&send_bytes).unwrap();
             channel.write_bool(send_bool).unwrap();
-            channel.write_bools(&send_bools).unwrap();
+            channel.write_bits(&bits).unwrap();
             channel.write_block(&send_block).unwrap();
             channel.write_point(&send_point).unwrap();
 
@@ -286,13 +283,13 @@ mod tests {
 
         channel.read_bytes(&mut recv_bytes).unwrap();
         let recv_bool = channel.read_bool().unwrap();
-        let recv_bools = channel.read_bools(10).unwrap();
+        let recv_bits = channel.read_bits(10).unwrap();
         let recv_block = channel.read_block().unwrap();
         let recv_point = channel.read_point().unwrap();
 
         assert_eq!(send_bytes, recv_bytes);
         assert_eq!(send_bool, recv_bool);
-        assert_eq!(send_bools.to_vec(), recv_bools);
+        assert_eq!(send_bits, recv_bits);
         assert_eq!(send_block, recv_block);
         assert_eq!(send_point, recv_point);
 
@@ -305,15 +302,16 @@ mod tests {
 
         let send_bytes = random::<[u8; 10]>();
         let send_bool = random::<bool>();
-        let send_bools = random::<[bool; 10]>();
+        let send_bits = BitVec::from(&random::<[bool; 10]>()[..]);
         let send_block = random::<Block>();
         let x = Scalar::from(random::<u128>());
         let send_point = x * constants::RISTRETTO_BASEPOINT_POINT;
 
+        let bits = send_bits.clone();
         let handle = thread::spawn(move || {
             sender.write_bytes(&send_bytes).unwrap();
             sender.write_bool(send_bool).unwrap();
-            sender.write_bools(&send_bools).unwrap();
+            sender.write_bits(&bits).unwrap();
             sender.write_block(&send_block).unwrap();
             sender.write_point(&send_point).unwrap();
 
@@ -324,14 +322,14 @@ mod tests {
         let mut recv_bytes = [0u8; 10];
         receiver.read_bytes(&mut recv_bytes).unwrap();
         let recv_bool = receiver.read_bool().unwrap();
-        let recv_bools = receiver.read_bools(10).unwrap();
+        let recv_bits = receiver.read_bits(10).unwrap();
         let recv_block = receiver.read_block().unwrap();
         let recv_point = receiver.read_point().unwrap();
 
 
         assert_eq!(send_bytes, recv_bytes);
         assert_eq!(send_bool, recv_bool);
-        assert_eq!(send_bools.to_vec(), recv_bools);
+        assert_eq!(send_bits, recv_bits);
         assert_eq!(send_block, recv_block);
         assert_eq!(send_point, recv_point);
 
@@ -0,0 +1,590 @@
+//! Multiplexing of sub-channels over one stream.
+//!
+//! A `Multiplexer` carves a byte stream, e.g., a `TcpStream` or a `UnixStream`, into numbered
+//! `SubChannel`s, each an `AbstractChannel` with its own buffering and flushes, which can run
+//! concurrently on different threads. Both parties open the sub-channels of a protocol under the
+//! same numbers.
+//!
+//! The stream carries frames `id || kind || epoch || len || payload`, with `id`, `epoch` and
+//! `len` as 32-bit little-endian integers. A data frame carries at most `MAX_FRAME_SIZE` bytes of
+//! sub-channel `id`. Flow control is per sub-channel: a party sends at most `WINDOW_SIZE` bytes
+//! ahead of what the peer has read, and the peer grants more with a credit frame of `len` bytes
+//! once it has read half a window. A sub-channel which is not read therefore never blocks the
+//! others.
+//!
+//! Dropping a sub-channel discards its unread bytes and sends a reset frame. The peer then
+//! restores the full window of the sub-channel and stamps its next data frames with the number
+//! of resets it has received as `epoch`, so the data frames still in flight, stamped with an
+//! older epoch, are discarded and a reopened sub-channel starts afresh. Data for a sub-channel
+//! which is not open yet is buffered until it is opened, up to `MAX_PENDING_SIZE` bytes and
+//! `MAX_PENDING_CHANNELS` sub-channels never opened by this party, beyond which the peer is
+//! considered malicious and the stream fails.
+//!
+//! Once the `Multiplexer` and all its sub-channels are dropped, the write half of the stream is
+//! flushed and shut down. The background thread reading the frames stops when the peer does the
+//! same, so both ends see the end of the stream.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Write},
+    net::{Shutdown, TcpStream},
+    os::unix::net::UnixStream,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    thread,
+};
+
+use crate::AbstractChannel;
+
+/// Maximal payload of a data frame.
+pub const MAX_FRAME_SIZE: usize = 1 << 14;
+/// Number of bytes of a sub-channel that can be in flight, i.e., sent but not yet read.
+pub const WINDOW_SIZE: usize = 1 << 18;
+/// Maximal number of bytes buffered for the sub-channels which are not open.
+pub const MAX_PENDING_SIZE: usize = 1 << 22;
+/// Maximal number of sub-channels the peer can send frames to before they are opened.
+pub const MAX_PENDING_CHANNELS: usize = 1 << 10;
+
+const FRAME_DATA: u8 = 0;
+const FRAME_CREDIT: u8 = 1;
+const FRAME_RESET: u8 = 2;
+const HEADER_SIZE: usize = 13;
+
+struct SubState {
+    recv: VecDeque<u8>,
+    credit: usize,
+    consumed: usize,
+    // Number of resets sent, i.e., the epoch of the data frames accepted from the peer.
+    recv_epoch: u32,
+    // Number of resets received, i.e., the epoch of the data frames sent to the peer.
+    send_epoch: u32,
+    // The sub-channel has been opened by this party at least once.
+    was_opened: bool,
+}
+
+impl Default for SubState {
+    fn default() -> Self {
+        Self {
+            recv: VecDeque::new(),
+            credit: WINDOW_SIZE,
+            consumed: 0,
+            recv_epoch: 0,
+            send_epoch: 0,
+            was_opened: false,
+        }
+    }
+}
+
+/// The write half of a stream, which can be shut down to signal the end of the stream.
+pub trait ShutdownWrite: Write {
+    /// Shut down the write half of the stream.
+    fn shutdown_write(&mut self) -> Result<()>;
+}
+
+impl ShutdownWrite for TcpStream {
+    fn shutdown_write(&mut self) -> Result<()> {
+        self.shutdown(Shutdown::Write)
+    }
+}
+
+impl ShutdownWrite for UnixStream {
+    fn shutdown_write(&mut self) -> Result<()> {
+        self.shutdown(Shutdown::Write)
+    }
+}
+
+#[derive(Default)]
+struct State {
+    channels: HashMap<u32, SubState>,
+    opened: HashSet<u32>,
+    // Bytes buffered for the sub-channels which are not open.
+    pending_size: usize,
+    // Sub-channels with a state created by the peer which have never been opened.
+    pending_channels: usize,
+    // The stream failed or was closed, with the error to return once the received data is read.
+    error: Option<(ErrorKind, String)>,
+}
+
+struct Shared<W: Write> {
+    state: Mutex<State>,
+    cond: Condvar,
+    writer: Mutex<BufWriter<W>>,
+}
+
+impl<W: Write> Shared<W> {
+    #[inline]
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    #[inline]
+    fn wait<'a>(&self, guard: MutexGuard<'a, State>) -> MutexGuard<'a, State> {
+        self.cond.wait(guard).unwrap()
+    }
+
+    fn write_frame(&self, id: u32, kind: u8, epoch: u32, len: usize, payload: &[u8]) -> Result<()> {
+        let mut header = [0u8; HEADER_SIZE];
+        header[..4].copy_from_slice(&id.to_le_bytes());
+        header[4] = kind;
+        header[5..9].copy_from_slice(&epoch.to_le_bytes());
+        header[9..].copy_from_slice(&(len as u32).to_le_bytes());
+        let mut writer = self.writer.lock().unwrap();
+        writer.write_all(&header)?;
+        writer.write_all(payload)?;
+        if kind != FRAME_DATA {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    fn demux<R: Read>(&self, mut reader: R) -> Result<()> {
+        let mut header = [0u8; HEADER_SIZE];
+        loop {
+            reader.read_exact(&mut header)?;
+            let id = u32::from_le_bytes(header[..4].try_into().unwrap());
+            let epoch = u32::from_le_bytes(header[5..9].try_into().unwrap());
+            let len = u32::from_le_bytes(header[9..].try_into().unwrap()) as usize;
+            match header[4] {
+                FRAME_DATA if len > 0 && len <= MAX_FRAME_SIZE => {
+                    let mut payload = vec![0u8; len];
+                    reader.read_exact(&mut payload)?;
+                    let mut state = self.lock();
+                    let open = state.opened.contains(&id);
+                    let channel = Self::pending_entry(&mut state, id)?;
+                    if epoch != channel.recv_epoch {
+                        // Sent before the peer received our last reset.
+                        continue;
+                    }
+                    if channel.recv.len() + len > WINDOW_SIZE {
+                        return Err(invalid_data("the peer exceeded the flow control window"));
+                    }
+                    channel.recv.extend(payload);
+                    if !open {
+                        state.pending_size += len;
+                        if state.pending_size > MAX_PENDING_SIZE {
+                            return Err(invalid_data(
+                                "the peer sent too much data to sub-channels which are not open",
+                            ));
+                        }
+                    }
+                }
+                FRAME_CREDIT => match self.lock().channels.get_mut(&id) {
+                    Some(channel) => channel.credit += len,
+                    None => return Err(invalid_data("credit for an unknown sub-channel")),
+                },
+                FRAME_RESET => {
+                    let mut state = self.lock();
+                    let channel = Self::pending_entry(&mut state, id)?;
+                    // The peer discards the data frames in flight, so the window is free again.
+                    channel.send_epoch = channel.send_epoch.wrapping_add(1);
+                    channel.credit = WINDOW_SIZE;
+                }
+                _ => return Err(invalid_data("invalid frame")),
+            }
+            self.cond.notify_all();
+        }
+    }
+
+    /// Return the state of sub-channel `id`, creating it if the peer is the first to use it.
+    fn pending_entry(state: &mut State, id: u32) -> Result<&mut SubState> {
+        if !state.channels.contains_key(&id) {
+            if state.pending_channels == MAX_PENDING_CHANNELS {
+                return Err(invalid_data(
+                    "the peer used too many sub-channels which are not open",
+                ));
+            }
+            state.pending_channels += 1;
+        }
+        Ok(state.channels.entry(id).or_default())
+    }
+}
+
+fn invalid_data(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+/// The state shared by the multiplexer and its sub-channels, but not by the background thread.
+/// Dropping the last one closes the write half of the stream.
+struct Handle<W: ShutdownWrite> {
+    shared: Arc<Shared<W>>,
+}
+
+impl<W: ShutdownWrite> Drop for Handle<W> {
+    fn drop(&mut self) {
+        let mut writer = self.shared.writer.lock().unwrap();
+        let _ = writer.flush();
+        let _ = writer.get_mut().shutdown_write();
+    }
+}
+
+/// A multiplexer of sub-channels over one stream.
+pub struct Multiplexer<W: ShutdownWrite> {
+    handle: Arc<Handle<W>>,
+}
+
+impl<W: ShutdownWrite + Send + 'static> Multiplexer<W> {
+    /// New a multiplexer over the two halves of a stream, e.g., a `TcpStream` and its
+    /// `try_clone`. The frames are read by a background thread, which stops when the stream is
+    /// closed by the peer, and the write half is shut down once the multiplexer and all its
+    /// sub-channels are dropped.
+    pub fn new<R: Read + Send + 'static>(reader: R, writer: W) -> Self {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State::default()),
+            cond: Condvar::new(),
+            writer: Mutex::new(BufWriter::new(writer)),
+        });
+        let demux = shared.clone();
+        thread::spawn(move || {
+            let e = demux.demux(BufReader::new(reader)).unwrap_err();
+            let error = if e.kind() == ErrorKind::UnexpectedEof {
+                (e.kind(), "the multiplexed stream is closed".to_string())
+            } else {
+                (e.kind(), e.to_string())
+            };
+            demux.lock().error = Some(error);
+            demux.cond.notify_all();
+        });
+        Self {
+            handle: Arc::new(Handle { shared }),
+        }
+    }
+
+    /// Open sub-channel `id`. It fails with `ErrorKind::AlreadyExists` if the sub-channel is
+    /// open, and can be opened again once it is dropped.
+    pub fn channel(&self, id: u32) -> Result<SubChannel<W>> {
+        let mut guard = self.handle.shared.lock();
+        let state = &mut *guard;
+        if !state.opened.insert(id) {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                format!("sub-channel {} is already open", id),
+            ));
+        }
+        match state.channels.get_mut(&id) {
+            Some(channel) => {
+                // The data sent by the peer before the sub-channel was opened is now its own.
+                state.pending_size -= channel.recv.len();
+                if !channel.was_opened {
+                    state.pending_channels -= 1;
+                }
+                channel.was_opened = true;
+            }
+            None => {
+                let channel = state.channels.entry(id).or_default();
+                channel.was_opened = true;
+            }
+        }
+        drop(guard);
+        Ok(SubChannel {
+            id,
+            shared: self.handle.shared.clone(),
+            _handle: self.handle.clone(),
+            write_buffer: Vec::new(),
+            read_bytes_size: 0,
+            write_bytes_size: 0,
+            flush_num: 0,
+        })
+    }
+}
+
+/// A sub-channel of a `Multiplexer`.
+pub struct SubChannel<W: ShutdownWrite> {
+    id: u32,
+    shared: Arc<Shared<W>>,
+    _handle: Arc<Handle<W>>,
+    write_buffer: Vec<u8>,
+
+    read_bytes_size: usize,
+    write_bytes_size: usize,
+    flush_num: usize,
+}
+
+impl<W: ShutdownWrite> SubChannel<W> {
+    /// Return the number of the sub-channel.
+    #[inline]
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Return `write_bytes_size`
+    pub fn write_bytes_size(&self) -> usize {
+        self.write_bytes_size
+    }
+
+    /// Return `read_bytes_size`
+    pub fn read_bytes_size(&self) -> usize {
+        self.read_bytes_size
+    }
+
+    /// Return `flush_num`
+    pub fn flush_num(&self) -> usize {
+        self.flush_num
+    }
+
+    /// Send the first `len` buffered bytes, waiting for credit from the peer as needed.
+    fn send(&mut self, len: usize) -> Result<()> {
+        let mut sent = 0;
+        while sent < len {
+            let mut state = self.shared.lock();
+            if state.channels[&self.id].credit == 0 {
+                // The peer may wait for the frames still in the buffer before granting credit.
+                drop(state);
+                self.shared.writer.lock().unwrap().flush()?;
+                state = self.shared.lock();
+            }
+            loop {
+                if let Some((kind, msg)) = &state.error {
+                    return Err(Error::new(*kind, msg.clone()));
+                }
+                if state.channels[&self.id].credit > 0 {
+                    break;
+                }
+                state = self.shared.wait(state);
+            }
+            let channel = state.channels.get_mut(&self.id).unwrap();
+            let n = (len - sent).min(MAX_FRAME_SIZE).min(channel.credit);
+            channel.credit -= n;
+            let epoch = channel.send_epoch;
+            drop(state);
+
+            let payload = &self.write_buffer[sent..sent + n];
+            self.shared
+                .write_frame(self.id, FRAME_DATA, epoch, n, payload)?;
+            sent += n;
+        }
+        self.write_buffer.drain(..len);
+        Ok(())
+    }
+}
+
+impl<W: ShutdownWrite> AbstractChannel for SubChannel<W> {
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.write_buffer.extend_from_slice(bytes);
+        self.write_bytes_size += bytes.len();
+        if self.write_buffer.len() >= MAX_FRAME_SIZE {
+            let full = self.write_buffer.len() - self.write_buffer.len() % MAX_FRAME_SIZE;
+            self.send(full)?;
+        }
+        Ok(())
+    }
+
+    fn read_bytes(&mut self, bytes: &mut [u8]) -> Result<()> {
+        let mut read = 0;
+        while read < bytes.len() {
+            let mut state = self.shared.lock();
+            loop {
+                if !state.channels[&self.id].recv.is_empty() {
+                    break;
+                }
+                if let Some((kind, msg)) = &state.error {
+                    return Err(Error::new(*kind, msg.clone()));
+                }
+                state = self.shared.wait(state);
+            }
+            let channel = state.channels.get_mut(&self.id).unwrap();
+            let n = (bytes.len() - read).min(channel.recv.len());
+            for (b, x) in bytes[read..read + n]
+                .iter_mut()
+                .zip(channel.recv.drain(..n))
+            {
+                *b = x;
+            }
+            read += n;
+            channel.consumed += n;
+            let credit = if channel.consumed >= WINDOW_SIZE / 2 {
+                std::mem::take(&mut channel.consumed)
+            } else {
+                0
+            };
+            drop(state);
+
+            if credit > 0 {
+                self.shared
+                    .write_frame(self.id, FRAME_CREDIT, 0, credit, &[])?;
+            }
+        }
+        self.read_bytes_size += bytes.len();
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.send(self.write_buffer.len())?;
+        self.shared.writer.lock().unwrap().flush()?;
+        self.flush_num += 1;
+        Ok(())
+    }
+}
+
+impl<W: ShutdownWrite> Drop for SubChannel<W> {
+    fn drop(&mut self) {
+        // The unread bytes are discarded, as are the ones in flight, which the peer sent before
+        // receiving the reset. The credit of the peer is restored by the reset itself.
+        let mut state = self.shared.lock();
+        state.opened.remove(&self.id);
+        let channel = state.channels.get_mut(&self.id).unwrap();
+        channel.recv = VecDeque::new();
+        channel.consumed = 0;
+        channel.recv_epoch = channel.recv_epoch.wrapping_add(1);
+        drop(state);
+        let _ = self.shared.write_frame(self.id, FRAME_RESET, 0, 0, &[]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::net::UnixStream;
+
+    use super::*;
+    use crate::{
+        cointoss::{cointoss_receive, cointoss_send},
+        AesRng, Block,
+    };
+
+    fn mux_pair() -> (Multiplexer<UnixStream>, Multiplexer<UnixStream>) {
+        let (a, b) = UnixStream::pair().unwrap();
+        (
+            Multiplexer::new(a.try_clone().unwrap(), a),
+            Multiplexer::new(b.try_clone().unwrap(), b),
+        )
+    }
+
+    #[test]
+    fn mux_channel_test() {
+        let (mux0, mux1) = mux_pair();
+        let data: Vec<u8> = (0..4 * WINDOW_SIZE + 7).map(|i| i as u8).collect();
+
+        // Sub-channel 0 runs cointoss, 1 a bulk transfer beyond the window and 2 an echo, all
+        // at once over the same stream.
+        let mut c0 = mux0.channel(0).unwrap();
+        let mut c1 = mux0.channel(1).unwrap();
+        let mut c2 = mux0.channel(2).unwrap();
+        assert_eq!(
+            mux0.channel(1).err().unwrap().kind(),
+            ErrorKind::AlreadyExists
+        );
+        let mut d0 = mux1.channel(0).unwrap();
+        let mut d1 = mux1.channel(1).unwrap();
+        let mut d2 = mux1.channel(2).unwrap();
+
+        let bulk = data.clone();
+        let handles = vec![
+            thread::spawn(move || {
+                let x = cointoss_send(&mut c0, &mut AesRng::new()).unwrap();
+                c0.write_block(&x).unwrap();
+                c0.flush().unwrap();
+            }),
+            thread::spawn(move || {
+                c1.write_bytes(&bulk).unwrap();
+                c1.flush().unwrap();
+                assert_eq!(c1.write_bytes_size(), bulk.len());
+            }),
+            thread::spawn(move || {
+                for i in 0..100u128 {
+                    c2.write_block(&Block::from(i)).unwrap();
+                    c2.flush().unwrap();
+                    assert_eq!(c2.read_block().unwrap(), Block::from(i + 1));
+                }
+            }),
+        ];
+
+        let echo = thread::spawn(move || {
+            for _ in 0..100 {
+                let x = u128::from(d2.read_block().unwrap());
+                d2.write_block(&Block::from(x + 1)).unwrap();
+                d2.flush().unwrap();
+            }
+        });
+        let x = cointoss_receive(&mut d0, &mut AesRng::new()).unwrap();
+        assert_eq!(d0.read_block().unwrap(), x);
+        // Read in a single call, which spans several windows.
+        let mut recv = vec![0u8; data.len()];
+        d1.read_bytes(&mut recv).unwrap();
+        assert_eq!(recv, data);
+
+        echo.join().unwrap();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        // A dropped sub-channel can be opened again, without its unread bytes.
+        drop(d1);
+        assert!(mux1.channel(1).is_ok());
+        let mut c3 = mux0.channel(3).unwrap();
+        let mut d3 = mux1.channel(3).unwrap();
+        c3.write_bytes(&[1, 2]).unwrap();
+        c3.flush().unwrap();
+        assert!(d3.read_bool().unwrap());
+        drop(d3);
+        let mut d3 = mux1.channel(3).unwrap();
+        // The bytes sent after the reset reaches the peer go to the reopened sub-channel.
+        d3.write_bytes(&[4]).unwrap();
+        d3.flush().unwrap();
+        let mut x = [0u8];
+        c3.read_bytes(&mut x).unwrap();
+        assert_eq!(x, [4]);
+        c3.write_bytes(&[3]).unwrap();
+        c3.flush().unwrap();
+        d3.read_bytes(&mut x).unwrap();
+        assert_eq!(x, [3]);
+
+        // A sub-channel dropped with a full window in flight is reopened with a full window.
+        let mut c4 = mux0.channel(4).unwrap();
+        let mut d4 = mux1.channel(4).unwrap();
+        c4.write_bytes(&data[..WINDOW_SIZE]).unwrap();
+        c4.flush().unwrap();
+        assert!(!d4.read_bool().unwrap());
+        drop(d4);
+        let mut d4 = mux1.channel(4).unwrap();
+        d4.write_bytes(&[4]).unwrap();
+        d4.flush().unwrap();
+        c4.read_bytes(&mut x).unwrap();
+        c4.write_bytes(&data[1..WINDOW_SIZE + 1]).unwrap();
+        c4.flush().unwrap();
+        let mut recv = vec![0u8; WINDOW_SIZE];
+        d4.read_bytes(&mut recv).unwrap();
+        assert_eq!(recv, data[1..WINDOW_SIZE + 1]);
+        drop(c4);
+
+        // Dropping a multiplexer and its sub-channels ends the stream of the peer.
+        drop(c3);
+        drop(mux0);
+        assert_eq!(d3.read_bool().unwrap_err().kind(), ErrorKind::UnexpectedEof);
+
+        // Closing the stream fails the reads.
+        let (a, b) = UnixStream::pair().unwrap();
+        let mux = Multiplexer::new(a.try_clone().unwrap(), a);
+        drop(b);
+        let mut c = mux.channel(0).unwrap();
+        assert_eq!(c.read_bool().unwrap_err().kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn mux_pending_test() {
+        // The peer cannot make this party keep an unbounded state for sub-channels it has not
+        // opened, neither with many sub-channels nor with much data.
+        let channels = (0..=MAX_PENDING_CHANNELS as u32).map(|id| (id, FRAME_RESET, 0));
+        let bytes = (0..=MAX_PENDING_SIZE / MAX_FRAME_SIZE).map(|i| {
+            (
+                (i * MAX_FRAME_SIZE / WINDOW_SIZE) as u32,
+                FRAME_DATA,
+                MAX_FRAME_SIZE,
+            )
+        });
+        for frames in [channels.collect::<Vec<_>>(), bytes.collect::<Vec<_>>()] {
+            let (a, mut b) = UnixStream::pair().unwrap();
+            let mux = Multiplexer::new(a.try_clone().unwrap(), a);
+            let peer = thread::spawn(move || {
+                for (id, kind, len) in frames {
+                    let mut frame = id.to_le_bytes().to_vec();
+                    frame.push(kind);
+                    frame.extend_from_slice(&0u32.to_le_bytes());
+                    frame.extend_from_slice(&(len as u32).to_le_bytes());
+                    frame.resize(HEADER_SIZE + len, 0);
+                    b.write_all(&frame).unwrap();
+                }
+            });
+            peer.join().unwrap();
+            let mut c = mux.channel(u32::MAX).unwrap();
+            assert_eq!(c.read_bool().unwrap_err().kind(), ErrorKind::InvalidData);
+        }
+    }
+}
@@ -1,85 +1,315 @@
+//! TCP channels.
+//!
+//! A `NetChannel` is set up with a `NetChannelBuilder`, either by connecting to a peer, retrying
+//! with exponential backoff while the peer is still starting up, or through a `NetListener`
+//! accepting any number of sessions. The connected sockets use `TCP_NODELAY` and the configured
+//! read and write timeouts, after which an operation on the channel fails instead of hanging.
+//! A timed out operation may have consumed or sent part of a message, so the channel is left at
+//! an unknown offset of the stream and must be discarded after such an error.
+
 use crate::AbstractChannel;
 use std::io::Result;
 use std::{
-    cell::RefCell,
     io::{BufReader, BufWriter, Read, Write},
-    net::{TcpListener, TcpStream, ToSocketAddrs},
-    rc::Rc,
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    thread,
+    time::Duration,
 };
 use structopt::StructOpt;
 
+/// Errors of setting up a `NetChannel`.
+#[derive(Debug, thiserror::Error)]
+pub enum NetChannelError {
+    #[error("NetChannel Address Error: {0}")]
+    AddressError(std::io::Error),
+
+    #[error("NetChannel Bind Error: {0}")]
+    BindError(std::io::Error),
+
+    #[error("NetChannel Connect Error after {attempts} attempts: {source}")]
+    ConnectError {
+        attempts: usize,
+        source: std::io::Error,
+    },
+
+    #[error("NetChannel Accept Error: {0}")]
+    AcceptError(std::io::Error),
+
+    #[error("NetChannel Socket Configuration Error: {0}")]
+    SocketError(std::io::Error),
+}
+
+/// Configuration of the connections of `NetChannel`.
+#[derive(Clone, Debug)]
+pub struct NetChannelBuilder {
+    connect_retries: usize,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    nodelay: bool,
+}
+
+impl Default for NetChannelBuilder {
+    fn default() -> Self {
+        Self {
+            connect_retries: 0,
+            initial_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_secs(2),
+            connect_timeout: None,
+            read_timeout: None,
+            write_timeout: None,
+            nodelay: true,
+        }
+    }
+}
+
+impl NetChannelBuilder {
+    /// New a builder with the default configuration: no connect retries, no timeouts and
+    /// `TCP_NODELAY` set.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Retry a failed connection up to `retries` times.
+    #[inline]
+    pub fn connect_retries(mut self, retries: usize) -> Self {
+        self.connect_retries = retries;
+        self
+    }
+
+    /// Wait `initial` before the first retry, then twice as long before each next one, up to
+    /// `max`.
+    #[inline]
+    pub fn retry_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Give up a connection attempt after `timeout`.
+    #[inline]
+    pub fn connect_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Fail a read that waits longer than `timeout`. The channel must be discarded after a read
+    /// fails this way, as the bytes of a partially read message are lost.
+    #[inline]
+    pub fn read_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
+    /// Fail a write that waits longer than `timeout`. The channel must be discarded after a
+    /// write fails this way, as part of the message may have been sent.
+    #[inline]
+    pub fn write_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.write_timeout = timeout;
+        self
+    }
+
+    /// Set or unset `TCP_NODELAY` on the connected sockets.
+    #[inline]
+    pub fn nodelay(mut self, nodelay: bool) -> Self {
+        self.nodelay = nodelay;
+        self
+    }
+
+    /// Connect to `addr` as the client, retrying as configured.
+    pub fn connect<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+    ) -> std::result::Result<NetChannel<TcpStream, TcpStream>, NetChannelError> {
+        let addrs: Vec<SocketAddr> = addr
+            .to_socket_addrs()
+            .map_err(NetChannelError::AddressError)?
+            .collect();
+        let mut backoff = self.initial_backoff;
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            match self.connect_once(&addrs) {
+                Ok(stream) => return self.from_stream(false, stream),
+                Err(e) if attempts > self.connect_retries => {
+                    return Err(NetChannelError::ConnectError {
+                        attempts,
+                        source: e,
+                    })
+                }
+                Err(_) => {
+                    thread::sleep(backoff);
+                    backoff = backoff.saturating_mul(2).min(self.max_backoff);
+                }
+            }
+        }
+    }
+
+    /// Bind a listener to `addr` accepting sessions as the server.
+    pub fn bind<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+    ) -> std::result::Result<NetListener, NetChannelError> {
+        let listener = TcpListener::bind(addr).map_err(NetChannelError::BindError)?;
+        Ok(NetListener {
+            listener,
+            builder: self.clone(),
+        })
+    }
+
+    /// New a channel over a connected `stream`, configured as the builder.
+    pub fn from_stream(
+        &self,
+        is_server: bool,
+        stream: TcpStream,
+    ) -> std::result::Result<NetChannel<TcpStream, TcpStream>, NetChannelError> {
+        stream
+            .set_nodelay(self.nodelay)
+            .and_then(|_| stream.set_read_timeout(self.read_timeout))
+            .and_then(|_| stream.set_write_timeout(self.write_timeout))
+            .map_err(NetChannelError::SocketError)?;
+        let reader = stream.try_clone().map_err(NetChannelError::SocketError)?;
+        Ok(NetChannel::from_parts(is_server, reader, stream))
+    }
+
+    fn connect_once(&self, addrs: &[SocketAddr]) -> Result<TcpStream> {
+        let mut last_err = None;
+        for addr in addrs {
+            let res = match self.connect_timeout {
+                Some(timeout) => TcpStream::connect_timeout(addr, timeout),
+                None => TcpStream::connect(addr),
+            };
+            match res {
+                Ok(stream) => return Ok(stream),
+                Err(e) => last_err = Some(e),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "no address to connect to")
+        }))
+    }
+}
+
+/// A TCP listener accepting `NetChannel` sessions.
+pub struct NetListener {
+    listener: TcpListener,
+    builder: NetChannelBuilder,
+}
+
+impl NetListener {
+    /// Return the address the listener is bound to, e.g., to find the port bound to port 0.
+    #[inline]
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Wait for the next session and return its channel, as the server.
+    pub fn accept(&self) -> std::result::Result<NetChannel<TcpStream, TcpStream>, NetChannelError> {
+        let (stream, _) = self
+            .listener
+            .accept()
+            .map_err(NetChannelError::AcceptError)?;
+        self.builder.from_stream(true, stream)
+    }
+
+    /// Iterate over the incoming sessions.
+    pub fn incoming(
+        &self,
+    ) -> impl Iterator<Item = std::result::Result<NetChannel<TcpStream, TcpStream>, NetChannelError>> + '_
+    {
+        std::iter::repeat_with(move || self.accept())
+    }
+}
+
 pub struct NetChannel<R: Read, W: Write> {
     is_server: bool,
-    reader: Rc<RefCell<BufReader<R>>>,
-    writer: Rc<RefCell<BufWriter<W>>>,
+    reader: BufReader<R>,
+    writer: BufWriter<W>,
     read_bytes_size: usize,
     write_bytes_size: usize,
     flush_num: usize,
 }
 
+impl<R: Read, W: Write> NetChannel<R, W> {
+    /// New a channel over a connected `reader` and `writer`.
+    pub fn from_parts(is_server: bool, reader: R, writer: W) -> Self {
+        Self {
+            is_server,
+            reader: BufReader::new(reader),
+            writer: BufWriter::new(writer),
+            read_bytes_size: 0,
+            write_bytes_size: 0,
+            flush_num: 0,
+        }
+    }
+
+    pub fn is_server(&self) -> bool {
+        self.is_server
+    }
+
+    /// Return the number of bytes read.
+    #[inline]
+    pub fn read_bytes_size(&self) -> usize {
+        self.read_bytes_size
+    }
+
+    /// Return the number of bytes written.
+    #[inline]
+    pub fn write_bytes_size(&self) -> usize {
+        self.write_bytes_size
+    }
+
+    /// Return the number of flushes.
+    #[inline]
+    pub fn flush_num(&self) -> usize {
+        self.flush_num
+    }
+}
+
 impl NetChannel<TcpStream, TcpStream> {
-    pub fn new<A: ToSocketAddrs>(is_server: bool, addr: A) -> Self {
+    /// New a channel with the default configuration, accepting a single session on `addr` as
+    /// the server or connecting to `addr` as the client. Use `NetChannelBuilder` for retries,
+    /// timeouts or several sessions.
+    pub fn new<A: ToSocketAddrs>(
+        is_server: bool,
+        addr: A,
+    ) -> std::result::Result<Self, NetChannelError> {
+        let builder = Self::builder();
         if is_server {
-            let listener = TcpListener::bind(addr).unwrap();
-            match listener.accept() {
-                Ok((socket, _)) => {
-                    println!("connected");
-                    Self {
-                        is_server,
-                        reader: Rc::new(RefCell::new(BufReader::new(socket.try_clone().unwrap()))),
-                        writer: Rc::new(RefCell::new(BufWriter::new(socket))),
-                        read_bytes_size: 0,
-                        write_bytes_size: 0,
-                        flush_num: 0,
-                    }
-                }
-                Err(e) => {
-                    panic!("could not get client: {e:?}");
-                }
-            }
+            builder.bind(addr)?.accept()
         } else {
-            match TcpStream::connect(addr) {
-                Ok(socket) => {
-                    println!("connected");
-                    Self {
-                        is_server,
-                        reader: Rc::new(RefCell::new(BufReader::new(socket.try_clone().unwrap()))),
-                        writer: Rc::new(RefCell::new(BufWriter::new(socket))),
-                        read_bytes_size: 0,
-                        write_bytes_size: 0,
-                        flush_num: 0,
-                    }
-                }
-                Err(e) => {
-                    panic!("could not get server: {e:?}");
-                }
-            }
+            builder.connect(addr)
         }
     }
 
-    pub fn is_server(&self) -> bool {
-        self.is_server
+    /// New a builder of TCP channels.
+    #[inline]
+    pub fn builder() -> NetChannelBuilder {
+        NetChannelBuilder::new()
     }
 }
 
 impl<R: Read, W: Write> AbstractChannel for NetChannel<R, W> {
     #[inline(always)]
     fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
-        self.writer.borrow_mut().write_all(bytes)?;
+        self.writer.write_all(bytes)?;
         self.write_bytes_size += bytes.len();
         Ok(())
     }
 
     #[inline(always)]
-    fn read_bytes(&mut self, mut bytes: &mut [u8]) -> Result<()> {
-        self.reader.borrow_mut().read_exact(&mut bytes)?;
+    fn read_bytes(&mut self, bytes: &mut [u8]) -> Result<()> {
+        self.reader.read_exact(bytes)?;
         self.read_bytes_size += bytes.len();
         Ok(())
     }
 
     #[inline(always)]
     fn flush(&mut self) -> Result<()> {
-        self.writer.borrow_mut().flush()?;
+        self.writer.flush()?;
         self.flush_num += 1;
         Ok(())
     }
@@ -87,6 +317,82 @@ impl<R: Read, W: Write> AbstractChannel for NetChannel<R, W> {
 
 #[derive(StructOpt, Debug)]
 pub struct CommandLineOpt {
-    #[structopt(short, long, default_value = "-1")]
-    pub is_server: u32,
+    /// Run only the server (1) or the client (0), both run in this process if omitted.
+    #[structopt(short, long)]
+    pub is_server: Option<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Block;
+    use std::io::ErrorKind;
+
+    #[test]
+    fn net_channel_test() {
+        // Find a free port, the client starts connecting before the server listens on it.
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let builder = NetChannel::builder()
+            .connect_retries(50)
+            .retry_backoff(Duration::from_millis(10), Duration::from_millis(100))
+            .read_timeout(Some(Duration::from_secs(10)));
+
+        let client_builder = builder.clone();
+        let handle = thread::spawn(move || {
+            for i in 0..3u128 {
+                let mut channel = client_builder.connect(addr).unwrap();
+                assert!(!channel.is_server());
+                channel.write_block(&Block::from(i)).unwrap();
+                channel.flush().unwrap();
+                assert_eq!(channel.read_block().unwrap(), Block::from(i + 1));
+            }
+        });
+
+        thread::sleep(Duration::from_millis(50));
+        let listener = builder.bind(addr).unwrap();
+        let sessions: Vec<_> = listener
+            .incoming()
+            .take(3)
+            .map(|channel| {
+                let mut channel = channel.unwrap();
+                assert!(channel.is_server());
+                thread::spawn(move || {
+                    let x = u128::from(channel.read_block().unwrap());
+                    channel.write_block(&Block::from(x + 1)).unwrap();
+                    channel.flush().unwrap();
+                    (channel.read_bytes_size(), channel.write_bytes_size())
+                })
+            })
+            .collect();
+        for session in sessions {
+            assert_eq!(session.join().unwrap(), (16, 16));
+        }
+        handle.join().unwrap();
+
+        // Typed errors, and a read timing out instead of hanging.
+        drop(listener);
+        match NetChannel::builder().connect_retries(2).connect(addr) {
+            Err(NetChannelError::ConnectError { attempts, .. }) => assert_eq!(attempts, 3),
+            _ => panic!("connected to a closed port"),
+        }
+        let listener = NetChannel::builder()
+            .read_timeout(Some(Duration::from_millis(10)))
+            .bind("127.0.0.1:0")
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+        assert!(matches!(
+            builder.bind(addr),
+            Err(NetChannelError::BindError(_))
+        ));
+        let _client = NetChannel::new(false, addr).unwrap();
+        let mut server = listener.accept().unwrap();
+        let err = server.read_block().unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ErrorKind::WouldBlock | ErrorKind::TimedOut
+        ));
+    }
 }
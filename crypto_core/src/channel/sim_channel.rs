@@ -0,0 +1,269 @@
+//! Simulated network channels.
+//!
+//! `SimChannel` runs a protocol over a fast in-process channel as if over a network with a
+//! given one-way latency and bandwidth, without waiting for either. Each party keeps a simulated
+//! clock, which advances with its real computation time and, when it reads a message, jumps to
+//! the arrival time of the message if it is not there yet. Every flush sends one message, which
+//! leaves when the link is free, takes its size divided by the bandwidth to transmit and arrives
+//! after the latency. The time spent blocked on the inner channel is not counted, so `elapsed`
+//! reports the wall time of the protocol on the simulated network.
+
+use std::{
+    io::Result,
+    num::NonZeroU64,
+    time::{Duration, Instant},
+};
+
+use crate::{local_channel_pair, AbstractChannel, LocalChannel};
+
+/// One-way latency and bandwidth of a simulated network.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NetworkProfile {
+    /// One-way latency of each message.
+    pub latency: Duration,
+    /// Bandwidth in bits per second, unlimited if `None`.
+    pub bandwidth: Option<NonZeroU64>,
+}
+
+impl NetworkProfile {
+    /// No latency and unlimited bandwidth.
+    pub const LOCAL: Self = Self {
+        latency: Duration::ZERO,
+        bandwidth: None,
+    };
+
+    /// A local network: 0.5 ms latency and 1 Gbps.
+    pub const LAN: Self = Self {
+        latency: Duration::from_micros(500),
+        bandwidth: NonZeroU64::new(1_000_000_000),
+    };
+
+    /// A wide-area network: 50 ms latency and 100 Mbps.
+    pub const WAN: Self = Self {
+        latency: Duration::from_millis(50),
+        bandwidth: NonZeroU64::new(100_000_000),
+    };
+
+    /// New a profile of one-way `latency` and `bandwidth` bits per second. The bandwidth is
+    /// non-zero, so that every message is transmitted in finite time.
+    #[inline]
+    pub fn new(latency: Duration, bandwidth: Option<NonZeroU64>) -> Self {
+        Self { latency, bandwidth }
+    }
+
+    /// Return the time to transmit `len` bytes.
+    #[inline]
+    pub fn transmission_time(&self, len: usize) -> Duration {
+        match self.bandwidth {
+            Some(bw) => {
+                let nanos = len as u128 * 8 * 1_000_000_000 / bw.get() as u128;
+                Duration::from_nanos(nanos as u64)
+            }
+            None => Duration::ZERO,
+        }
+    }
+}
+
+/// A channel simulating a network over an inner channel. Both parties must use a `SimChannel`,
+/// as each message carries its simulated arrival time.
+pub struct SimChannel<C> {
+    inner: C,
+    profile: NetworkProfile,
+
+    // Simulated time at `last`.
+    clock: Duration,
+    last: Instant,
+    // Simulated time at which the outgoing link is free.
+    link_free: Duration,
+
+    write_buffer: Vec<u8>,
+    read_buffer: Vec<u8>,
+    read_pos: usize,
+
+    read_bytes_size: usize,
+    write_bytes_size: usize,
+    flush_num: usize,
+}
+
+impl<C: AbstractChannel> SimChannel<C> {
+    /// New a channel simulating `profile` over `inner`, with the simulated clock starting now.
+    pub fn new(inner: C, profile: NetworkProfile) -> Self {
+        Self {
+            inner,
+            profile,
+            clock: Duration::ZERO,
+            last: Instant::now(),
+            link_free: Duration::ZERO,
+            write_buffer: Vec::new(),
+            read_buffer: Vec::new(),
+            read_pos: 0,
+            read_bytes_size: 0,
+            write_bytes_size: 0,
+            flush_num: 0,
+        }
+    }
+
+    /// Return the simulated network profile.
+    #[inline]
+    pub fn profile(&self) -> NetworkProfile {
+        self.profile
+    }
+
+    /// Return the simulated wall time since the channel was created.
+    #[inline]
+    pub fn elapsed(&self) -> Duration {
+        self.clock + self.last.elapsed()
+    }
+
+    /// Restart the simulated clock, e.g., after a setup phase which is not measured. Both
+    /// parties must restart at the same point of the protocol.
+    pub fn reset_clock(&mut self) {
+        self.clock = Duration::ZERO;
+        self.last = Instant::now();
+        self.link_free = Duration::ZERO;
+    }
+
+    /// Return `write_bytes_size`
+    pub fn write_bytes_size(&self) -> usize {
+        self.write_bytes_size
+    }
+
+    /// Return `read_bytes_size`
+    pub fn read_bytes_size(&self) -> usize {
+        self.read_bytes_size
+    }
+
+    /// Return `flush_num`
+    pub fn flush_num(&self) -> usize {
+        self.flush_num
+    }
+
+    /// Return the inner channel.
+    #[inline]
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+
+    /// Advance the simulated clock by the real time since the last operation.
+    #[inline]
+    fn tick(&mut self) {
+        let now = Instant::now();
+        self.clock += now - self.last;
+        self.last = now;
+    }
+
+    /// Read the next message, and advance the simulated clock to its arrival.
+    fn read_message(&mut self) -> Result<()> {
+        self.tick();
+        let mut header = [0u8; 12];
+        self.inner.read_bytes(&mut header)?;
+        let arrival = Duration::from_nanos(u64::from_le_bytes(header[..8].try_into().unwrap()));
+        let len = u32::from_le_bytes(header[8..].try_into().unwrap()) as usize;
+        self.read_buffer.resize(len, 0);
+        self.read_pos = 0;
+        self.inner.read_bytes(&mut self.read_buffer)?;
+        // The time blocked on the inner channel is replaced by the simulated waiting time.
+        self.last = Instant::now();
+        self.clock = self.clock.max(arrival);
+        Ok(())
+    }
+}
+
+impl<C: AbstractChannel> AbstractChannel for SimChannel<C> {
+    #[inline]
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.write_buffer.extend_from_slice(bytes);
+        self.write_bytes_size += bytes.len();
+        Ok(())
+    }
+
+    fn read_bytes(&mut self, bytes: &mut [u8]) -> Result<()> {
+        let mut read = 0;
+        while read < bytes.len() {
+            if self.read_pos == self.read_buffer.len() {
+                self.read_message()?;
+            }
+            let n = (bytes.len() - read).min(self.read_buffer.len() - self.read_pos);
+            bytes[read..read + n]
+                .copy_from_slice(&self.read_buffer[self.read_pos..self.read_pos + n]);
+            self.read_pos += n;
+            read += n;
+        }
+        self.read_bytes_size += bytes.len();
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if !self.write_buffer.is_empty() {
+            self.tick();
+            let start = self.clock.max(self.link_free);
+            self.link_free = start + self.profile.transmission_time(self.write_buffer.len());
+            let arrival = self.link_free + self.profile.latency;
+
+            let mut header = [0u8; 12];
+            header[..8].copy_from_slice(&(arrival.as_nanos() as u64).to_le_bytes());
+            header[8..].copy_from_slice(&(self.write_buffer.len() as u32).to_le_bytes());
+            self.inner.write_bytes(&header)?;
+            self.inner.write_bytes(&self.write_buffer)?;
+            self.write_buffer.clear();
+        }
+        self.inner.flush()?;
+        self.flush_num += 1;
+        Ok(())
+    }
+}
+
+/// New a pair of in-process channels simulating `profile`.
+pub fn sim_channel_pair(
+    profile: NetworkProfile,
+) -> (SimChannel<LocalChannel>, SimChannel<LocalChannel>) {
+    let (sender, receiver) = local_channel_pair();
+    (
+        SimChannel::new(sender, profile),
+        SimChannel::new(receiver, profile),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::Block;
+
+    #[test]
+    fn sim_channel_test() {
+        // 10 ms one-way, 1 byte per microsecond.
+        let profile = NetworkProfile::new(Duration::from_millis(10), NonZeroU64::new(8_000_000));
+        assert_eq!(profile.transmission_time(1000), Duration::from_millis(1));
+        let (mut sender, mut receiver) = sim_channel_pair(profile);
+        let real = Instant::now();
+
+        let handle = thread::spawn(move || {
+            for _ in 0..5 {
+                let x = receiver.read_block().unwrap();
+                receiver.write_block(&x).unwrap();
+                receiver.flush().unwrap();
+            }
+            let mut bulk = vec![0u8; 100_000];
+            receiver.read_bytes(&mut bulk).unwrap();
+            receiver.elapsed()
+        });
+
+        // 5 round trips.
+        for i in 0..5u128 {
+            sender.write_block(&Block::from(i)).unwrap();
+            sender.flush().unwrap();
+            assert_eq!(sender.read_block().unwrap(), Block::from(i));
+        }
+        let rtt = sender.elapsed();
+        assert!(rtt >= Duration::from_millis(100));
+        // Then 100 ms to transmit and 10 ms to arrive.
+        sender.write_bytes(&vec![1u8; 100_000]).unwrap();
+        sender.flush().unwrap();
+        let recv = handle.join().unwrap();
+        assert!(recv >= rtt + Duration::from_millis(110));
+        assert!(recv < Duration::from_millis(210) + real.elapsed());
+        assert_eq!(sender.flush_num(), 6);
+    }
+}
@@ -0,0 +1,473 @@
+//! Transcript-recording channels.
+//!
+//! `TranscriptChannel` tees every byte written to and read from an inner channel. It keeps a
+//! running hash of the transcript and, optionally, the full log. Both are tagged by direction and
+//! by the protocol phase set with `set_phase`. The log can be saved to a file and fed back into a
+//! single party by `ReplayChannel`.
+//!
+//! Within a phase, the bytes sent by the server and the bytes sent by the client are hashed as
+//! two separate streams. The hash absorbs, for each phase with traffic, its label and the hashes
+//! of both streams, so it depends neither on how the bytes were split into calls nor on how a
+//! party interleaved its writes and reads, e.g., when both parties send in the same round. As
+//! the streams are those of the server and the client, rather than this party and the peer, both
+//! parties of a run compute the same hash if they switch phases at the same points of the
+//! protocol. The hash can thus bind later protocol steps to everything exchanged so far.
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Write},
+    path::Path,
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::{AbstractChannel, RandomOracle};
+
+const TRANSCRIPT_TAG: &str = "zkOracles/transcript";
+const TRANSCRIPT_MAGIC: &[u8; 8] = b"zkOTrsc1";
+
+/// Direction of transcript bytes, as seen by the recording party.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// A segment of a transcript.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TranscriptEntry {
+    pub phase: String,
+    pub direction: Direction,
+    pub bytes: Vec<u8>,
+}
+
+/// Full log of the transcript of one party.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Transcript {
+    pub entries: Vec<TranscriptEntry>,
+}
+
+impl Transcript {
+    /// New an empty transcript.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append `bytes`, merged into the last entry if it has the same phase and direction.
+    pub fn push(&mut self, phase: &str, direction: Direction, bytes: &[u8]) {
+        match self.entries.last_mut() {
+            Some(last) if last.phase == phase && last.direction == direction => {
+                last.bytes.extend_from_slice(bytes)
+            }
+            _ => self.entries.push(TranscriptEntry {
+                phase: phase.to_string(),
+                direction,
+                bytes: bytes.to_vec(),
+            }),
+        }
+    }
+
+    /// Return the total number of bytes in `direction`.
+    pub fn len(&self, direction: Direction) -> usize {
+        self.entries
+            .iter()
+            .filter(|e| e.direction == direction)
+            .map(|e| e.bytes.len())
+            .sum()
+    }
+
+    /// Write the transcript to `writer`.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(TRANSCRIPT_MAGIC)?;
+        writer.write_all(&(self.entries.len() as u64).to_le_bytes())?;
+        for entry in &self.entries {
+            let direction = match entry.direction {
+                Direction::Sent => 0u8,
+                Direction::Received => 1u8,
+            };
+            writer.write_all(&[direction])?;
+            writer.write_all(&(entry.phase.len() as u64).to_le_bytes())?;
+            writer.write_all(entry.phase.as_bytes())?;
+            writer.write_all(&(entry.bytes.len() as u64).to_le_bytes())?;
+            writer.write_all(&entry.bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Read a transcript written by `write_to` from `reader`.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != TRANSCRIPT_MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "not a transcript"));
+        }
+        let n = read_u64(reader)?;
+        let mut entries = Vec::new();
+        for _ in 0..n {
+            let mut direction = [0u8; 1];
+            reader.read_exact(&mut direction)?;
+            let direction = match direction[0] {
+                0 => Direction::Sent,
+                1 => Direction::Received,
+                _ => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        "invalid transcript direction",
+                    ))
+                }
+            };
+            let phase = String::from_utf8(read_vec(reader)?)
+                .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+            let bytes = read_vec(reader)?;
+            entries.push(TranscriptEntry {
+                phase,
+                direction,
+                bytes,
+            });
+        }
+        Ok(Self { entries })
+    }
+
+    /// Save the transcript to the file at `path`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Load a transcript from the file at `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::read_from(&mut BufReader::new(File::open(path)?))
+    }
+}
+
+#[inline]
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[inline]
+fn read_vec<R: Read>(reader: &mut R) -> Result<Vec<u8>> {
+    let len = read_u64(reader)?;
+    let mut bytes = Vec::new();
+    reader.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "truncated transcript"));
+    }
+    Ok(bytes)
+}
+
+/// A channel recording the transcript of an inner channel.
+pub struct TranscriptChannel<C> {
+    inner: C,
+    is_server: bool,
+    phase: String,
+    oracle: RandomOracle,
+    // Hashes of the bytes sent by the client and by the server in the current phase, if any.
+    streams: Option<[Sha256; 2]>,
+    log: Option<Transcript>,
+}
+
+impl<C: AbstractChannel> TranscriptChannel<C> {
+    /// New a channel keeping the running hash of the transcript of `inner`. `is_server` tells
+    /// which party this is, the peer must use the opposite.
+    pub fn new(inner: C, is_server: bool) -> Self {
+        Self {
+            inner,
+            is_server,
+            phase: String::new(),
+            oracle: RandomOracle::new(TRANSCRIPT_TAG),
+            streams: None,
+            log: None,
+        }
+    }
+
+    /// New a channel keeping both the running hash and the full log of the transcript.
+    pub fn with_log(inner: C, is_server: bool) -> Self {
+        let mut channel = Self::new(inner, is_server);
+        channel.log = Some(Transcript::new());
+        channel
+    }
+
+    /// Tag the following bytes with the protocol phase `label`.
+    pub fn set_phase(&mut self, label: &str) {
+        if self.phase != label {
+            self.close_phase();
+            self.phase = label.to_string();
+        }
+    }
+
+    /// Return the current protocol phase.
+    #[inline]
+    pub fn phase(&self) -> &str {
+        &self.phase
+    }
+
+    /// Return the hash of the transcript so far.
+    pub fn hash(&self) -> [u8; 32] {
+        let mut oracle = self.oracle.clone();
+        if let Some(streams) = &self.streams {
+            self.absorb(&mut oracle, streams.clone());
+        }
+        oracle.finalize()
+    }
+
+    /// Return the log of the transcript, if it is kept.
+    #[inline]
+    pub fn transcript(&self) -> Option<&Transcript> {
+        self.log.as_ref()
+    }
+
+    /// Save the log of the transcript to the file at `path`.
+    pub fn dump<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        match &self.log {
+            Some(log) => log.save(path),
+            None => Err(Error::new(
+                ErrorKind::InvalidInput,
+                "the transcript log is not kept",
+            )),
+        }
+    }
+
+    /// Return a reference to the inner channel.
+    #[inline]
+    pub fn get_ref(&self) -> &C {
+        &self.inner
+    }
+
+    /// Return the inner channel.
+    #[inline]
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+
+    fn record(&mut self, direction: Direction, bytes: &[u8]) {
+        if bytes.is_empty() {
+            return;
+        }
+        let from_server = (direction == Direction::Sent) == self.is_server;
+        self.streams.get_or_insert_with(Default::default)[from_server as usize].update(bytes);
+        if let Some(log) = &mut self.log {
+            log.push(&self.phase, direction, bytes);
+        }
+    }
+
+    fn close_phase(&mut self) {
+        if let Some(streams) = self.streams.take() {
+            let mut oracle = self.oracle.clone();
+            self.absorb(&mut oracle, streams);
+            self.oracle = oracle;
+        }
+    }
+
+    fn absorb(&self, oracle: &mut RandomOracle, [client, server]: [Sha256; 2]) {
+        oracle
+            .update(self.phase.as_bytes())
+            .update(&server.finalize())
+            .update(&client.finalize());
+    }
+}
+
+impl<C: AbstractChannel> AbstractChannel for TranscriptChannel<C> {
+    #[inline]
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.inner.write_bytes(bytes)?;
+        self.record(Direction::Sent, bytes);
+        Ok(())
+    }
+
+    #[inline]
+    fn read_bytes(&mut self, bytes: &mut [u8]) -> Result<()> {
+        self.inner.read_bytes(bytes)?;
+        self.record(Direction::Received, bytes);
+        Ok(())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A channel replaying the recorded transcript of one party, without a peer.
+///
+/// Reads return the received bytes of the transcript, and writes are checked against its sent
+/// bytes, so a run diverging from the recording fails with `ErrorKind::InvalidData`. The party
+/// must be run with the same inputs and the same seeded randomness as in the recording.
+pub struct ReplayChannel {
+    entries: Vec<TranscriptEntry>,
+    index: usize,
+    offset: usize,
+}
+
+impl ReplayChannel {
+    /// New a channel replaying `transcript`.
+    pub fn new(transcript: Transcript) -> Self {
+        let entries = transcript
+            .entries
+            .into_iter()
+            .filter(|e| !e.bytes.is_empty())
+            .collect();
+        Self {
+            entries,
+            index: 0,
+            offset: 0,
+        }
+    }
+
+    /// New a channel replaying the transcript saved in the file at `path`.
+    #[inline]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::new(Transcript::load(path)?))
+    }
+
+    /// Return true if the whole transcript has been replayed.
+    #[inline]
+    pub fn is_done(&self) -> bool {
+        self.index == self.entries.len()
+    }
+
+    /// Return the next `len` bytes of the current entry, which must be in `direction`.
+    fn next_bytes(&mut self, direction: Direction, len: usize) -> Result<&[u8]> {
+        let entry = self
+            .entries
+            .get(self.index)
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "end of the transcript"))?;
+        if entry.direction != direction {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "diverged from the transcript in phase {:?}: expected {:?} bytes",
+                    entry.phase, entry.direction
+                ),
+            ));
+        }
+        let start = self.offset;
+        let end = (start + len).min(entry.bytes.len());
+        if end == entry.bytes.len() {
+            self.index += 1;
+            self.offset = 0;
+        } else {
+            self.offset = end;
+        }
+        Ok(&entry.bytes[start..end])
+    }
+}
+
+impl AbstractChannel for ReplayChannel {
+    fn write_bytes(&mut self, mut bytes: &[u8]) -> Result<()> {
+        while !bytes.is_empty() {
+            let index = self.index;
+            let expected = self.next_bytes(Direction::Sent, bytes.len())?;
+            let n = expected.len();
+            if expected != &bytes[..n] {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "diverged from the transcript in phase {:?}: different bytes sent",
+                        self.entries[index].phase
+                    ),
+                ));
+            }
+            bytes = &bytes[n..];
+        }
+        Ok(())
+    }
+
+    fn read_bytes(&mut self, mut bytes: &mut [u8]) -> Result<()> {
+        while !bytes.is_empty() {
+            let recorded = self.next_bytes(Direction::Received, bytes.len())?;
+            let n = recorded.len();
+            bytes[..n].copy_from_slice(recorded);
+            bytes = &mut bytes[n..];
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::{
+        cointoss::{cointoss_receive, cointoss_send},
+        local_channel_pair, AesRng, Block,
+    };
+
+    #[test]
+    fn transcript_channel_test() {
+        let (sender, receiver) = local_channel_pair();
+        let seed = rand::random::<Block>();
+
+        let handle = thread::spawn(move || {
+            let mut channel = TranscriptChannel::new(receiver, false);
+            channel.set_phase("cointoss");
+            let mut rng = AesRng::new();
+            let out = cointoss_receive(&mut channel, &mut rng).unwrap();
+            channel.set_phase("echo");
+            let x = channel.read_block().unwrap();
+            channel.write_bytes(x.as_ref()).unwrap();
+            channel.flush().unwrap();
+            // Both parties send in the same round, but record in opposite orders.
+            channel.set_phase("swap");
+            channel.write_block(&Block::from(2u128)).unwrap();
+            channel.flush().unwrap();
+            assert_eq!(channel.read_block().unwrap(), Block::from(1u128));
+            (out, channel.hash())
+        });
+
+        let mut channel = TranscriptChannel::with_log(sender, true);
+        channel.set_phase("cointoss");
+        let mut rng = AesRng::from_seed(seed);
+        let out = cointoss_send(&mut channel, &mut rng).unwrap();
+        channel.set_phase("echo");
+        // Written as one call, read back in two.
+        channel.write_block(&Block::from(7u128)).unwrap();
+        channel.flush().unwrap();
+        let mut echo = [0u8; 16];
+        channel.read_bytes(&mut echo[..5]).unwrap();
+        channel.read_bytes(&mut echo[5..]).unwrap();
+        assert_eq!(Block::try_from_slice(&echo).unwrap(), Block::from(7u128));
+        channel.set_phase("swap");
+        channel.write_block(&Block::from(1u128)).unwrap();
+        channel.flush().unwrap();
+        assert_eq!(channel.read_block().unwrap(), Block::from(2u128));
+
+        let (peer_out, peer_hash) = handle.join().unwrap();
+        assert_eq!(out, peer_out);
+        assert_eq!(channel.hash(), peer_hash);
+
+        let log = channel.transcript().unwrap();
+        assert_eq!(log.len(Direction::Sent), 32 + 32 + 16 + 16);
+        assert_eq!(log.len(Direction::Received), 32 + 32 + 16 + 16);
+        assert_eq!(log.entries.last().unwrap().phase, "swap");
+
+        // Save, load and replay the sender alone.
+        let path = std::env::temp_dir().join(format!("transcript-{}.bin", std::process::id()));
+        channel.dump(&path).unwrap();
+        let mut replay = ReplayChannel::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let mut rng = AesRng::from_seed(seed);
+        assert_eq!(cointoss_send(&mut replay, &mut rng).unwrap(), out);
+        assert!(!replay.is_done());
+
+        // Other randomness diverges from the recording.
+        let mut replay = ReplayChannel::new(log.clone());
+        let err = cointoss_send(&mut replay, &mut AesRng::new()).unwrap_err();
+        assert!(matches!(
+            err,
+            crate::cointoss::CointossError::IoError(e) if e.kind() == ErrorKind::InvalidData
+        ));
+    }
+}
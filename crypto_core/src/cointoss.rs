@@ -0,0 +1,131 @@
+//! Two-party coin-tossing protocol π_RAND (cf. `doc/src/primitives/ote.md`).
+//!
+//! Both parties commit to a random seed, exchange the commitments, open them and output the
+//! XOR of the seeds. The party running `cointoss_send` commits first, and the party running
+//! `cointoss_receive` opens last, after checking the opening of the first party.
+
+use rand::{CryptoRng, Rng, SeedableRng};
+
+use crate::{AbstractChannel, AesRng, Block, Commitment};
+
+/// Errors of the coin-tossing protocol.
+#[derive(Debug, thiserror::Error)]
+pub enum CointossError {
+    #[error("Cointoss IO Error")]
+    IoError(std::io::Error),
+
+    #[error("Cointoss Commitment Check Failed")]
+    CommitmentCheckFailed,
+}
+
+impl From<std::io::Error> for CointossError {
+    fn from(e: std::io::Error) -> CointossError {
+        CointossError::IoError(e)
+    }
+}
+
+#[inline]
+fn read_commitment<C: AbstractChannel>(channel: &mut C) -> std::io::Result<Commitment> {
+    let mut com = [0u8; 32];
+    channel.read_bytes(&mut com)?;
+    Ok(Commitment(com))
+}
+
+/// Run π_RAND as the party who commits first, and return the shared seed.
+pub fn cointoss_send<C: AbstractChannel, R: CryptoRng + Rng>(
+    channel: &mut C,
+    rng: &mut R,
+) -> Result<Block, CointossError> {
+    let s0 = rng.gen::<Block>();
+    let r0 = rng.gen::<Block>();
+    channel.write_bytes(Commitment::commit(s0.as_ref(), &r0).as_ref())?;
+    channel.flush()?;
+
+    let c1 = read_commitment(channel)?;
+    channel.write_block(&s0)?;
+    channel.write_block(&r0)?;
+    channel.flush()?;
+
+    let s1 = channel.read_block()?;
+    let r1 = channel.read_block()?;
+    if !c1.open(s1.as_ref(), &r1) {
+        return Err(CointossError::CommitmentCheckFailed);
+    }
+    Ok(s0 ^ s1)
+}
+
+/// Run π_RAND as the party who commits second, and return the shared seed.
+pub fn cointoss_receive<C: AbstractChannel, R: CryptoRng + Rng>(
+    channel: &mut C,
+    rng: &mut R,
+) -> Result<Block, CointossError> {
+    let s1 = rng.gen::<Block>();
+    let r1 = rng.gen::<Block>();
+
+    let c0 = read_commitment(channel)?;
+    channel.write_bytes(Commitment::commit(s1.as_ref(), &r1).as_ref())?;
+    channel.flush()?;
+
+    let s0 = channel.read_block()?;
+    let r0 = channel.read_block()?;
+    if !c0.open(s0.as_ref(), &r0) {
+        return Err(CointossError::CommitmentCheckFailed);
+    }
+    channel.write_block(&s1)?;
+    channel.write_block(&r1)?;
+    channel.flush()?;
+    Ok(s0 ^ s1)
+}
+
+/// Generate `n` challenges `χ_1, ..., χ_n` from the shared seed.
+#[inline]
+pub fn challenges(seed: Block, n: usize) -> Vec<Block> {
+    let mut rng = AesRng::from_seed(seed);
+    (0..n).map(|_| rng.gen::<Block>()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::local_channel_pair;
+
+    #[test]
+    fn cointoss_test() {
+        let (mut sender, mut receiver) = local_channel_pair();
+        let handle = thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let seed = cointoss_send(&mut sender, &mut rng).unwrap();
+            (seed, challenges(seed, 10))
+        });
+
+        let mut rng = AesRng::new();
+        let seed = cointoss_receive(&mut receiver, &mut rng).unwrap();
+        let (seed_, chis) = handle.join().unwrap();
+        assert_eq!(seed, seed_);
+        assert_eq!(challenges(seed, 10), chis);
+    }
+
+    #[test]
+    fn cointoss_abort_test() {
+        // The first party opens to a different seed than it committed to.
+        let (mut sender, mut receiver) = local_channel_pair();
+        let handle = thread::spawn(move || {
+            let r0 = rand::random::<Block>();
+            sender
+                .write_bytes(Commitment::commit(Block::default().as_ref(), &r0).as_ref())
+                .unwrap();
+            sender.flush().unwrap();
+            read_commitment(&mut sender).unwrap();
+            sender.write_block(&Block::from(1u128)).unwrap();
+            sender.write_block(&r0).unwrap();
+            sender.flush().unwrap();
+        });
+
+        let mut rng = AesRng::new();
+        let res = cointoss_receive(&mut receiver, &mut rng);
+        assert!(matches!(res, Err(CointossError::CommitmentCheckFailed)));
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,48 @@
+//! Hash-based commitment `Com(m; r) = H(m, r)`, where `H` is the random oracle and `r` is a
+//! random block.
+
+use crate::{Block, RandomOracle};
+
+/// Domain tag of the commitment.
+const COMMIT_TAG: &str = "zkOracles/commitment";
+
+/// Hash commitment to a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Commitment(pub [u8; 32]);
+
+impl Commitment {
+    /// Commit to `msg` with randomness `r`.
+    #[inline]
+    pub fn commit(msg: &[u8], r: &Block) -> Self {
+        let mut ro = RandomOracle::new(COMMIT_TAG);
+        ro.update(msg).update_block(r);
+        Commitment(ro.finalize())
+    }
+
+    /// Check the opening `(msg, r)` of the commitment.
+    #[inline]
+    pub fn open(&self, msg: &[u8], r: &Block) -> bool {
+        Commitment::commit(msg, r) == *self
+    }
+}
+
+impl AsRef<[u8]> for Commitment {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commitment_test() {
+        let r = rand::random::<Block>();
+        let com = Commitment::commit(b"message", &r);
+        assert!(com.open(b"message", &r));
+        assert!(!com.open(b"massage", &r));
+        assert!(!com.open(b"message", &rand::random::<Block>()));
+    }
+}
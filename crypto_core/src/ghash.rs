@@ -0,0 +1,219 @@
+//! GHASH universal hash of AES-GCM (cf. NIST SP 800-38D, §6.4 and
+//! `doc/src/zkoracles/aes-gcm.md`).
+//!
+//! All blocks are in the byte and bit order of GCM, i.e., a block holds the 16 bytes of the
+//! specification, and the multiplication is `Block::gf_mul_gcm`.
+
+use crate::block::Block;
+
+/// Incremental computation of `GHASH_H`.
+///
+/// Bytes may be absorbed in pieces of any length. `pad` completes the current block with
+/// zeros, which gives the `0^v` and `0^u` paddings of the specification.
+#[derive(Clone)]
+pub struct Ghash {
+    h: Block,
+    state: Block,
+    buffer: [u8; 16],
+    nbuffered: usize,
+}
+
+impl Ghash {
+    /// New a GHASH with key `h`.
+    pub fn new(h: Block) -> Self {
+        Self {
+            h,
+            state: Block::default(),
+            buffer: [0u8; 16],
+            nbuffered: 0,
+        }
+    }
+
+    /// Absorb a complete block. Pending bytes are padded first.
+    #[inline]
+    pub fn update_block(&mut self, x: Block) {
+        self.pad();
+        self.state = (self.state ^ x).gf_mul_gcm(self.h);
+    }
+
+    /// Absorb `data`, keeping the bytes of an incomplete block for the next call.
+    pub fn update(&mut self, data: &[u8]) {
+        let mut data = data;
+        if self.nbuffered > 0 {
+            let n = (16 - self.nbuffered).min(data.len());
+            self.buffer[self.nbuffered..self.nbuffered + n].copy_from_slice(&data[..n]);
+            self.nbuffered += n;
+            data = &data[n..];
+            if self.nbuffered < 16 {
+                return;
+            }
+            self.pad();
+        }
+
+        let mut chunks = data.chunks_exact(16);
+        for chunk in &mut chunks {
+            self.update_block(Block::try_from_slice(chunk).unwrap());
+        }
+        let rest = chunks.remainder();
+        self.buffer[..rest.len()].copy_from_slice(rest);
+        self.nbuffered = rest.len();
+    }
+
+    /// Complete the pending bytes, if any, to a block with zeros and absorb it.
+    #[inline]
+    pub fn pad(&mut self) {
+        if self.nbuffered > 0 {
+            self.buffer[self.nbuffered..].fill(0);
+            self.nbuffered = 0;
+            self.state = (self.state ^ Block::from(self.buffer)).gf_mul_gcm(self.h);
+        }
+    }
+
+    /// Absorb the length block `[len(A)]_64 || [len(C)]_64`, where the lengths are given in
+    /// bytes, and return the hash.
+    pub fn finalize_with_lengths(mut self, aad_len: usize, ciphertext_len: usize) -> Block {
+        self.update_block(length_block(aad_len, ciphertext_len));
+        self.state
+    }
+
+    /// Pad the pending bytes and return the hash.
+    pub fn finalize(mut self) -> Block {
+        self.pad();
+        self.state
+    }
+}
+
+/// Return the length block `[len(A)]_64 || [len(C)]_64`, where the lengths are given in bytes.
+#[inline]
+pub fn length_block(aad_len: usize, ciphertext_len: usize) -> Block {
+    let mut bytes = [0u8; 16];
+    bytes[..8].copy_from_slice(&(8 * aad_len as u64).to_be_bytes());
+    bytes[8..].copy_from_slice(&(8 * ciphertext_len as u64).to_be_bytes());
+    Block::from(bytes)
+}
+
+/// Compute `S = GHASH_H(A || 0^v || C || 0^u || [len(A)]_64 || [len(C)]_64)` of AES-GCM.
+/// The tag is `S` xor the encryption of `J_0`.
+pub fn ghash_gcm(h: Block, aad: &[u8], ciphertext: &[u8]) -> Block {
+    let mut ghash = Ghash::new(h);
+    ghash.update(aad);
+    ghash.pad();
+    ghash.update(ciphertext);
+    ghash.pad();
+    ghash.finalize_with_lengths(aad.len(), ciphertext.len())
+}
+
+/// Return the blocks `X_1, ..., X_m` of `A || 0^v || C || 0^u || [len(A)]_64 || [len(C)]_64`,
+/// to be hashed with `ghash_from_powers`.
+pub fn gcm_blocks(aad: &[u8], ciphertext: &[u8]) -> Vec<Block> {
+    let mut blocks: Vec<Block> = Vec::with_capacity((aad.len() + ciphertext.len()) / 16 + 3);
+    for data in [aad, ciphertext] {
+        blocks.extend(data.chunks(16).map(|chunk| {
+            let mut bytes = [0u8; 16];
+            bytes[..chunk.len()].copy_from_slice(chunk);
+            Block::from(bytes)
+        }));
+    }
+    blocks.push(length_block(aad.len(), ciphertext.len()));
+    blocks
+}
+
+/// Return the powers `H^1, ..., H^n`.
+pub fn h_powers(h: Block, n: usize) -> Vec<Block> {
+    let mut powers = Vec::with_capacity(n);
+    let mut power = h;
+    for _ in 0..n {
+        powers.push(power);
+        power = power.gf_mul_gcm(h);
+    }
+    powers
+}
+
+/// Compute `X_1 * H^m ^ X_2 * H^(m-1) ^ ... ^ X_m * H` from the table `powers[i] = H^(i+1)`,
+/// which has at least `m` entries. The table may also be a share of the powers, since the
+/// hash is linear in them.
+pub fn ghash_from_powers(powers: &[Block], blocks: &[Block]) -> Block {
+    let m = blocks.len();
+    assert!(powers.len() >= m, "not enough powers of H");
+
+    let xs: Vec<Block> = blocks.iter().map(|x| x.reflect()).collect();
+    let hs: Vec<Block> = powers[..m].iter().rev().map(|h| h.reflect()).collect();
+    Block::gf_inner_product(&xs, &hs).reflect()
+}
+
+#[cfg(test)]
+mod tests {
+    use aes::cipher::{generic_array::GenericArray, BlockEncrypt, KeyInit};
+    use aes::Aes128;
+
+    use super::*;
+
+    fn aes_encrypt(key: &[u8], x: Block) -> Block {
+        let aes = Aes128::new(GenericArray::from_slice(key));
+        let mut x = GenericArray::from(<[u8; 16]>::from(x));
+        aes.encrypt_block(&mut x);
+        Block::try_from_slice(x.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn ghash_gcm_test() {
+        // NIST GCM test case 4 (McGrew and Viega, "The Galois/Counter Mode of Operation")
+        let key = hex::decode("feffe9928665731c6d6a8f9467308308").unwrap();
+        let iv = hex::decode("cafebabefacedbaddecaf888").unwrap();
+        let aad = hex::decode("feedfacedeadbeeffeedfacedeadbeefabaddad2").unwrap();
+        let ciphertext = hex::decode(
+            "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e\
+             21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091",
+        )
+        .unwrap();
+
+        let h = aes_encrypt(&key, Block::default());
+        assert_eq!(
+            <[u8; 16]>::from(h).to_vec(),
+            hex::decode("b83b533708bf535d0aa6e52980d53b78").unwrap()
+        );
+
+        let s = ghash_gcm(h, &aad, &ciphertext);
+        assert_eq!(
+            <[u8; 16]>::from(s).to_vec(),
+            hex::decode("698e57f70e6ecc7fd9463b7260a9ae5f").unwrap()
+        );
+
+        let mut j0 = [0u8; 16];
+        j0[..12].copy_from_slice(&iv);
+        j0[15] = 1;
+        let tag = aes_encrypt(&key, Block::from(j0)) ^ s;
+        assert_eq!(
+            <[u8; 16]>::from(tag).to_vec(),
+            hex::decode("5bc94fbc3221a5db94fae95ae7121a47").unwrap()
+        );
+
+        // Absorbing the data in odd pieces gives the same hash.
+        let mut ghash = Ghash::new(h);
+        for chunk in aad.chunks(7) {
+            ghash.update(chunk);
+        }
+        ghash.pad();
+        for chunk in ciphertext.chunks(5) {
+            ghash.update(chunk);
+        }
+        ghash.pad();
+        assert_eq!(ghash.finalize_with_lengths(aad.len(), ciphertext.len()), s);
+
+        // So does the hash from a table of powers, and from XOR shares of the table.
+        let blocks = gcm_blocks(&aad, &ciphertext);
+        let powers = h_powers(h, blocks.len() + 2);
+        assert_eq!(ghash_from_powers(&powers, &blocks), s);
+
+        let shares_0: Vec<Block> = (0..powers.len()).map(|_| rand::random::<Block>()).collect();
+        let shares_1: Vec<Block> = shares_0
+            .iter()
+            .zip(powers.iter())
+            .map(|(a, b)| *a ^ *b)
+            .collect();
+        assert_eq!(
+            ghash_from_powers(&shares_0, &blocks) ^ ghash_from_powers(&shares_1, &blocks),
+            s
+        );
+    }
+}
@@ -3,10 +3,12 @@
 
 //! The code is derived from Swanky https://github.com/GaloisInc/swanky.
 
-use crate::block::Block;
-use aes::cipher::{generic_array::GenericArray, BlockEncrypt, KeyInit};
+use crate::{block::Block, secret::wipe_aes};
+use aes::cipher::{
+    generic_array::{typenum::U16, GenericArray},
+    BlockEncrypt, KeyInit,
+};
 use aes::Aes128;
-use core::arch::x86_64::*;
 use once_cell::sync::Lazy;
 /// AES-based correlation-robust hash function.
 ///
@@ -16,17 +18,24 @@ pub struct AesHash {
     aes: Aes128,
 }
 
-/// `AesHash` with a fixed key.
+// `AesHash` with a fixed key.
 //pub const AES_HASH: AesHash = AesHash {
 //    aes: FIXED_KEY_AES128,
 //};
 
+/// `AesHash` with a fixed key.
 pub static AES_HASH: Lazy<AesHash> = Lazy::new(|| {
     let key = GenericArray::from([0u8; 16]);
     let aes = Aes128::new(&key);
     AesHash { aes }
 });
 
+impl Drop for AesHash {
+    fn drop(&mut self) {
+        wipe_aes(&mut self.aes);
+    }
+}
+
 impl AesHash {
     /// Initialize the hash function using `key`.
     #[inline]
@@ -57,14 +66,7 @@ impl AesHash {
     /// function and `σ(x₀ || x₁) = (x₀ ⊕ x₁) || x₁`.
     #[inline]
     pub fn ccr_hash(&self, i: Block, x: Block) -> Block {
-        unsafe {
-            let x = _mm_xor_si128(
-                _mm_shuffle_epi32(x.into(), 78),
-                #[allow(overflowing_literals)]
-                _mm_and_si128(x.into(), _mm_set_epi64x(0xFFFF_FFFF_FFFF_FFFF, 0x00)),
-            );
-            self.cr_hash(i, Block::from(x))
-        }
+        self.cr_hash(i, x.sigma())
     }
 
     /// Tweakable circular correlation robust hash function (cf.
@@ -86,4 +88,79 @@ impl AesHash {
         let z = Block::try_from_slice(z.as_slice()).unwrap();
         y ^ z
     }
+
+    /// Batched tweakable circular correlation robust hash function, which computes
+    /// `out[k] = tccr_hash(tweaks[k], xs[k])`.
+    ///
+    /// Chunks of eight blocks go through AES together, so that the AES-NI pipeline stays full.
+    /// The AES backend only pipelines full chunks, so the last `xs.len() % 8` blocks are hashed
+    /// one by one, which is faster than a partial chunk (see the `hash_aes` benches).
+    #[inline]
+    pub fn tccr_hash_many(&self, tweaks: &[Block], xs: &[Block], out: &mut [Block]) {
+        assert_eq!(tweaks.len(), xs.len());
+        assert_eq!(xs.len(), out.len());
+
+        let mut ys = [GenericArray::<u8, U16>::default(); 8];
+        let mut zs = [GenericArray::<u8, U16>::default(); 8];
+        let n = xs.len() - xs.len() % 8;
+        for ((tweaks, xs), out) in tweaks[..n]
+            .chunks_exact(8)
+            .zip(xs[..n].chunks_exact(8))
+            .zip(out[..n].chunks_exact_mut(8))
+        {
+            for (y, x) in ys.iter_mut().zip(xs.iter()) {
+                *y = GenericArray::from(<[u8; 16]>::from(*x));
+            }
+            self.aes.encrypt_blocks(&mut ys);
+
+            for ((z, y), i) in zs.iter_mut().zip(ys.iter()).zip(tweaks.iter()) {
+                let t = Block::try_from_slice(y.as_slice()).unwrap() ^ *i;
+                *z = GenericArray::from(<[u8; 16]>::from(t));
+            }
+            self.aes.encrypt_blocks(&mut zs);
+
+            for ((o, y), z) in out.iter_mut().zip(ys.iter()).zip(zs.iter()) {
+                *o = Block::try_from_slice(y.as_slice()).unwrap()
+                    ^ Block::try_from_slice(z.as_slice()).unwrap();
+            }
+        }
+        for ((o, i), x) in out[n..]
+            .iter_mut()
+            .zip(tweaks[n..].iter())
+            .zip(xs[n..].iter())
+        {
+            *o = self.tccr_hash(*i, *x);
+        }
+    }
+
+    /// Compute `tccr_hash` of eight blocks in parallel.
+    #[inline]
+    pub fn tccr_hash8(&self, tweaks: [Block; 8], xs: [Block; 8]) -> [Block; 8] {
+        let mut out = [Block::default(); 8];
+        self.tccr_hash_many(&tweaks, &xs, &mut out);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Block, AES_HASH};
+
+    #[test]
+    fn tccr_hash_many_test() {
+        let tweaks: Vec<Block> = (0..19).map(|_| rand::random::<Block>()).collect();
+        let xs: Vec<Block> = (0..19).map(|_| rand::random::<Block>()).collect();
+        let expected: Vec<Block> = tweaks
+            .iter()
+            .zip(xs.iter())
+            .map(|(i, x)| AES_HASH.tccr_hash(*i, *x))
+            .collect();
+
+        let mut out = vec![Block::default(); 19];
+        AES_HASH.tccr_hash_many(&tweaks, &xs, &mut out);
+        assert_eq!(out, expected);
+
+        let out = AES_HASH.tccr_hash8(tweaks[..8].try_into().unwrap(), xs[..8].try_into().unwrap());
+        assert_eq!(out[..], expected[..8]);
+    }
 }
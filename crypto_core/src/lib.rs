@@ -1,13 +1,26 @@
+pub mod bitvec;
 pub mod block;
 pub mod channel;
+pub mod cointoss;
+pub mod commitment;
+pub mod ghash;
 pub mod hash_aes;
+pub mod prg;
 pub mod rand_aes;
+pub mod random_oracle;
+pub mod secret;
+pub mod transpose;
 pub mod utils;
 
 pub use crate::{
+    bitvec::BitVec,
     block::Block,
+    commitment::Commitment,
     hash_aes::{AesHash, AES_HASH},
+    prg::{Prg, PrgError},
     rand_aes::AesRng,
+    random_oracle::RandomOracle,
+    secret::SecretBlock,
 };
 
 pub use channel::*;
@@ -0,0 +1,255 @@
+//! Seekable AES-based pseudorandom generator.
+//!
+//! Block `i` of stream `s` under seed `k` is `AES_k(0 || s || i)`, so the output at any offset
+//! is computed directly. Child seeds are derived as `AES_k(1 || s || id)`, which is disjoint
+//! from the output of every stream.
+
+use aes::cipher::{
+    generic_array::{typenum::U16, GenericArray},
+    BlockEncrypt, KeyInit,
+};
+use aes::Aes128;
+use rand::{CryptoRng, Error, RngCore, SeedableRng};
+use zeroize::Zeroize;
+
+use crate::{secret::wipe_aes, Block};
+
+/// Domain separation bit of the child seeds.
+const CHILD_DOMAIN: u128 = 1 << 127;
+
+/// Largest stream id, as the top bit of the counter block separates the child seeds.
+pub const MAX_STREAM: u64 = (1 << 63) - 1;
+
+/// Errors of the PRG.
+#[derive(Debug, thiserror::Error)]
+pub enum PrgError {
+    #[error("Stream id {0} is larger than MAX_STREAM")]
+    StreamOutOfRange(u64),
+}
+
+/// Seekable pseudorandom generator keyed by a `Block` seed and a stream id.
+#[derive(Clone)]
+pub struct Prg {
+    aes: Aes128,
+    stream: u64,
+    counter: u64,
+    buffer: [u8; 16],
+    nbuffered: usize,
+}
+
+impl std::fmt::Debug for Prg {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Prg {{ stream: {}, counter: {} }}",
+            self.stream, self.counter
+        )
+    }
+}
+
+impl Prg {
+    /// New a PRG with `seed` and stream id `stream`, which must be at most `MAX_STREAM`.
+    #[inline]
+    pub fn new(seed: Block, stream: u64) -> Result<Self, PrgError> {
+        if stream > MAX_STREAM {
+            return Err(PrgError::StreamOutOfRange(stream));
+        }
+        Ok(Self::new_unchecked(seed, stream))
+    }
+
+    #[inline]
+    fn new_unchecked(seed: Block, stream: u64) -> Self {
+        let key: [u8; 16] = seed.into();
+        Prg {
+            aes: Aes128::new(&GenericArray::from(key)),
+            stream,
+            counter: 0,
+            buffer: [0u8; 16],
+            nbuffered: 0,
+        }
+    }
+
+    /// Return the stream id.
+    #[inline]
+    pub fn stream(&self) -> u64 {
+        self.stream
+    }
+
+    /// Return the index of the next output block.
+    #[inline]
+    pub fn counter(&self) -> u64 {
+        self.counter
+    }
+
+    /// Move to output block `counter`, discarding the buffered bytes.
+    #[inline]
+    pub fn seek(&mut self, counter: u64) {
+        self.counter = counter;
+        self.nbuffered = 0;
+    }
+
+    #[inline]
+    fn counter_block(&self, domain: u128, index: u64) -> GenericArray<u8, U16> {
+        let x = domain | (self.stream as u128) << 64 | index as u128;
+        GenericArray::from(<[u8; 16]>::from(Block::from(x)))
+    }
+
+    /// Fill `blocks` with the next output blocks. Buffered bytes from `fill_bytes` are skipped.
+    pub fn fill_blocks(&mut self, blocks: &mut [Block]) {
+        self.nbuffered = 0;
+        let mut ms = [GenericArray::<u8, U16>::default(); 8];
+        for chunk in blocks.chunks_mut(8) {
+            let n = chunk.len();
+            for (k, m) in ms[..n].iter_mut().enumerate() {
+                *m = self.counter_block(0, self.counter + k as u64);
+            }
+            self.aes.encrypt_blocks(&mut ms[..n]);
+            for (block, m) in chunk.iter_mut().zip(ms.iter()) {
+                *block = Block::try_from_slice(m.as_slice()).unwrap();
+            }
+            self.counter += n as u64;
+        }
+    }
+
+    /// Return the output block at `counter`, without moving the position.
+    #[inline]
+    pub fn block_at(&self, counter: u64) -> Block {
+        let mut m = self.counter_block(0, counter);
+        self.aes.encrypt_block(&mut m);
+        Block::try_from_slice(m.as_slice()).unwrap()
+    }
+
+    /// Derive the seed of child `id`, which is independent of the output of every stream.
+    #[inline]
+    pub fn child_seed(&self, id: u64) -> Block {
+        let mut m = self.counter_block(CHILD_DOMAIN, id);
+        self.aes.encrypt_block(&mut m);
+        Block::try_from_slice(m.as_slice()).unwrap()
+    }
+
+    /// Derive child PRG `id` on stream 0.
+    #[inline]
+    pub fn child(&self, id: u64) -> Self {
+        Prg::new_unchecked(self.child_seed(id), 0)
+    }
+}
+
+impl Drop for Prg {
+    fn drop(&mut self) {
+        wipe_aes(&mut self.aes);
+        self.buffer.zeroize();
+    }
+}
+
+impl RngCore for Prg {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0u8; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        let mut dest = dest;
+        if self.nbuffered > 0 {
+            let n = self.nbuffered.min(dest.len());
+            let start = 16 - self.nbuffered;
+            dest[..n].copy_from_slice(&self.buffer[start..start + n]);
+            self.nbuffered -= n;
+            dest = &mut dest[n..];
+        }
+        if dest.is_empty() {
+            return;
+        }
+
+        let nblocks = dest.len() / 16;
+        let mut blocks = vec![Block::default(); nblocks];
+        self.fill_blocks(&mut blocks);
+        for (d, block) in dest.chunks_exact_mut(16).zip(blocks.iter()) {
+            d.copy_from_slice(block.as_ref());
+        }
+
+        let rest = dest.len() % 16;
+        if rest > 0 {
+            self.buffer = self.block_at(self.counter).into();
+            self.counter += 1;
+            let offset = dest.len() - rest;
+            dest[offset..].copy_from_slice(&self.buffer[..rest]);
+            self.nbuffered = 16 - rest;
+        }
+    }
+    #[inline]
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for Prg {
+    type Seed = Block;
+
+    #[inline]
+    fn from_seed(seed: Self::Seed) -> Self {
+        Prg::new_unchecked(seed, 0)
+    }
+}
+
+impl CryptoRng for Prg {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prg_test() {
+        let seed = rand::random::<Block>();
+        let mut prg = Prg::new(seed, 3).unwrap();
+        let mut blocks = vec![Block::default(); 21];
+        prg.fill_blocks(&mut blocks);
+        assert_eq!(prg.counter(), 21);
+
+        // Seeking gives the output at any offset.
+        let mut prg = Prg::new(seed, 3).unwrap();
+        prg.seek(13);
+        let mut tail = vec![Block::default(); 8];
+        prg.fill_blocks(&mut tail);
+        assert_eq!(tail, blocks[13..]);
+        assert_eq!(prg.block_at(5), blocks[5]);
+
+        // Bytes are read in order across calls.
+        let mut prg = Prg::new(seed, 3).unwrap();
+        let mut bytes = vec![0u8; 21 * 16];
+        for chunk in bytes.chunks_mut(7) {
+            prg.fill_bytes(chunk);
+        }
+        let expected: Vec<u8> = blocks.iter().flat_map(|b| b.as_ref().to_vec()).collect();
+        assert_eq!(bytes, expected);
+
+        // Streams and children are separated.
+        let mut other = Prg::new(seed, 4).unwrap();
+        let mut other_blocks = vec![Block::default(); 21];
+        other.fill_blocks(&mut other_blocks);
+        assert!(other_blocks.iter().all(|b| !blocks.contains(b)));
+
+        let prg = Prg::new(seed, 3).unwrap();
+        let child_seeds: Vec<Block> = (0..21).map(|id| prg.child_seed(id)).collect();
+        assert!(child_seeds.iter().all(|b| !blocks.contains(b)));
+        assert_eq!(
+            prg.child(1).block_at(0),
+            Prg::new(child_seeds[1], 0).unwrap().block_at(0)
+        );
+        assert_ne!(prg.child(1).block_at(0), prg.child(2).block_at(0));
+
+        // Stream ids with the top bit set would collide with the child seeds.
+        assert!(Prg::new(seed, MAX_STREAM).is_ok());
+        assert!(matches!(
+            Prg::new(seed, MAX_STREAM + 1),
+            Err(PrgError::StreamOutOfRange(_))
+        ));
+    }
+}
@@ -1,13 +1,14 @@
 //! Fixed-key AES random number generator.
 //! The code is derived from Swanky. https://github.com/GaloisInc/swanky.
 
-use crate::Block;
+use crate::{secret::wipe_aes, Block};
 use aes::cipher::KeyInit;
 use aes::{cipher::generic_array::GenericArray, Aes128};
 use cipher::consts::U16;
 use cipher::BlockEncrypt;
 use rand::{CryptoRng, Error, Rng, RngCore, SeedableRng};
 use rand_core::block::{BlockRng, BlockRngCore};
+use zeroize::Zeroize;
 
 /// Implementation of a random number generator based on fixed-key AES.
 ///
@@ -116,12 +117,10 @@ impl BlockRngCore for AesRngCore {
         ms[7] = GenericArray::from(self.state.to_be_bytes());
         self.state += 1;
         self.aes.encrypt_blocks(&mut ms);
-        let c: Vec<Block> = ms
-            .into_iter()
-            .map(|m| Block::try_from_slice(m.as_slice()).unwrap())
-            .collect();
-        unsafe {
-            *results = *(&c as *const _ as *const [u32; 32]);
+        for (r, m) in results.chunks_exact_mut(4).zip(ms.iter()) {
+            for (r, bytes) in r.iter_mut().zip(m.chunks_exact(4)) {
+                *r = u32::from_le_bytes(bytes.try_into().unwrap());
+            }
         }
     }
 }
@@ -143,6 +142,13 @@ impl SeedableRng for AesRngCore {
 
 impl CryptoRng for AesRngCore {}
 
+impl Drop for AesRngCore {
+    fn drop(&mut self) {
+        wipe_aes(&mut self.aes);
+        self.state.zeroize();
+    }
+}
+
 impl From<AesRngCore> for AesRng {
     #[inline]
     fn from(core: AesRngCore) -> Self {
@@ -162,4 +168,14 @@ mod tests {
         let b = rng.gen::<[Block; 8]>();
         assert_ne!(a, b);
     }
+
+    #[test]
+    fn test_seed() {
+        let seed = rand::random::<Block>();
+        let mut rng = AesRng::from_seed(seed);
+        let mut rng_ = AesRng::from_seed(seed);
+        let a = rng.gen::<[Block; 16]>();
+        let b = rng_.gen::<[Block; 16]>();
+        assert_eq!(a, b);
+    }
 }
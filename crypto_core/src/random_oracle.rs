@@ -0,0 +1,125 @@
+//! Random oracle instantiated with SHA-256 and explicit domain separation.
+//!
+//! The oracle first absorbs a domain tag, and then each input with its length, so inputs of
+//! different lengths, or hashed under different tags, never collide.
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+use sha2::{Digest, Sha256};
+
+use crate::Block;
+
+/// Incremental random oracle `H(tag, x_1, ..., x_n)`.
+#[derive(Clone)]
+pub struct RandomOracle {
+    hasher: Sha256,
+}
+
+impl RandomOracle {
+    /// New a random oracle with domain tag `tag`.
+    #[inline]
+    pub fn new(tag: &str) -> Self {
+        let mut ro = Self {
+            hasher: Sha256::new(),
+        };
+        ro.update(tag.as_bytes());
+        ro
+    }
+
+    /// Absorb `data` with its length.
+    #[inline]
+    pub fn update(&mut self, data: &[u8]) -> &mut Self {
+        self.hasher.update((data.len() as u64).to_le_bytes());
+        self.hasher.update(data);
+        self
+    }
+
+    /// Absorb a block.
+    #[inline]
+    pub fn update_block(&mut self, block: &Block) -> &mut Self {
+        self.update(block.as_ref())
+    }
+
+    /// Absorb an integer, e.g., an index.
+    #[inline]
+    pub fn update_u128(&mut self, x: u128) -> &mut Self {
+        self.update(&x.to_le_bytes())
+    }
+
+    /// Absorb the compressed encoding of a Ristretto point.
+    #[inline]
+    pub fn update_point(&mut self, point: &RistrettoPoint) -> &mut Self {
+        self.update(point.compress().as_bytes())
+    }
+
+    /// Return the 256-bit digest.
+    #[inline]
+    pub fn finalize(self) -> [u8; 32] {
+        self.hasher.finalize().into()
+    }
+
+    /// Return the digest truncated to a block.
+    #[inline]
+    pub fn finalize_block(self) -> Block {
+        Block::try_from_slice(&self.finalize()[..16]).unwrap()
+    }
+}
+
+/// Hash `inputs` to a block under domain tag `tag`.
+#[inline]
+pub fn hash_to_block(tag: &str, inputs: &[&[u8]]) -> Block {
+    let mut ro = RandomOracle::new(tag);
+    for input in inputs {
+        ro.update(input);
+    }
+    ro.finalize_block()
+}
+
+/// Hash blocks to a block under domain tag `tag`.
+#[inline]
+pub fn hash_blocks(tag: &str, blocks: &[Block]) -> Block {
+    let mut ro = RandomOracle::new(tag);
+    for block in blocks {
+        ro.update_block(block);
+    }
+    ro.finalize_block()
+}
+
+/// Hash Ristretto points to a block under domain tag `tag`.
+#[inline]
+pub fn hash_points(tag: &str, points: &[RistrettoPoint]) -> Block {
+    let mut ro = RandomOracle::new(tag);
+    for point in points {
+        ro.update_point(point);
+    }
+    ro.finalize_block()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_oracle_test() {
+        let x = rand::random::<Block>();
+        let y = rand::random::<Block>();
+        assert_eq!(hash_blocks("a", &[x, y]), hash_blocks("a", &[x, y]));
+        assert_eq!(
+            hash_blocks("a", &[x, y]),
+            hash_to_block("a", &[x.as_ref(), y.as_ref()])
+        );
+        assert_ne!(hash_blocks("a", &[x, y]), hash_blocks("b", &[x, y]));
+        assert_ne!(hash_blocks("a", &[x, y]), hash_blocks("a", &[y, x]));
+
+        // Inputs are length-prefixed, so moving bytes between inputs changes the hash.
+        assert_ne!(
+            hash_to_block("a", &[b"ab", b"c"]),
+            hash_to_block("a", &[b"a", b"bc"])
+        );
+        assert_ne!(hash_to_block("ab", &[]), hash_to_block("a", &[b"b"]));
+
+        let p = curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+        let q = p + p;
+        assert_eq!(hash_points("a", &[p, q]), hash_points("a", &[p, q]));
+        assert_ne!(hash_points("a", &[p, q]), hash_points("a", &[q, p]));
+    }
+}
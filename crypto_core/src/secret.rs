@@ -0,0 +1,116 @@
+//! Handling of secret material.
+//!
+//! `Block` is `Copy`, so a secret such as a global correlation `Δ` or a key share is duplicated
+//! wherever it is used. `SecretBlock` is not `Copy`, is compared in constant time, is redacted in
+//! `Debug` output and is zeroized when dropped. Its value is read explicitly with `expose`.
+
+use std::sync::atomic::{compiler_fence, Ordering};
+
+use aes::cipher::{generic_array::GenericArray, KeyInit};
+use aes::Aes128;
+use rand::{CryptoRng, Rng};
+use subtle::{Choice, ConstantTimeEq};
+use zeroize::Zeroize;
+
+use crate::Block;
+
+/// A secret block, zeroized on drop.
+pub struct SecretBlock(Block);
+
+impl SecretBlock {
+    #[inline]
+    pub fn new(block: Block) -> Self {
+        Self(block)
+    }
+
+    /// New a uniformly random secret block.
+    #[inline]
+    pub fn random<R: Rng + CryptoRng>(rng: &mut R) -> Self {
+        Self(rng.gen())
+    }
+
+    /// Return a reference to the secret value.
+    #[inline]
+    pub fn expose(&self) -> &Block {
+        &self.0
+    }
+}
+
+impl Clone for SecretBlock {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(self.0)
+    }
+}
+
+impl From<Block> for SecretBlock {
+    #[inline]
+    fn from(block: Block) -> Self {
+        Self(block)
+    }
+}
+
+impl Zeroize for SecretBlock {
+    #[inline]
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Drop for SecretBlock {
+    #[inline]
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ConstantTimeEq for SecretBlock {
+    #[inline]
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0.ct_eq(&other.0)
+    }
+}
+
+impl PartialEq for SecretBlock {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl Eq for SecretBlock {}
+
+impl std::fmt::Debug for SecretBlock {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "SecretBlock(..)")
+    }
+}
+
+/// Overwrite the key schedule of `aes` with the one of the all-zero key. The `aes` crate only
+/// zeroizes its key schedules with a newer `zeroize`, so this is done here instead.
+pub(crate) fn wipe_aes(aes: &mut Aes128) {
+    let zero = Aes128::new(&GenericArray::default());
+    // The old key schedule holds no heap memory, so it is fine not to drop it.
+    unsafe { std::ptr::write_volatile(aes, zero) };
+    compiler_fence(Ordering::SeqCst);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::ZERO_BLOCK;
+
+    #[test]
+    fn secret_block_test() {
+        let mut rng = rand::thread_rng();
+        let x = rng.gen::<Block>();
+        let mut s = SecretBlock::new(x);
+        assert_eq!(*s.expose(), x);
+        assert_eq!(s, s.clone());
+        assert_ne!(s, SecretBlock::new(x ^ Block::from(1u128)));
+        assert_eq!(format!("{:?}", s), "SecretBlock(..)");
+
+        s.zeroize();
+        assert_eq!(*s.expose(), ZERO_BLOCK);
+    }
+}
@@ -0,0 +1,256 @@
+//! Transposition of bit matrices, e.g., the `λ × ℓ` matrices of OT extension.
+//!
+//! A matrix of `nrows` rows and `ncols` columns is stored row by row, each row in
+//! `ceil(ncols / 8)` bytes with the bits of a byte in little-endian order, as in `pack_bits`.
+//! The fast path loads tiles of 16 × 16 bytes, transposes their bytes with unpack instructions
+//! and moves each resulting 16 × 8 sub-matrix with `_mm_movemask_epi8` (cf. Mischasan,
+//! <https://mischasan.wordpress.com/2011/07/24/what-is-sse-good-for-transposing-a-bit-matrix/>).
+
+#[cfg(all(
+    target_arch = "x86_64",
+    target_feature = "sse2",
+    not(feature = "portable")
+))]
+use core::arch::x86_64::*;
+
+use crate::block::Block;
+
+/// Transpose a bit matrix of `nrows` rows and `ncols` columns into `ncols` rows of
+/// `nrows` bits. Both dimensions may be arbitrary, the matrix is padded with zeros internally.
+pub fn transpose(m: &[u8], nrows: usize, ncols: usize) -> Vec<u8> {
+    let nrow_bytes = ncols.div_ceil(8);
+    assert_eq!(m.len(), nrows * nrow_bytes, "invalid matrix size");
+
+    // Pad the rows to a multiple of 16.
+    let nrows_padded = nrows.div_ceil(16) * 16;
+    let out = if nrows_padded == nrows {
+        transpose_padded(m, nrows, nrow_bytes)
+    } else {
+        let mut padded = m.to_vec();
+        padded.resize(nrows_padded * nrow_bytes, 0);
+        transpose_padded(&padded, nrows_padded, nrow_bytes)
+    };
+
+    // Drop the padded rows and columns.
+    let ncol_bytes_padded = nrows_padded / 8;
+    let ncol_bytes = nrows.div_ceil(8);
+    if ncol_bytes == ncol_bytes_padded {
+        let mut out = out;
+        out.truncate(ncols * ncol_bytes);
+        out
+    } else {
+        out.chunks_exact(ncol_bytes_padded)
+            .take(ncols)
+            .flat_map(|row| row[..ncol_bytes].iter().copied())
+            .collect()
+    }
+}
+
+/// Transpose 128 columns of `nrows` bits, stored one after the other, into `nrows` blocks.
+pub fn transpose_to_blocks(cols: &[u8], nrows: usize) -> Vec<Block> {
+    transpose(cols, 128, nrows)
+        .chunks_exact(16)
+        .map(|row| Block::try_from_slice(row).unwrap())
+        .collect()
+}
+
+/// Transpose `nrows` blocks into 128 columns of `nrows` bits, stored one after the other.
+pub fn transpose_from_blocks(rows: &[Block]) -> Vec<u8> {
+    let m: Vec<u8> = rows.iter().flat_map(|row| row.as_ref().to_vec()).collect();
+    transpose(&m, rows.len(), 128)
+}
+
+#[inline]
+fn transpose_padded(m: &[u8], nrows: usize, nrow_bytes: usize) -> Vec<u8> {
+    #[cfg(all(
+        target_arch = "x86_64",
+        target_feature = "sse2",
+        not(feature = "portable")
+    ))]
+    {
+        // Safety: SSE2 is enabled at compile time.
+        unsafe { transpose_sse(m, nrows, nrow_bytes) }
+    }
+    #[cfg(not(all(
+        target_arch = "x86_64",
+        target_feature = "sse2",
+        not(feature = "portable")
+    )))]
+    {
+        transpose_portable(m, nrows, nrow_bytes)
+    }
+}
+
+/// Gather byte `cb` of rows `r, ..., r + 15`.
+#[inline(always)]
+fn gather(m: &[u8], r: usize, cb: usize, nrow_bytes: usize) -> [u8; 16] {
+    let mut bytes = [0u8; 16];
+    for (k, byte) in bytes.iter_mut().enumerate() {
+        *byte = m[(r + k) * nrow_bytes + cb];
+    }
+    bytes
+}
+
+/// One stage of the byte transpose of a 16 × 16 tile: interleave the elements of each pair of
+/// vectors, the low halves into the first 8 vectors and the high halves into the last 8.
+#[cfg(all(
+    target_arch = "x86_64",
+    target_feature = "sse2",
+    not(feature = "portable")
+))]
+macro_rules! interleave {
+    ($v:expr, $lo:ident, $hi:ident) => {{
+        let v = $v;
+        let mut w = [_mm_setzero_si128(); 16];
+        for i in 0..8 {
+            w[i] = $lo(v[2 * i], v[2 * i + 1]);
+            w[i + 8] = $hi(v[2 * i], v[2 * i + 1]);
+        }
+        w
+    }};
+}
+
+/// Write the transpose of the 16 × 8 sub-matrix `v`, i.e., byte `cb` of rows `r, ..., r + 15`.
+#[cfg(all(
+    target_arch = "x86_64",
+    target_feature = "sse2",
+    not(feature = "portable")
+))]
+#[inline]
+#[target_feature(enable = "sse2")]
+unsafe fn store_columns(out: &mut [u8], mut v: __m128i, r: usize, cb: usize, ncol_bytes: usize) {
+    // The movemask collects the most significant bit of each byte, i.e., column `8 * cb + b`
+    // of the 16 rows.
+    for b in (0..8).rev() {
+        let mask = _mm_movemask_epi8(v) as u16;
+        let o = (8 * cb + b) * ncol_bytes + r / 8;
+        out[o..o + 2].copy_from_slice(&mask.to_le_bytes());
+        v = _mm_slli_epi64(v, 1);
+    }
+}
+
+/// Transpose with SSE2, where `nrows` is a multiple of 16.
+#[cfg(all(
+    target_arch = "x86_64",
+    target_feature = "sse2",
+    not(feature = "portable")
+))]
+#[target_feature(enable = "sse2")]
+unsafe fn transpose_sse(m: &[u8], nrows: usize, nrow_bytes: usize) -> Vec<u8> {
+    let ncol_bytes = nrows / 8;
+    let mut out = vec![0u8; 8 * nrow_bytes * ncol_bytes];
+    let ntiles = nrow_bytes / 16;
+    for r in (0..nrows).step_by(16) {
+        for t in 0..ntiles {
+            let mut v = [_mm_setzero_si128(); 16];
+            for (k, row) in v.iter_mut().enumerate() {
+                let p = m[(r + k) * nrow_bytes + 16 * t..].as_ptr();
+                *row = _mm_loadu_si128(p as *const __m128i);
+            }
+            let v = interleave!(v, _mm_unpacklo_epi8, _mm_unpackhi_epi8);
+            let v = interleave!(v, _mm_unpacklo_epi16, _mm_unpackhi_epi16);
+            let v = interleave!(v, _mm_unpacklo_epi32, _mm_unpackhi_epi32);
+            let v = interleave!(v, _mm_unpacklo_epi64, _mm_unpackhi_epi64);
+            // Vector `j` now holds the byte whose index is `j` with its 4 bits reversed.
+            for (j, col) in v.into_iter().enumerate() {
+                let cb = 16 * t + ((j as u8).reverse_bits() >> 4) as usize;
+                store_columns(&mut out, col, r, cb, ncol_bytes);
+            }
+        }
+        for cb in 16 * ntiles..nrow_bytes {
+            let bytes = gather(m, r, cb, nrow_bytes);
+            let v = _mm_loadu_si128(bytes.as_ptr() as *const __m128i);
+            store_columns(&mut out, v, r, cb, ncol_bytes);
+        }
+    }
+    out
+}
+
+/// Portable transpose, where `nrows` is a multiple of 16.
+#[cfg_attr(
+    all(
+        target_arch = "x86_64",
+        target_feature = "sse2",
+        not(feature = "portable")
+    ),
+    allow(dead_code)
+)]
+fn transpose_portable(m: &[u8], nrows: usize, nrow_bytes: usize) -> Vec<u8> {
+    let ncol_bytes = nrows / 8;
+    let mut out = vec![0u8; 8 * nrow_bytes * ncol_bytes];
+    for r in (0..nrows).step_by(16) {
+        for cb in 0..nrow_bytes {
+            let bytes = gather(m, r, cb, nrow_bytes);
+            for b in 0..8 {
+                let mask = bytes
+                    .iter()
+                    .enumerate()
+                    .fold(0u16, |acc, (k, byte)| acc | (((byte >> b) & 1) as u16) << k);
+                let o = (8 * cb + b) * ncol_bytes + r / 8;
+                out[o..o + 2].copy_from_slice(&mask.to_le_bytes());
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transpose_naive(m: &[u8], nrows: usize, ncols: usize) -> Vec<u8> {
+        let nrow_bytes = ncols.div_ceil(8);
+        let ncol_bytes = nrows.div_ceil(8);
+        let mut out = vec![0u8; ncols * ncol_bytes];
+        for i in 0..nrows {
+            for j in 0..ncols {
+                let bit = (m[i * nrow_bytes + j / 8] >> (j % 8)) & 1;
+                out[j * ncol_bytes + i / 8] |= bit << (i % 8);
+            }
+        }
+        out
+    }
+
+    fn random_matrix(nrows: usize, ncols: usize) -> Vec<u8> {
+        let nrow_bytes = ncols.div_ceil(8);
+        let mut m: Vec<u8> = (0..nrows * nrow_bytes)
+            .map(|_| rand::random::<u8>())
+            .collect();
+        // Clear the padding bits of each row.
+        if ncols % 8 != 0 {
+            for row in m.chunks_exact_mut(nrow_bytes) {
+                row[nrow_bytes - 1] &= (1 << (ncols % 8)) - 1;
+            }
+        }
+        m
+    }
+
+    #[test]
+    fn transpose_test() {
+        for (nrows, ncols) in [
+            (128, 128),
+            (128, 1024),
+            (16, 8),
+            (3, 5),
+            (128, 300),
+            (77, 128),
+        ] {
+            let m = random_matrix(nrows, ncols);
+            let expected = transpose_naive(&m, nrows, ncols);
+            assert_eq!(transpose(&m, nrows, ncols), expected);
+            assert_eq!(transpose(&expected, ncols, nrows), m);
+
+            let nrows_padded = nrows.div_ceil(16) * 16;
+            let mut padded = m.clone();
+            padded.resize(nrows_padded * ncols.div_ceil(8), 0);
+            assert_eq!(
+                transpose_portable(&padded, nrows_padded, ncols.div_ceil(8)),
+                transpose_naive(&padded, nrows_padded, ncols.div_ceil(8) * 8)
+            );
+        }
+
+        let rows: Vec<Block> = (0..200).map(|_| rand::random::<Block>()).collect();
+        let cols = transpose_from_blocks(&rows);
+        assert_eq!(transpose_to_blocks(&cols, rows.len()), rows);
+    }
+}
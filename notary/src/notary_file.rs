@@ -3,17 +3,18 @@
 use mina_curves::pasta::Fp;
 use mina_hasher::{Hashable, ROInput};
 use mina_signer::{signature::Signature, Keypair, Signer};
+use zeroize::Zeroizing;
 
 use crate::ZkOraclesVersion;
 
-/// Row data of a notarization file.
-#[derive(Clone)]
+/// Row data of a notarization file. The notary key share is zeroized on drop, and the data is
+/// not `Clone` so that the share is not duplicated.
 pub struct NotaryFileRawData {
     // Commitment of client key share using Poseidon-based commitment.
     pub client_key_share_commitment: Fp,
 
     // Notary key share with 128-bit string.
-    pub notary_key_share: [u8; 16],
+    pub notary_key_share: Zeroizing<[u8; 16]>,
 
     // The length of the encrypted query.
     pub encrypted_query_length: u32,
@@ -58,7 +59,7 @@ impl NotaryFileRawData {
     ) -> Self {
         Self {
             client_key_share_commitment,
-            notary_key_share,
+            notary_key_share: Zeroizing::new(notary_key_share),
             encrypted_query_length,
             encrypted_query,
             encrypted_query_iv,
@@ -96,7 +97,6 @@ impl Hashable for NotaryFileRawData {
 }
 
 /// Structure of a notarization file, including the signature signed by the Notary.
-#[derive(Clone)]
 pub struct NotaryFile {
     pub raw_data: NotaryFileRawData,
     pub sig: Option<Signature>,
@@ -128,15 +128,15 @@ impl Notary {
         }
     }
 
-    /// Create a signed file.
+    /// Create a signed file from `raw_data`.
     pub fn create_file(
         &self,
         signer: &mut impl Signer<NotaryFileRawData>,
-        raw_data: &NotaryFileRawData,
+        raw_data: NotaryFileRawData,
     ) -> NotaryFile {
-        let sig = signer.sign(&self.kp, raw_data);
+        let sig = signer.sign(&self.kp, &raw_data);
         NotaryFile {
-            raw_data: raw_data.clone(),
+            raw_data,
             sig: Some(sig),
         }
     }
@@ -190,7 +190,7 @@ mod tests {
         let mut signer =
             mina_signer::create_kimchi::<NotaryFileRawData>(ZkOraclesVersion::VERSION0_1_0);
 
-        let notary_file = notary.create_file(&mut signer, &raw_data);
+        let notary_file = notary.create_file(&mut signer, raw_data);
 
         let res = notary.verify_file(&mut signer, &notary_file);
 
@@ -2,14 +2,17 @@ use circuit::Circuit;
 use criterion::{criterion_group, criterion_main, Criterion};
 use crypto_core::AesRng;
 use std::time::Duration;
+use twopc::GCEvaluator;
 use twopc::GCGenerator;
+use twopc::HalfGateEvaluator;
 use twopc::HalfGateGenerator;
+use twopc::InputLabel;
 
 fn bench_garble_adder64(c: &mut Criterion) {
     c.bench_function("garbling adder64", |b| {
         let circ = Circuit::load("../circuit/circuit_files/bristol/adder64.txt").unwrap();
         let mut rng = AesRng::new();
-        let gen = HalfGateGenerator;
+        let gen = HalfGateGenerator::new();
 
         b.iter(|| {
             let complete_gc = gen.garble(&mut rng, &circ).unwrap();
@@ -22,7 +25,7 @@ fn bench_garble_aes_128_reverse(c: &mut Criterion) {
     c.bench_function("garbling aes128 reverse", |b| {
         let circ = Circuit::load("../circuit/circuit_files/bristol/aes_128_reverse.txt").unwrap();
         let mut rng = AesRng::new();
-        let gen = HalfGateGenerator;
+        let gen = HalfGateGenerator::new();
 
         b.iter(|| {
             let complete_gc = gen.garble(&mut rng, &circ).unwrap();
@@ -30,9 +33,35 @@ fn bench_garble_aes_128_reverse(c: &mut Criterion) {
         });
     });
 }
+fn bench_eval_aes_128_reverse(c: &mut Criterion) {
+    c.bench_function("evaluating aes128 reverse", |b| {
+        let circ = Circuit::load("../circuit/circuit_files/bristol/aes_128_reverse.txt").unwrap();
+        let mut rng = AesRng::new();
+        let complete_gc = HalfGateGenerator::new().garble(&mut rng, &circ).unwrap();
+        let gc = complete_gc.to_public(&[]);
+        let input_labels: Vec<InputLabel> = complete_gc
+            .input_labels
+            .iter()
+            .enumerate()
+            .map(|(id, label)| InputLabel {
+                id,
+                label: label[0],
+            })
+            .collect();
+
+        b.iter(|| {
+            // A fresh evaluator, as an evaluator rejects a garbled circuit it has evaluated.
+            let outputs = HalfGateEvaluator::new()
+                .eval_labels(&circ, &gc, &input_labels)
+                .unwrap();
+            criterion::black_box(outputs);
+        });
+    });
+}
+
 criterion_group! {
     name = garbling;
     config = Criterion::default().warm_up_time(Duration::from_millis(100));
-    targets = bench_garble_adder64, bench_garble_aes_128_reverse
+    targets = bench_garble_adder64, bench_garble_aes_128_reverse, bench_eval_aes_128_reverse
 }
 criterion_main!(garbling);
@@ -0,0 +1,152 @@
+use std::num::NonZeroU64;
+use std::thread;
+use std::time::Duration;
+
+use circuit::Circuit;
+use crypto_core::{
+    sim_channel_pair, AesRng, BitVec, Block, LocalChannel, NetworkProfile, SimChannel,
+};
+use rand::Rng;
+use twopc::{
+    ChouOrlandiReceiver, ChouOrlandiSender, GCEvaluator, GCGenerator, HalfGateEvaluator,
+    HalfGateGenerator, KosReceiver, KosSender, OtReceiver, OtSender, RotToOtReceiver,
+    RotToOtSender, ThreeHalvesEvaluator, ThreeHalvesGenerator, YaoEvaluator, YaoGarbler,
+};
+
+const AES_CIRCUIT: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../circuit/circuit_files/bristol/aes_128_reverse.txt"
+);
+const OT_NUM: usize = 1 << 12;
+
+/// Run the two parties of a protocol on a simulated network, and print the simulated wall time
+/// of the slower party and the bytes sent by both.
+fn simulate<F, G>(name: &str, profile: NetworkProfile, sender: F, receiver: G)
+where
+    F: FnOnce(&mut SimChannel<LocalChannel>) + Send + 'static,
+    G: FnOnce(&mut SimChannel<LocalChannel>),
+{
+    let (mut s, mut r) = sim_channel_pair(profile);
+    let handle = thread::spawn(move || {
+        sender(&mut s);
+        (s.elapsed(), s.write_bytes_size())
+    });
+    receiver(&mut r);
+    let (elapsed, sent) = handle.join().unwrap();
+    println!(
+        "  {:<24} {:>10.1} ms {:>12} bytes",
+        name,
+        elapsed.max(r.elapsed()).as_secs_f64() * 1000.0,
+        sent + r.write_bytes_size()
+    );
+}
+
+fn ot_inputs() -> (Vec<(Block, Block)>, BitVec) {
+    let mut rng = AesRng::new();
+    let m = (0..OT_NUM).map(|_| (rng.gen(), rng.gen())).collect();
+    (m, BitVec::random(&mut rng, OT_NUM))
+}
+
+fn co(profile: NetworkProfile) {
+    let (m, choices) = ot_inputs();
+    simulate(
+        "Chou-Orlandi OT",
+        profile,
+        move |channel| {
+            let mut rng = AesRng::new();
+            let mut ot = ChouOrlandiSender::new_with_rng(&mut rng);
+            ot.send(channel, &m, &mut rng).unwrap();
+        },
+        |channel| {
+            let mut ot = ChouOrlandiReceiver::new();
+            ot.receive(channel, &choices, &mut AesRng::new()).unwrap();
+        },
+    );
+}
+
+fn kos(profile: NetworkProfile) {
+    let (m, choices) = ot_inputs();
+    simulate(
+        "KOS OT extension",
+        profile,
+        move |channel| {
+            let mut rng = AesRng::new();
+            let delta = rng.gen::<Block>();
+            let ot = KosSender::init(channel, delta, &mut rng).unwrap();
+            RotToOtSender::new(ot).send(channel, &m, &mut rng).unwrap();
+        },
+        |channel| {
+            let mut rng = AesRng::new();
+            let ot = KosReceiver::init(channel, &mut rng).unwrap();
+            RotToOtReceiver::new(ot)
+                .receive(channel, &choices, &mut rng)
+                .unwrap();
+        },
+    );
+}
+
+fn yao<G, E>(name: &str, profile: NetworkProfile, gen: G, ev: E)
+where
+    G: GCGenerator + Send + 'static,
+    E: GCEvaluator,
+{
+    let circ = Circuit::load(AES_CIRCUIT).unwrap();
+    simulate(
+        name,
+        profile,
+        move |channel| {
+            let mut rng = AesRng::new();
+            let delta = rng.gen::<Block>();
+            let ot = KosSender::init(channel, delta, &mut rng).unwrap();
+            let mut garbler = YaoGarbler::new(gen, RotToOtSender::new(ot));
+            let input = BitVec::zeros(128);
+            garbler.run(channel, &circ, &input, &mut rng).unwrap();
+        },
+        |channel| {
+            let circ = Circuit::load(AES_CIRCUIT).unwrap();
+            let mut rng = AesRng::new();
+            let ot = KosReceiver::init(channel, &mut rng).unwrap();
+            let mut evaluator = YaoEvaluator::new(ev, RotToOtReceiver::new(ot));
+            let input = BitVec::zeros(128);
+            evaluator.run(channel, &circ, &input, &mut rng).unwrap();
+        },
+    );
+}
+
+// compare protocol variants on simulated networks
+// cargo run --release --example network
+pub fn main() {
+    let slow_wan = NetworkProfile::new(Duration::from_millis(100), NonZeroU64::new(10_000_000));
+    for (name, profile) in [
+        ("local", NetworkProfile::LOCAL),
+        ("LAN", NetworkProfile::LAN),
+        ("WAN", NetworkProfile::WAN),
+        ("slow WAN", slow_wan),
+    ] {
+        println!(
+            "{}: {:?} latency, {} bandwidth",
+            name,
+            profile.latency,
+            profile
+                .bandwidth
+                .map_or("unlimited".to_string(), |bw| format!(
+                    "{} Mbps",
+                    bw.get() / 1_000_000
+                ))
+        );
+        co(profile);
+        kos(profile);
+        yao(
+            "Yao AES, half gates",
+            profile,
+            HalfGateGenerator::new(),
+            HalfGateEvaluator::new(),
+        );
+        yao(
+            "Yao AES, three halves",
+            profile,
+            ThreeHalvesGenerator::new(),
+            ThreeHalvesEvaluator::new(),
+        );
+    }
+}
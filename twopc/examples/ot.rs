@@ -1,7 +1,9 @@
 use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
 
 use crypto_core::CommandLineOpt;
-use crypto_core::{AesRng, Block, NetChannel};
+use crypto_core::{AesRng, BitVec, Block, NetChannel};
 use structopt::StructOpt;
 use twopc::ot::{ChouOrlandiReceiver, ChouOrlandiSender, OtReceiver, OtSender};
 
@@ -9,21 +11,21 @@ fn rand_block_vec(size: usize) -> Vec<Block> {
     (0..size).map(|_| rand::random::<Block>()).collect()
 }
 
-fn rand_bool_vec(size: usize) -> Vec<bool> {
-    (0..size).map(|_| rand::random::<bool>()).collect()
+fn rand_bit_vec(size: usize) -> BitVec {
+    BitVec::random(&mut rand::thread_rng(), size)
 }
 
 fn coot_test(netio: &mut NetChannel<TcpStream, TcpStream>) {
     if netio.is_server() {
         let m0 = rand_block_vec(8);
         let m1 = rand_block_vec(8);
-        let m: Vec<(Block, Block)> = m0.into_iter().zip(m1.into_iter()).collect();
+        let m: Vec<(Block, Block)> = m0.into_iter().zip(m1).collect();
         let mut rng = AesRng::new();
         let mut ot = ChouOrlandiSender::new();
         ot.send(netio, &m, &mut rng).unwrap();
         println!("send blocks: {:?}", m);
     } else {
-        let select = rand_bool_vec(8);
+        let select = rand_bit_vec(8);
         let mut rng = AesRng::new();
         let mut ot = ChouOrlandiReceiver::new();
         let result = ot.receive(netio, &select, &mut rng).unwrap();
@@ -32,12 +34,34 @@ fn coot_test(netio: &mut NetChannel<TcpStream, TcpStream>) {
     }
 }
 
-// run the main function in two terminals
+// run both parties in this process
+// cargo run --example ot
+// or each party in its own terminal, started in any order
 // cargo run --example ot -- --is-server 1
 // cargo run --example ot -- --is-server 0
 pub fn main() {
     let opt = CommandLineOpt::from_args();
-    let is_server = opt.is_server != 0;
-    let mut netio = NetChannel::new(is_server, "127.0.0.1:12345");
-    coot_test(&mut netio);
+    let builder = NetChannel::builder()
+        .connect_retries(20)
+        .retry_backoff(Duration::from_millis(50), Duration::from_secs(1));
+    match opt.is_server {
+        Some(0) => {
+            let mut netio = builder.connect("127.0.0.1:12345").unwrap();
+            coot_test(&mut netio);
+        }
+        Some(_) => {
+            let mut netio = builder.bind("127.0.0.1:12345").unwrap().accept().unwrap();
+            coot_test(&mut netio);
+        }
+        None => {
+            let listener = builder.bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap();
+            let client = thread::spawn(move || {
+                let mut netio = builder.connect(addr).unwrap();
+                coot_test(&mut netio);
+            });
+            coot_test(&mut listener.accept().unwrap());
+            client.join().unwrap();
+        }
+    }
 }
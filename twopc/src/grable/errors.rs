@@ -1,8 +1,46 @@
+use crate::grable::gc::TweakNonce;
+
 #[derive(Debug, thiserror::Error)]
 pub enum GeneratorError {
     /// Error encountered during garbling when an input label is uninitialized
     #[error("Encountered uninitialized input label during garbling")]
     UninitializedLabel(usize),
+    /// I/O error encountered while streaming garbled tables
+    #[error("Encountered io error while streaming garbled circuit")]
+    IoError(#[from] std::io::Error),
+    /// Provided delta does not have its lsb set
+    #[error("Provided delta does not have its lsb set")]
+    InvalidDelta,
+    /// Provided input label pair is for a wire which is not an input wire
+    #[error("Provided input label pair is for a non-input wire")]
+    InvalidInputId(usize),
+    /// Provided input label pair is given more than once
+    #[error("Provided input label pair is duplicated")]
+    DuplicateInputLabel(usize),
+    /// Provided input label pair is not consistent with delta
+    #[error("Provided input label pair is inconsistent with delta")]
+    InconsistentInputLabel(usize),
+    /// Generator received invalid input counts for provided circuit
+    #[error("Generator received invalid input counts for provided circuit")]
+    InvalidInputCount(usize, usize),
+    /// Label bundle is not found in the session
+    #[error("Label bundle {0} is not found in the session")]
+    UnknownBundle(String),
+    /// Label bundle is already in the session
+    #[error("Label bundle {0} already exists in the session")]
+    BundleExists(String),
+    /// Number of output labels or policies does not match the circuit
+    #[error("Generator received invalid output counts")]
+    InvalidOutputCount(usize, usize),
+    /// Output label returned by the evaluator is neither of the two labels of the wire
+    #[error("Output label {0} returned by the evaluator is invalid")]
+    InvalidOutputLabel(usize),
+    /// Circuit has too many AND gates for the hash tweaks
+    #[error("Circuit has too many AND gates: {0}")]
+    CircuitTooLarge(usize),
+    /// Generator has issued all the tweak nonces of its session
+    #[error("Tweak nonces of session {0} are exhausted")]
+    NoncesExhausted(u64),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -13,4 +51,34 @@ pub enum EvaluatorError {
     /// Evaluator received invalid input counts for provided circuit
     #[error("Evaluator received invalid input counts for provided circuit")]
     InvalidInputCount(usize, usize),
+    /// Input label is for a wire which is not an input wire
+    #[error("Input label {0} is for a non-input wire")]
+    InvalidInputId(usize),
+    /// Input label is given more than once
+    #[error("Input label {0} is duplicated")]
+    DuplicateInputLabel(usize),
+    /// I/O error encountered while streaming garbled tables
+    #[error("Encountered io error while streaming garbled circuit")]
+    IoError(#[from] std::io::Error),
+    /// Label bundle is not found in the session
+    #[error("Label bundle {0} is not found in the session")]
+    UnknownBundle(String),
+    /// Label bundle is already in the session
+    #[error("Label bundle {0} already exists in the session")]
+    BundleExists(String),
+    /// Garbled circuit evaluated in a session carries generator input labels, while all its
+    /// inputs come from the bundles of the session
+    #[error("Garbled circuit carries {0} unexpected generator input labels")]
+    UnexpectedGeneratorInputs(usize),
+    /// Garbled circuit reuses the tweak nonce of a circuit evaluated earlier, i.e., its counter
+    /// is not larger than the counters of the circuits of the same session accepted by the
+    /// evaluator
+    #[error("Tweak nonce {0:?} is reused")]
+    ReusedNonce(TweakNonce),
+    /// Garbled table of an AND gate is missing or of another garbling scheme
+    #[error("Garbled table of AND gate {0} is invalid")]
+    InvalidTable(usize),
+    /// Number of output labels, policies or decoding bits does not match the circuit
+    #[error("Evaluator received invalid output counts")]
+    InvalidOutputCount(usize, usize),
 }
@@ -1,24 +1,42 @@
 use crypto_core::{block::SELECT_MASK, Block, AES_HASH};
 
+use super::init_input_labels;
 use crate::EvaluatorError;
 use crate::GCEvaluator;
+use crate::grable::gc::{gate_tweak, NonceCheck, TweakNonce};
 use crate::GarbledCircuit;
+use crate::GateTable;
 use crate::InputLabel;
 use circuit::gate::Gate;
 
-pub struct HalfGateEvaluator;
+/// The evaluator rejects a garbled circuit whose tweak nonce is not larger than the nonces of
+/// the circuits of the same session it evaluated before.
+#[derive(Debug, Default)]
+pub struct HalfGateEvaluator {
+    nonces: NonceCheck,
+}
 
 impl HalfGateEvaluator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
     #[inline]
-    pub fn and_gate(&self, x: Block, y: Block, table: [Block; 2], gid: usize) -> Block {
+    pub fn and_gate(
+        &self,
+        x: Block,
+        y: Block,
+        table: [Block; 2],
+        nonce: TweakNonce,
+        gid: usize,
+    ) -> Block {
         let sa = x.lsb() as usize;
         let sb = y.lsb() as usize;
 
-        let index = gid as u128;
-        let index_next = (gid + 1) as u128;
-
-        let hash_x = AES_HASH.tccr_hash(index.into(), x);
-        let hash_y = AES_HASH.tccr_hash(index_next.into(), y);
+        // Two blocks are below the eight the AES pipeline needs, so they are hashed one by one
+        // (see `AesHash::tccr_hash_many`).
+        let hash_x = AES_HASH.tccr_hash(gate_tweak(nonce, 2 * gid), x);
+        let hash_y = AES_HASH.tccr_hash(gate_tweak(nonce, 2 * gid + 1), y);
 
         let w_g = hash_x ^ (SELECT_MASK[sa] & table[0]);
         let w_e = hash_y ^ (SELECT_MASK[sb] & (table[1] ^ x));
@@ -38,27 +56,20 @@ impl HalfGateEvaluator {
 }
 
 impl GCEvaluator for HalfGateEvaluator {
-    fn eval(
+    fn eval_labels(
         &self,
         circ: &circuit::Circuit,
         gc: &GarbledCircuit,
         evaluator_input_labels: &[InputLabel],
-    ) -> Result<Vec<bool>, EvaluatorError> {
-        let input_labels = [
-            gc.generator_input_labels.clone(),
-            evaluator_input_labels.to_vec(),
-        ]
-        .concat();
-        assert_eq!(
-            input_labels.len(),
-            circ.ninput_wires,
-            "Number of input wires is not consistent!"
-        );
+    ) -> Result<Vec<Block>, EvaluatorError> {
+        let input_labels =
+            init_input_labels(circ, &gc.generator_input_labels, evaluator_input_labels)?;
+        self.nonces.accept(gc.nonce)?;
 
         let mut wire_labels: Vec<Option<Block>> = vec![None; circ.nwires];
 
-        for input_label in input_labels {
-            wire_labels[input_label.id] = Some(input_label.label);
+        for (wire, label) in wire_labels.iter_mut().zip(input_labels) {
+            *wire = Some(label);
         }
 
         let mut gid = 1;
@@ -95,7 +106,11 @@ impl GCEvaluator for HalfGateEvaluator {
                         wire_labels[lin_id].ok_or(EvaluatorError::UninitializedLabel(lin_id))?;
                     let y =
                         wire_labels[rin_id].ok_or(EvaluatorError::UninitializedLabel(rin_id))?;
-                    let z = self.and_gate(x, y, gc.table[gid - 1], gid);
+                    let table = match gc.table.get(gid - 1) {
+                        Some(GateTable::HalfGate(t)) => *t,
+                        _ => return Err(EvaluatorError::InvalidTable(gid - 1)),
+                    };
+                    let z = self.and_gate(x, y, table, gc.nonce, gid);
 
                     wire_labels[out_id] = Some(z);
                     gid += 1;
@@ -103,20 +118,25 @@ impl GCEvaluator for HalfGateEvaluator {
             };
         }
 
-        let mut outputs: Vec<bool> = Vec::with_capacity(circ.noutput_wires);
-        for (i, id) in ((circ.nwires - circ.noutput_wires)..circ.nwires).enumerate() {
-            outputs.push((wire_labels[id].unwrap().lsb()) ^ gc.output_bits[i]);
-        }
-        Ok(outputs)
+        ((circ.nwires - circ.noutput_wires)..circ.nwires)
+            .map(|id| wire_labels[id].ok_or(EvaluatorError::UninitializedLabel(id)))
+            .collect()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use circuit::{Circuit, CircuitInput};
-    use crypto_core::{AesRng, Block};
+    use crypto_core::{AesRng, BitVec, Block};
 
-    use crate::{GCEvaluator, GCGenerator, HalfGateEvaluator, HalfGateGenerator, InputLabel};
+    use crate::{
+        CotReceiver, CotSender, EvaluatorError, GCEvaluator, GCGenerator, GeneratorError,
+        HalfGateEvaluator, HalfGateGenerator, InputLabel, InputLabelPair, KosReceiver, KosSender,
+        TweakNonce,
+    };
+    use crypto_core::local_channel_pair;
+    use rand::Rng;
+    use std::thread;
 
     #[test]
     fn garbled_circuit_test() {
@@ -126,7 +146,7 @@ mod tests {
         let mut m2 = vec![false; 64];
         m2[0] = true;
 
-        let res = vec![false; 64];
+        let res = BitVec::zeros(64);
 
         let mut rng = AesRng::new();
         let circ = Circuit::load("../circuit/circuit_files/bristol/adder64.txt").unwrap();
@@ -137,8 +157,8 @@ mod tests {
         assert_eq!(circ.nand, 63);
         assert_eq!(circ.ninv, 0);
 
-        let gen = HalfGateGenerator;
-        let ev = HalfGateEvaluator;
+        let gen = HalfGateGenerator::new();
+        let ev = HalfGateEvaluator::new();
 
         let complete_gc = gen.garble(&mut rng, &circ).unwrap();
 
@@ -167,6 +187,74 @@ mod tests {
         assert_eq!(outputs, res);
     }
 
+    #[test]
+    fn invalid_inputs_test() {
+        let mut rng = AesRng::new();
+        let circ = Circuit::load("../circuit/circuit_files/bristol/adder64.txt").unwrap();
+        let gen = HalfGateGenerator::new();
+        let ev = HalfGateEvaluator::new();
+        let complete_gc = gen.garble(&mut rng, &circ).unwrap();
+        let mut gc = complete_gc.to_public(&[]);
+        let mut inputs: Vec<InputLabel> = complete_gc
+            .input_labels
+            .iter()
+            .enumerate()
+            .map(|(id, label)| InputLabel {
+                id,
+                label: label[0],
+            })
+            .collect();
+
+        // Missing, out-of-range and duplicated input labels are rejected instead of panicking
+        // or overwriting a label.
+        let res = ev.eval(&circ, &gc, &inputs[1..]);
+        assert!(matches!(res, Err(EvaluatorError::InvalidInputCount(128, 127))));
+        inputs[5].id = circ.nwires;
+        let res = ev.eval(&circ, &gc, &inputs);
+        assert!(matches!(res, Err(EvaluatorError::InvalidInputId(id)) if id == circ.nwires));
+        inputs[5].id = 6;
+        let res = ev.eval(&circ, &gc, &inputs);
+        assert!(matches!(res, Err(EvaluatorError::DuplicateInputLabel(6))));
+        inputs[5].id = 5;
+
+        // Withheld output bits are rejected instead of truncating the outputs.
+        gc.output_bits.clear();
+        let res = ev.eval(&circ, &gc, &inputs);
+        assert!(matches!(res, Err(EvaluatorError::InvalidOutputCount(64, 0))));
+    }
+
+    #[test]
+    fn reused_nonce_test() {
+        let mut rng = AesRng::new();
+        let circ = Circuit::load("../circuit/circuit_files/bristol/adder64.txt").unwrap();
+        let delta = rng.gen::<Block>().set_lsb();
+        let inputs: Vec<InputLabel> = (0..128)
+            .map(|id| InputLabel {
+                id,
+                label: Block::default(),
+            })
+            .collect();
+
+        // Generators sharing delta garble under distinct sessions.
+        let gen = HalfGateGenerator::with_session(1);
+        let other = HalfGateGenerator::with_session(2);
+        let gc = gen.garble_with_labels(&mut rng, &circ, delta, &[]).unwrap();
+        let other_gc = other.garble_with_labels(&mut rng, &circ, delta, &[]).unwrap();
+        assert_eq!(gc.nonce, TweakNonce { session: 1, counter: 0 });
+        assert_eq!(other_gc.nonce, TweakNonce { session: 2, counter: 0 });
+        assert_ne!(HalfGateGenerator::new().session(), HalfGateGenerator::new().session());
+
+        // The evaluator keeps the counters of each session, and rejects a reused nonce.
+        let ev = HalfGateEvaluator::new();
+        ev.eval_labels(&circ, &gc.to_public(&[]), &inputs).unwrap();
+        ev.eval_labels(&circ, &other_gc.to_public(&[]), &inputs).unwrap();
+        let res = ev.eval_labels(&circ, &gc.to_public(&[]), &inputs);
+        assert!(matches!(res, Err(EvaluatorError::ReusedNonce(n)) if n == gc.nonce));
+        let gc = gen.garble_with_labels(&mut rng, &circ, delta, &[]).unwrap();
+        assert_eq!(gc.nonce.counter, 1);
+        ev.eval_labels(&circ, &gc.to_public(&[]), &inputs).unwrap();
+    }
+
     #[test]
     fn gc_aes_test() {
         let mut input = vec![false; 128];
@@ -174,8 +262,8 @@ mod tests {
 
         let mut rng = AesRng::new();
         let circ = Circuit::load("../circuit/circuit_files/bristol/aes_128_reverse.txt").unwrap();
-        let gen = HalfGateGenerator;
-        let ev = HalfGateEvaluator;
+        let gen = HalfGateGenerator::new();
+        let ev = HalfGateEvaluator::new();
 
         let complete_gc = gen.garble(&mut rng, &circ).unwrap();
 
@@ -203,9 +291,102 @@ mod tests {
             })
             .collect();
 
-        let mut outputs = ev.eval(&circ, &gc, &evaluator_input_labels).unwrap();
+        let mut outputs = ev
+            .eval(&circ, &gc, &evaluator_input_labels)
+            .unwrap()
+            .to_vec();
         outputs.reverse();
         assert_eq!(outputs.into_iter().map(|i| (i as u8).to_string()).collect::<String>(),
             "01100110111010010100101111010100111011111000101000101100001110111000100001001100111110100101100111001010001101000010101100101110");
     }
+
+    #[test]
+    fn garble_with_cot_labels_test() {
+        // m1 = 2^64 - 1
+        let m1 = vec![true; 64];
+        // m2 = 1
+        let m2 = BitVec::from_u64(1, 64);
+
+        let (mut sender, mut receiver) = local_channel_pair();
+
+        // The evaluator input labels are fixed by correlated OT.
+        let handle = thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let delta = rng.gen::<Block>().set_lsb();
+            let mut ot = KosSender::init(&mut sender, delta, &mut rng).unwrap();
+            let labels = ot.send_correlated(&mut sender, 64, &mut rng).unwrap();
+            (delta, labels)
+        });
+        let mut rng = AesRng::new();
+        let mut ot = KosReceiver::init(&mut receiver, &mut rng).unwrap();
+        let evaluator_labels = ot
+            .receive_correlated(&mut receiver, &m2, &mut rng)
+            .unwrap();
+        let (delta, labels) = handle.join().unwrap();
+
+        let circ = Circuit::load("../circuit/circuit_files/bristol/adder64.txt").unwrap();
+        let gen = HalfGateGenerator::new();
+        let ev = HalfGateEvaluator::new();
+
+        let input_labels: Vec<InputLabelPair> = labels
+            .iter()
+            .enumerate()
+            .map(|(i, (z_0, z_1))| InputLabelPair {
+                id: i + 64,
+                labels: [*z_0, *z_1],
+            })
+            .collect();
+        let complete_gc = gen
+            .garble_with_labels(&mut rng, &circ, delta, &input_labels)
+            .unwrap();
+        assert_eq!(*complete_gc.delta.expose(), delta);
+
+        let generator_inputs: Vec<CircuitInput> = m1
+            .into_iter()
+            .enumerate()
+            .map(|(id, value)| CircuitInput {
+                id,
+                value: Block::from(value as u128),
+            })
+            .collect();
+        let gc = complete_gc.to_public(&generator_inputs);
+
+        let evaluator_input_labels: Vec<InputLabel> = evaluator_labels
+            .into_iter()
+            .enumerate()
+            .map(|(id, label)| InputLabel { id: id + 64, label })
+            .collect();
+
+        let outputs = ev.eval(&circ, &gc, &evaluator_input_labels).unwrap();
+        assert_eq!(outputs, BitVec::zeros(64));
+    }
+
+    #[test]
+    fn garble_with_inconsistent_labels_test() {
+        let mut rng = AesRng::new();
+        let circ = Circuit::load("../circuit/circuit_files/bristol/adder64.txt").unwrap();
+        let gen = HalfGateGenerator::new();
+        let delta = rng.gen::<Block>().set_lsb();
+        let z_0 = rng.gen::<Block>();
+
+        let res = gen.garble_with_labels(&mut rng, &circ, delta ^ Block::from(1), &[]);
+        assert!(matches!(res, Err(GeneratorError::InvalidDelta)));
+
+        let pair = InputLabelPair {
+            id: 3,
+            labels: [z_0, z_0 ^ delta ^ Block::from(2)],
+        };
+        let res = gen.garble_with_labels(&mut rng, &circ, delta, &[pair]);
+        assert!(matches!(
+            res,
+            Err(GeneratorError::InconsistentInputLabel(3))
+        ));
+
+        let pair = InputLabelPair {
+            id: 128,
+            labels: [z_0, z_0 ^ delta],
+        };
+        let res = gen.garble_with_labels(&mut rng, &circ, delta, &[pair]);
+        assert!(matches!(res, Err(GeneratorError::InvalidInputId(128))));
+    }
 }
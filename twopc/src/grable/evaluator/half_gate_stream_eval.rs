@@ -0,0 +1,162 @@
+use crypto_core::{AbstractChannel, BitVec, Block};
+
+use super::{init_input_labels, HalfGateEvaluator};
+use crate::grable::gc::{NonceCheck, TweakNonce};
+use crate::grable::wires::LiveWires;
+use crate::EvaluatorError;
+use crate::InputLabel;
+use circuit::gate::{Circuit, Gate};
+
+/// Half-gate evaluator that reads the garbled tables produced by `HalfGateStreamGenerator`
+/// and evaluates each gate as its table arrives. Reused tweak nonces are rejected as by
+/// `HalfGateEvaluator`.
+#[derive(Debug, Default)]
+pub struct HalfGateStreamEvaluator {
+    nonces: NonceCheck,
+}
+
+impl HalfGateStreamEvaluator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Evaluate `circ` with the garbled tables read from `channel`, and decode the outputs.
+    pub fn eval<C: AbstractChannel>(
+        &self,
+        channel: &mut C,
+        circ: &Circuit,
+        input_labels: &[InputLabel],
+    ) -> Result<BitVec, EvaluatorError> {
+        let input_labels = init_input_labels(circ, &[], input_labels)?;
+
+        let ev = HalfGateEvaluator::new();
+        let mut wire_labels: LiveWires<Block> = LiveWires::new(circ);
+
+        for (id, label) in input_labels.into_iter().enumerate() {
+            wire_labels.insert(id, label, true);
+        }
+
+        let nonce = TweakNonce::read(channel)?;
+        self.nonces.accept(nonce)?;
+        let public_one_label = channel.read_block()?;
+
+        let mut gid = 1;
+        for (gate, live) in circ.gates.iter().zip(circ.liveness()) {
+            match *gate {
+                Gate::Inv { lin_id, out_id, .. } => {
+                    let x = wire_labels
+                        .take(lin_id, live.lin_last)
+                        .ok_or(EvaluatorError::UninitializedLabel(lin_id))?;
+                    let z = ev.inv_gate(x, public_one_label);
+                    wire_labels.insert(out_id, z, live.out_used);
+                }
+                Gate::Xor {
+                    lin_id,
+                    rin_id,
+                    out_id,
+                    ..
+                } => {
+                    let x = wire_labels
+                        .take(lin_id, live.lin_last)
+                        .ok_or(EvaluatorError::UninitializedLabel(lin_id))?;
+                    let y = wire_labels
+                        .take(rin_id, live.rin_last)
+                        .ok_or(EvaluatorError::UninitializedLabel(rin_id))?;
+                    let z = ev.xor_gate(x, y);
+                    wire_labels.insert(out_id, z, live.out_used);
+                }
+                Gate::And {
+                    lin_id,
+                    rin_id,
+                    out_id,
+                    ..
+                } => {
+                    let x = wire_labels
+                        .take(lin_id, live.lin_last)
+                        .ok_or(EvaluatorError::UninitializedLabel(lin_id))?;
+                    let y = wire_labels
+                        .take(rin_id, live.rin_last)
+                        .ok_or(EvaluatorError::UninitializedLabel(rin_id))?;
+                    let table = [channel.read_block()?, channel.read_block()?];
+                    let z = ev.and_gate(x, y, table, nonce, gid);
+                    wire_labels.insert(out_id, z, live.out_used);
+                    gid += 1;
+                }
+            };
+        }
+
+        let output_bits = channel.read_bits(circ.noutput_wires)?;
+        ((circ.nwires - circ.noutput_wires)..circ.nwires)
+            .zip(output_bits.iter())
+            .map(|(id, bit)| {
+                let z = wire_labels
+                    .output(id)
+                    .ok_or(EvaluatorError::UninitializedLabel(id))?;
+                Ok(z.lsb() ^ bit)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use circuit::Circuit;
+    use crypto_core::{local_channel_pair, AesRng, BitVec};
+
+    use crate::{HalfGateGenerator, HalfGateStreamEvaluator, HalfGateStreamGenerator, InputLabel};
+
+    fn stream_eval(path: &'static str, input: Vec<bool>) -> BitVec {
+        let (mut sender, mut receiver) = local_channel_pair();
+        let circ = Circuit::load(path).unwrap();
+
+        let mut rng = AesRng::new();
+        let gen = HalfGateStreamGenerator::new(&HalfGateGenerator::new(), &mut rng, &circ).unwrap();
+        let input_labels: Vec<InputLabel> = gen
+            .input_labels()
+            .iter()
+            .zip(input)
+            .enumerate()
+            .map(|(id, (label, value))| InputLabel {
+                id,
+                label: label[value as usize],
+            })
+            .collect();
+
+        let handle = thread::spawn(move || {
+            let circ = Circuit::load(path).unwrap();
+            gen.garble(&mut sender, &circ).unwrap();
+        });
+
+        let ev = HalfGateStreamEvaluator::new();
+        let outputs = ev.eval(&mut receiver, &circ, &input_labels).unwrap();
+        handle.join().unwrap();
+        outputs
+    }
+
+    #[test]
+    fn stream_adder64_test() {
+        // m1 = 2^64 - 1, m2 = 1
+        let mut input = vec![true; 64];
+        let mut m2 = vec![false; 64];
+        m2[0] = true;
+        input.extend(m2);
+
+        let outputs = stream_eval("../circuit/circuit_files/bristol/adder64.txt", input);
+        assert_eq!(outputs, BitVec::zeros(64));
+    }
+
+    #[test]
+    fn stream_aes_test() {
+        let input = vec![false; 256];
+        let mut outputs = stream_eval(
+            "../circuit/circuit_files/bristol/aes_128_reverse.txt",
+            input,
+        )
+        .to_vec();
+        outputs.reverse();
+        assert_eq!(outputs.into_iter().map(|i| (i as u8).to_string()).collect::<String>(),
+            "01100110111010010100101111010100111011111000101000101100001110111000100001001100111110100101100111001010001101000010101100101110");
+    }
+}
@@ -1,17 +1,72 @@
 pub mod half_gate_eval;
+pub mod half_gate_stream_eval;
+pub mod three_halves_eval;
 
 pub use half_gate_eval::*;
+pub use half_gate_stream_eval::*;
+pub use three_halves_eval::*;
 
 use super::errors::EvaluatorError;
 use crate::gc::{GarbledCircuit, InputLabel};
 use circuit::Circuit;
+use crypto_core::{BitVec, Block};
 
 pub trait GCEvaluator {
-    /// Evaluate a garbled circuit
+    /// Evaluate a garbled circuit, and decode the outputs with the output bits of `gc`
     fn eval(
         &self,
         circ: &Circuit,
         gc: &GarbledCircuit,
         evaluator_input_labels: &[InputLabel],
-    ) -> Result<Vec<bool>, EvaluatorError>;
+    ) -> Result<BitVec, EvaluatorError> {
+        let output_labels = self.eval_labels(circ, gc, evaluator_input_labels)?;
+        if gc.output_bits.len() != output_labels.len() {
+            return Err(EvaluatorError::InvalidOutputCount(
+                output_labels.len(),
+                gc.output_bits.len(),
+            ));
+        }
+        Ok(output_labels
+            .iter()
+            .zip(gc.output_bits.iter())
+            .map(|(label, bit)| label.lsb() ^ bit)
+            .collect())
+    }
+
+    /// Evaluate a garbled circuit, and return the output labels without decoding them
+    fn eval_labels(
+        &self,
+        circ: &Circuit,
+        gc: &GarbledCircuit,
+        evaluator_input_labels: &[InputLabel],
+    ) -> Result<Vec<Block>, EvaluatorError>;
+}
+
+/// Return the labels of all input wires of `circ` in the order of their ids, given the labels
+/// of the generator and of the evaluator. Check that they cover the input wires exactly once.
+pub(crate) fn init_input_labels(
+    circ: &Circuit,
+    generator_input_labels: &[InputLabel],
+    evaluator_input_labels: &[InputLabel],
+) -> Result<Vec<Block>, EvaluatorError> {
+    let count = generator_input_labels.len() + evaluator_input_labels.len();
+    if count != circ.ninput_wires {
+        return Err(EvaluatorError::InvalidInputCount(circ.ninput_wires, count));
+    }
+
+    let mut labels: Vec<Option<Block>> = vec![None; circ.ninput_wires];
+    for input in generator_input_labels
+        .iter()
+        .chain(evaluator_input_labels.iter())
+    {
+        let label = labels
+            .get_mut(input.id)
+            .ok_or(EvaluatorError::InvalidInputId(input.id))?;
+        if label.is_some() {
+            return Err(EvaluatorError::DuplicateInputLabel(input.id));
+        }
+        *label = Some(input.label);
+    }
+    // The ids are distinct input wires and as many as the input wires, so they cover them all.
+    Ok(labels.into_iter().map(|label| label.unwrap()).collect())
 }
@@ -0,0 +1,221 @@
+//! Evaluator of the three-halves garbling scheme.
+
+use crypto_core::Block;
+
+use super::init_input_labels;
+use crate::grable::gc::{NonceCheck, TweakNonce};
+use crate::grable::three_halves::{control_mask, hash, row_label};
+use crate::EvaluatorError;
+use crate::GCEvaluator;
+use crate::GarbledCircuit;
+use crate::GateTable;
+use crate::InputLabel;
+use circuit::gate::Gate;
+
+/// Reused tweak nonces are rejected as by `HalfGateEvaluator`.
+#[derive(Debug, Default)]
+pub struct ThreeHalvesEvaluator {
+    nonces: NonceCheck,
+}
+
+impl ThreeHalvesEvaluator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn and_gate(
+        &self,
+        x: Block,
+        y: Block,
+        g: &[u64; 3],
+        control: u8,
+        nonce: TweakNonce,
+        gid: usize,
+    ) -> Block {
+        let row = 2 * x.lsb() as usize + y.lsb() as usize;
+        let hashes = [
+            hash(x, nonce, gid, 0),
+            hash(y, nonce, gid, 1),
+            hash(x ^ y, nonce, gid, 2),
+        ];
+        let r = ((control >> (2 * row)) & 3) ^ control_mask(hashes[0], hashes[1]);
+        row_label(x, y, hashes, r, g)
+    }
+
+    #[inline]
+    pub fn xor_gate(&self, x: Block, y: Block) -> Block {
+        x ^ y
+    }
+
+    #[inline]
+    pub fn inv_gate(&self, x: Block, public_one_label: Block) -> Block {
+        x ^ public_one_label
+    }
+}
+
+impl GCEvaluator for ThreeHalvesEvaluator {
+    fn eval_labels(
+        &self,
+        circ: &circuit::Circuit,
+        gc: &GarbledCircuit,
+        evaluator_input_labels: &[InputLabel],
+    ) -> Result<Vec<Block>, EvaluatorError> {
+        let input_labels =
+            init_input_labels(circ, &gc.generator_input_labels, evaluator_input_labels)?;
+        self.nonces.accept(gc.nonce)?;
+
+        let mut wire_labels: Vec<Option<Block>> = vec![None; circ.nwires];
+
+        for (wire, label) in wire_labels.iter_mut().zip(input_labels) {
+            *wire = Some(label);
+        }
+
+        let mut gid = 1;
+        for gate in circ.gates.iter() {
+            match *gate {
+                Gate::Inv { lin_id, out_id, .. } => {
+                    let x =
+                        wire_labels[lin_id].ok_or(EvaluatorError::UninitializedLabel(lin_id))?;
+                    let z = self.inv_gate(x, gc.public_one_label);
+
+                    wire_labels[out_id] = Some(z);
+                }
+                Gate::Xor {
+                    lin_id,
+                    rin_id,
+                    out_id,
+                    ..
+                } => {
+                    let x =
+                        wire_labels[lin_id].ok_or(EvaluatorError::UninitializedLabel(lin_id))?;
+                    let y =
+                        wire_labels[rin_id].ok_or(EvaluatorError::UninitializedLabel(rin_id))?;
+                    let z = self.xor_gate(x, y);
+
+                    wire_labels[out_id] = Some(z);
+                }
+                Gate::And {
+                    lin_id,
+                    rin_id,
+                    out_id,
+                    ..
+                } => {
+                    let x =
+                        wire_labels[lin_id].ok_or(EvaluatorError::UninitializedLabel(lin_id))?;
+                    let y =
+                        wire_labels[rin_id].ok_or(EvaluatorError::UninitializedLabel(rin_id))?;
+                    let (g, control) = match gc.table.get(gid - 1) {
+                        Some(GateTable::ThreeHalves(g, control)) => (g, *control),
+                        _ => return Err(EvaluatorError::InvalidTable(gid - 1)),
+                    };
+                    let z = self.and_gate(x, y, g, control, gc.nonce, gid);
+
+                    wire_labels[out_id] = Some(z);
+                    gid += 1;
+                }
+            };
+        }
+
+        ((circ.nwires - circ.noutput_wires)..circ.nwires)
+            .map(|id| wire_labels[id].ok_or(EvaluatorError::UninitializedLabel(id)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use circuit::{Circuit, CircuitInput};
+    use crypto_core::{local_channel_pair, AesRng, Block};
+    use rand::Rng;
+
+    use crate::{
+        EvaluatorError, GCEvaluator, GCGenerator, GarbledCircuit, GateTable, HalfGateEvaluator,
+        InputLabel, ThreeHalvesEvaluator, ThreeHalvesGenerator, TweakNonce,
+    };
+
+    #[test]
+    fn three_halves_and_gate_test() {
+        let mut rng = AesRng::new();
+        let gen = ThreeHalvesGenerator::new();
+        let ev = ThreeHalvesEvaluator::new();
+        let nonce = TweakNonce {
+            session: 7,
+            counter: 0,
+        };
+        for gid in 1..256 {
+            let delta = rng.gen::<Block>().set_lsb();
+            let x_0 = rng.gen::<Block>();
+            let y_0 = rng.gen::<Block>();
+            let x = [x_0, x_0 ^ delta];
+            let y = [y_0, y_0 ^ delta];
+            let (z, t) = gen.and_gate(x, y, delta, nonce, gid, rng.gen::<u8>());
+            let (g, control) = match t {
+                GateTable::ThreeHalves(g, control) => (g, control),
+                _ => unreachable!(),
+            };
+            for a in 0..2 {
+                for b in 0..2 {
+                    let label = ev.and_gate(x[a], y[b], &g, control, nonce, gid);
+                    assert_eq!(label, z[a & b]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn three_halves_aes_test() {
+        let mut input = vec![false; 128];
+        let mut key = vec![false; 128];
+
+        let mut rng = AesRng::new();
+        let circ = Circuit::load("../circuit/circuit_files/bristol/aes_128_reverse.txt").unwrap();
+        let complete_gc = ThreeHalvesGenerator::new().garble(&mut rng, &circ).unwrap();
+
+        key.reverse();
+        input.reverse();
+
+        let generator_inputs: Vec<CircuitInput> = input
+            .into_iter()
+            .enumerate()
+            .map(|(id, value)| CircuitInput {
+                id,
+                value: Block::from(value as u128),
+            })
+            .collect();
+        let gc = complete_gc.to_public(&generator_inputs);
+
+        let evaluator_input_labels: Vec<InputLabel> = key
+            .into_iter()
+            .zip(complete_gc.input_labels[128..256].iter())
+            .enumerate()
+            .map(|(id, (value, label))| InputLabel {
+                id: id + 128,
+                label: label[value as usize],
+            })
+            .collect();
+
+        // The table is sent with 25 bytes per AND gate instead of 32.
+        let (mut sender, mut receiver) = local_channel_pair();
+        let handle = thread::spawn(move || {
+            gc.send(&mut sender).unwrap();
+        });
+        let gc = GarbledCircuit::receive(&mut receiver, &circ, 128, circ.noutput_wires).unwrap();
+        handle.join().unwrap();
+        assert!(gc.table.iter().all(|t| t.size() == 25));
+
+        let mut outputs = ThreeHalvesEvaluator::new()
+            .eval(&circ, &gc, &evaluator_input_labels)
+            .unwrap()
+            .to_vec();
+        outputs.reverse();
+        assert_eq!(outputs.into_iter().map(|i| (i as u8).to_string()).collect::<String>(),
+            "01100110111010010100101111010100111011111000101000101100001110111000100001001100111110100101100111001010001101000010101100101110");
+
+        // A half-gate evaluator rejects a three-halves table.
+        let res = HalfGateEvaluator::new().eval(&circ, &gc, &evaluator_input_labels);
+        assert!(matches!(res, Err(EvaluatorError::InvalidTable(0))));
+    }
+}
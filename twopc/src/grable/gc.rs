@@ -1,8 +1,15 @@
 //! Define the struct of garbled circuit.
 //! Part of the code is derived form TLSNotary.
 
-use circuit::CircuitInput;
-use crypto_core::block::Block;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+use circuit::{Circuit, CircuitInput};
+use crypto_core::{block::Block, AbstractChannel, BitVec, SecretBlock};
+use zeroize::Zeroize;
+
+use crate::{EvaluatorError, GeneratorError};
 
 #[derive(Debug, Clone, Copy)]
 pub struct InputLabel {
@@ -12,16 +19,195 @@ pub struct InputLabel {
     pub label: Block,
 }
 
+/// Input wire label pair provided by the caller of garbling
+#[derive(Debug, Clone, Copy)]
+pub struct InputLabelPair {
+    /// Input wire label id
+    pub id: usize,
+    /// Input wire labels for 0 and 1, where `labels[1] = labels[0] xor delta`
+    pub labels: [Block; 2],
+}
+
+/// Largest number of AND gates in a garbled circuit, so that the index of every hash of a
+/// gate fits in the low 32 bits of its tweak.
+pub(crate) const MAX_AND_GATES: usize = 1 << 30;
+
+/// Nonce of the hash tweaks of a garbled circuit. `session` identifies the generator issuing
+/// the nonce and `counter` numbers the circuits it garbles, so that circuits garbled under the
+/// same delta by distinct generators, or by one generator, never share a tweak.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TweakNonce {
+    /// Session id of the generator
+    pub session: u64,
+    /// Number of the circuit within the session
+    pub counter: u32,
+}
+
+impl TweakNonce {
+    pub(crate) fn write<C: AbstractChannel>(&self, channel: &mut C) -> std::io::Result<()> {
+        channel.write_bytes(&self.session.to_le_bytes())?;
+        channel.write_bytes(&self.counter.to_le_bytes())
+    }
+
+    pub(crate) fn read<C: AbstractChannel>(channel: &mut C) -> std::io::Result<Self> {
+        let mut session = [0u8; 8];
+        channel.read_bytes(&mut session)?;
+        let mut counter = [0u8; 4];
+        channel.read_bytes(&mut counter)?;
+        Ok(Self {
+            session: u64::from_le_bytes(session),
+            counter: u32::from_le_bytes(counter),
+        })
+    }
+}
+
+/// Tweak of the `index`-th hash of a garbled circuit with `nonce`, i.e., `session || counter ||
+/// index` with 64, 32 and 32 bits. `index` is below `2^32` for circuits of at most
+/// `MAX_AND_GATES` AND gates.
+#[inline]
+pub(crate) fn gate_tweak(nonce: TweakNonce, index: usize) -> Block {
+    Block::from(((nonce.session as u128) << 64) | ((nonce.counter as u128) << 32) | index as u128)
+}
+
+/// Tweak nonces issued by a generator. Its session id is sampled at random unless the caller
+/// provides one, e.g., derived from the transcript of the OT fixing delta, and each circuit
+/// takes the next counter of the session.
+#[derive(Debug)]
+pub(crate) struct NonceCounter {
+    session: u64,
+    counter: AtomicU32,
+}
+
+impl NonceCounter {
+    /// New a counter of session `session`.
+    pub(crate) fn new(session: u64) -> Self {
+        Self {
+            session,
+            counter: AtomicU32::new(0),
+        }
+    }
+
+    /// Return the session id.
+    #[inline]
+    pub(crate) fn session(&self) -> u64 {
+        self.session
+    }
+
+    /// Return the next nonce, checking that `circ` is small enough for its tweaks.
+    #[inline]
+    pub(crate) fn next(&self, circ: &Circuit) -> Result<TweakNonce, GeneratorError> {
+        if circ.nand > MAX_AND_GATES {
+            return Err(GeneratorError::CircuitTooLarge(circ.nand));
+        }
+        let counter = self
+            .counter
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |c| c.checked_add(1))
+            .map_err(|_| GeneratorError::NoncesExhausted(self.session))?;
+        Ok(TweakNonce {
+            session: self.session,
+            counter,
+        })
+    }
+}
+
+/// Tweak nonces accepted by an evaluator. A garbled circuit is only accepted if its counter is
+/// larger than the counters of all circuits of the same session accepted before, so a reused
+/// nonce is rejected.
+#[derive(Debug, Default)]
+pub(crate) struct NonceCheck(Mutex<HashMap<u64, u64>>);
+
+impl NonceCheck {
+    /// Accept `nonce` if it is fresh.
+    #[inline]
+    pub(crate) fn accept(&self, nonce: TweakNonce) -> Result<(), EvaluatorError> {
+        let mut sessions = self.0.lock().unwrap();
+        let next = sessions.entry(nonce.session).or_insert(0);
+        if (nonce.counter as u64) < *next {
+            return Err(EvaluatorError::ReusedNonce(nonce));
+        }
+        *next = nonce.counter as u64 + 1;
+        Ok(())
+    }
+}
+
+/// Garbled table of an AND gate, whose size depends on the garbling scheme
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GateTable {
+    /// Half gates: two ciphertexts of one block each
+    HalfGate([Block; 2]),
+    /// Three halves: three half-block ciphertexts and the encrypted 2-bit control values of
+    /// the four rows
+    ThreeHalves([u64; 3], u8),
+}
+
+impl GateTable {
+    /// Size of the gate ciphertexts in bytes
+    pub fn size(&self) -> usize {
+        match self {
+            GateTable::HalfGate(_) => 32,
+            GateTable::ThreeHalves(..) => 25,
+        }
+    }
+
+    fn tag(&self) -> u8 {
+        match self {
+            GateTable::HalfGate(_) => 0,
+            GateTable::ThreeHalves(..) => 1,
+        }
+    }
+
+    fn write<C: AbstractChannel>(&self, channel: &mut C) -> std::io::Result<()> {
+        match self {
+            GateTable::HalfGate(t) => {
+                channel.write_block(&t[0])?;
+                channel.write_block(&t[1])
+            }
+            GateTable::ThreeHalves(g, r) => {
+                let mut bytes = [0u8; 25];
+                for (chunk, g) in bytes.chunks_exact_mut(8).zip(g.iter()) {
+                    chunk.copy_from_slice(&g.to_le_bytes());
+                }
+                bytes[24] = *r;
+                channel.write_bytes(&bytes)
+            }
+        }
+    }
+
+    fn read<C: AbstractChannel>(channel: &mut C, tag: u8) -> std::io::Result<Self> {
+        match tag {
+            0 => Ok(GateTable::HalfGate([
+                channel.read_block()?,
+                channel.read_block()?,
+            ])),
+            1 => {
+                let mut bytes = [0u8; 25];
+                channel.read_bytes(&mut bytes)?;
+                let mut g = [0u64; 3];
+                for (g, chunk) in g.iter_mut().zip(bytes.chunks_exact(8)) {
+                    *g = u64::from_le_bytes(chunk.try_into().unwrap());
+                }
+                Ok(GateTable::ThreeHalves(g, bytes[24]))
+            }
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "unknown garbled table type",
+            )),
+        }
+    }
+}
+
 /// Complete garbled circuit data, including private data which should not be revealed
-/// to the evaluator
+/// to the evaluator. The labels and `delta` are zeroized on drop.
 #[derive(Debug, Clone)]
 pub struct CompleteGarbledCircuit {
     pub input_labels: Vec<[Block; 2]>,
     pub wire_labels: Vec<[Block; 2]>,
-    pub table: Vec<[Block; 2]>,
-    pub output_bits: Vec<bool>,
+    pub table: Vec<GateTable>,
+    pub output_bits: BitVec,
     pub public_one_label: Block,
-    pub delta: Block,
+    pub delta: SecretBlock,
+    /// Nonce of the hash tweaks, issued by the generator
+    pub nonce: TweakNonce,
 }
 
 /// Garbled circuit data safe to share with evaluator
@@ -29,23 +215,26 @@ pub struct CompleteGarbledCircuit {
 pub struct GarbledCircuit {
     /// Wire labels corresponding to the generators input bits
     pub generator_input_labels: Vec<InputLabel>,
-    /// Truth table for garbled AND gates
-    pub table: Vec<[Block; 2]>,
+    /// Truth table for garbled AND gates, all of the same garbling scheme
+    pub table: Vec<GateTable>,
     /// Wire labels corresponding to public one
     /// public_one_label = random_label xor delta
     pub public_one_label: Block,
     /// LSBs of output labels
-    pub output_bits: Vec<bool>,
+    pub output_bits: BitVec,
+    /// Nonce of the hash tweaks
+    pub nonce: TweakNonce,
 }
 
 impl CompleteGarbledCircuit {
     pub fn new(
         input_labels: Vec<[Block; 2]>,
         wire_labels: Vec<[Block; 2]>,
-        table: Vec<[Block; 2]>,
-        output_bits: Vec<bool>,
+        table: Vec<GateTable>,
+        output_bits: BitVec,
         public_one_label: Block,
-        delta: Block,
+        delta: SecretBlock,
+        nonce: TweakNonce,
     ) -> Self {
         Self {
             input_labels,
@@ -54,6 +243,7 @@ impl CompleteGarbledCircuit {
             output_bits,
             public_one_label,
             delta,
+            nonce,
         }
     }
 
@@ -71,6 +261,68 @@ impl CompleteGarbledCircuit {
             table: self.table.clone(),
             output_bits: self.output_bits.clone(),
             public_one_label: self.public_one_label,
+            nonce: self.nonce,
+        }
+    }
+}
+
+impl Drop for CompleteGarbledCircuit {
+    fn drop(&mut self) {
+        self.input_labels.zeroize();
+        self.wire_labels.zeroize();
+        self.public_one_label.zeroize();
+    }
+}
+
+impl GarbledCircuit {
+    /// Send the garbled circuit to the evaluator. The generator input labels are sent in the
+    /// order of their ids, and the table is preceded by one byte for its garbling scheme.
+    pub fn send<C: AbstractChannel>(&self, channel: &mut C) -> std::io::Result<()> {
+        self.nonce.write(channel)?;
+        for label in self.generator_input_labels.iter() {
+            channel.write_block(&label.label)?;
         }
+        let tag = self.table.first().map_or(0, |t| t.tag());
+        channel.write_bytes(&[tag])?;
+        for t in self.table.iter() {
+            t.write(channel)?;
+        }
+        channel.write_block(&self.public_one_label)?;
+        channel.write_bits(&self.output_bits)?;
+        channel.flush()
+    }
+
+    /// Receive a garbled circuit of `circ` from the generator, whose inputs are the first
+    /// `ngenerator_inputs` input wires. `noutput_bits` is either the number of output wires, or
+    /// 0 if the generator withholds the decoding bits (see [`crate::grable::output`]).
+    pub fn receive<C: AbstractChannel>(
+        channel: &mut C,
+        circ: &Circuit,
+        ngenerator_inputs: usize,
+        noutput_bits: usize,
+    ) -> std::io::Result<Self> {
+        let nonce = TweakNonce::read(channel)?;
+        let generator_input_labels = (0..ngenerator_inputs)
+            .map(|id| {
+                Ok(InputLabel {
+                    id,
+                    label: channel.read_block()?,
+                })
+            })
+            .collect::<std::io::Result<_>>()?;
+        let mut tag = [0u8];
+        channel.read_bytes(&mut tag)?;
+        let table = (0..circ.nand)
+            .map(|_| GateTable::read(channel, tag[0]))
+            .collect::<std::io::Result<_>>()?;
+        let public_one_label = channel.read_block()?;
+        let output_bits = channel.read_bits(noutput_bits)?;
+        Ok(Self {
+            generator_input_labels,
+            table,
+            public_one_label,
+            output_bits,
+            nonce,
+        })
     }
 }
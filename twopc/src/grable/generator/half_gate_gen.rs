@@ -1,39 +1,77 @@
-use super::{GCGenerator, GeneratorError};
-use crate::CompleteGarbledCircuit;
+use super::{init_input_labels, GCGenerator, GeneratorError};
+use crate::grable::gc::{gate_tweak, NonceCounter, TweakNonce};
+use crate::{CompleteGarbledCircuit, GateTable, InputLabelPair};
 use circuit::gate::{Circuit, Gate};
 use crypto_core::{
     block::{Block, SELECT_MASK},
-    AES_HASH,
+    BitVec, SecretBlock, AES_HASH,
 };
 use rand::{CryptoRng, Rng};
 
-pub struct HalfGateGenerator;
+/// The generator issues the tweak nonce of each circuit it garbles, made of its session id and
+/// a counter.
+#[derive(Debug)]
+pub struct HalfGateGenerator {
+    nonces: NonceCounter,
+}
 
 impl HalfGateGenerator {
+    /// New a generator with a random session id, so that generators garbling under the same
+    /// delta use distinct tweaks except with probability about `n^2 / 2^65` for `n` generators.
+    pub fn new() -> Self {
+        Self::with_session(rand::random::<u64>())
+    }
+
+    /// New a generator with session id `session`, e.g., derived from the transcript of the OT
+    /// fixing delta. The session id must be unique among the generators using the same delta.
+    pub fn with_session(session: u64) -> Self {
+        Self {
+            nonces: NonceCounter::new(session),
+        }
+    }
+
+    /// Return the session id of the tweak nonces.
+    #[inline]
+    pub fn session(&self) -> u64 {
+        self.nonces.session()
+    }
+
+    /// Issue the tweak nonce of the next circuit `circ`.
+    #[inline]
+    pub(crate) fn next_nonce(&self, circ: &Circuit) -> Result<TweakNonce, GeneratorError> {
+        self.nonces.next(circ)
+    }
+
     #[inline]
     pub fn and_gate(
         &self,
         x: [Block; 2],
         y: [Block; 2],
         delta: Block,
+        nonce: TweakNonce,
         gid: usize,
     ) -> ([Block; 2], [Block; 2]) {
         let pa = x[0].lsb() as usize;
         let pb = y[0].lsb() as usize;
 
-        let index = gid as u128;
-        let index_next = (gid + 1) as u128;
+        // Each half gate has its own tweak
+        let tweak = gate_tweak(nonce, 2 * gid);
+        let tweak_next = gate_tweak(nonce, 2 * gid + 1);
 
-        let hash_x0 = AES_HASH.tccr_hash(index.into(), x[0]);
-        let hash_y0 = AES_HASH.tccr_hash(index_next.into(), y[0]);
+        // Four blocks are below the eight the AES pipeline needs, so they are hashed one by one
+        // (see `AesHash::tccr_hash_many`).
+        let hash_x0 = AES_HASH.tccr_hash(tweak, x[0]);
+        let hash_x1 = AES_HASH.tccr_hash(tweak, x[1]);
+        let hash_y0 = AES_HASH.tccr_hash(tweak_next, y[0]);
+        let hash_y1 = AES_HASH.tccr_hash(tweak_next, y[1]);
 
         // First half gate: garbler knows pb
-        let t_g = hash_x0 ^ AES_HASH.tccr_hash(index.into(), x[1]) ^ (SELECT_MASK[pb] & delta);
+        let t_g = hash_x0 ^ hash_x1 ^ (SELECT_MASK[pb] & delta);
         // Output label w_g for wire 0
         let w_g = hash_x0 ^ (SELECT_MASK[pa] & t_g);
 
         // Second half gate: evaluator knows (pb xor b)
-        let t_e = hash_y0 ^ AES_HASH.tccr_hash(index_next.into(), y[1]) ^ x[0];
+        let t_e = hash_y0 ^ hash_y1 ^ x[0];
         // Output label w_e for wire 0
         let w_e = hash_y0 ^ (SELECT_MASK[pb] & (t_e ^ x[0]));
 
@@ -55,31 +93,32 @@ impl HalfGateGenerator {
     }
 }
 
+impl Default for HalfGateGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl GCGenerator for HalfGateGenerator {
-    fn garble<R: Rng + CryptoRng>(
+    fn garble_with_labels<R: Rng + CryptoRng>(
         &self,
         rng: &mut R,
         circ: &Circuit,
+        delta: Block,
+        input_labels: &[InputLabelPair],
     ) -> Result<CompleteGarbledCircuit, GeneratorError> {
-        // Generate random delta
-        let mut delta = rng.gen::<Block>();
-        // Set delta lsb to 1
-        delta = delta.set_lsb();
+        let input_labels = init_input_labels(rng, circ, delta, input_labels)?;
+        let nonce = self.next_nonce(circ)?;
 
         // Generate a random label for public 1.
         let public_one_label = rng.gen::<Block>() ^ delta;
 
-        let mut input_labels: Vec<[Block; 2]> = Vec::with_capacity(circ.ninput_wires);
-        let mut table: Vec<[Block; 2]> = Vec::with_capacity(circ.nand);
+        let mut table: Vec<GateTable> = Vec::with_capacity(circ.nand);
         let mut wire_labels: Vec<Option<[Block; 2]>> = vec![None; circ.nwires];
 
         // Initiate input labels
-        for wire in wire_labels.iter_mut().take(circ.ninput_wires) {
-            let z_0 = rng.gen::<Block>();
-            let z_1 = z_0 ^ delta;
-            let z = [z_0, z_1];
-            input_labels.push(z);
-            *wire = Some(z);
+        for (wire, z) in wire_labels.iter_mut().zip(input_labels.iter()) {
+            *wire = Some(*z);
         }
 
         // Process each gate
@@ -116,15 +155,15 @@ impl GCGenerator for HalfGateGenerator {
                         wire_labels[lin_id].ok_or(GeneratorError::UninitializedLabel(lin_id))?;
                     let y =
                         wire_labels[rin_id].ok_or(GeneratorError::UninitializedLabel(rin_id))?;
-                    let (z, t) = self.and_gate(x, y, delta, gid);
-                    table.push(t);
+                    let (z, t) = self.and_gate(x, y, delta, nonce, gid);
+                    table.push(GateTable::HalfGate(t));
                     wire_labels[out_id] = Some(z);
                     gid += 1;
                 }
             };
         }
 
-        let mut output_bits: Vec<bool> = Vec::with_capacity(circ.noutput_wires);
+        let mut output_bits = BitVec::with_capacity(circ.noutput_wires);
         for wire in wire_labels.iter().skip(circ.nwires - circ.noutput_wires) {
             output_bits.push(wire.unwrap()[0].lsb());
         }
@@ -135,7 +174,8 @@ impl GCGenerator for HalfGateGenerator {
             table,
             output_bits,
             public_one_label,
-            delta,
+            SecretBlock::new(delta),
+            nonce,
         ))
     }
 }
@@ -0,0 +1,153 @@
+use super::{init_input_labels, GeneratorError, HalfGateGenerator};
+use crate::grable::gc::TweakNonce;
+use crate::grable::wires::LiveWires;
+use crate::InputLabelPair;
+use circuit::gate::{Circuit, Gate};
+use crypto_core::{block::Block, AbstractChannel, BitVec, SecretBlock};
+use rand::{CryptoRng, Rng};
+use zeroize::Zeroize;
+
+/// Half-gate generator that streams the garbled tables to the evaluator gate by gate,
+/// instead of building the complete garbled circuit in memory.
+///
+/// The stream consists of the nonce of the hash tweaks, the public one label, the table of each
+/// AND gate in circuit order, and the output decoding bits. The labels and `delta` are
+/// zeroized on drop.
+pub struct HalfGateStreamGenerator {
+    delta: SecretBlock,
+    nonce: TweakNonce,
+    public_one_label: Block,
+    input_labels: Vec<[Block; 2]>,
+}
+
+impl Drop for HalfGateStreamGenerator {
+    fn drop(&mut self) {
+        self.public_one_label.zeroize();
+        self.input_labels.zeroize();
+    }
+}
+
+impl HalfGateStreamGenerator {
+    /// Sample `delta`, the public one label and the input labels of `circ`, with the tweak
+    /// nonce issued by `gen`.
+    pub fn new<R: Rng + CryptoRng>(
+        gen: &HalfGateGenerator,
+        rng: &mut R,
+        circ: &Circuit,
+    ) -> Result<Self, GeneratorError> {
+        // Generate random delta and set its lsb to 1
+        let delta = rng.gen::<Block>().set_lsb();
+        Self::with_labels(gen, rng, circ, delta, &[])
+    }
+
+    /// Use global `delta` and a subset of input label pairs provided by the caller, and sample
+    /// the public one label and the remaining input labels. The tweak nonce is issued by `gen`.
+    pub fn with_labels<R: Rng + CryptoRng>(
+        gen: &HalfGateGenerator,
+        rng: &mut R,
+        circ: &Circuit,
+        delta: Block,
+        input_labels: &[InputLabelPair],
+    ) -> Result<Self, GeneratorError> {
+        let input_labels = init_input_labels(rng, circ, delta, input_labels)?;
+        let public_one_label = rng.gen::<Block>() ^ delta;
+        let nonce = gen.next_nonce(circ)?;
+
+        Ok(Self {
+            delta: SecretBlock::new(delta),
+            nonce,
+            public_one_label,
+            input_labels,
+        })
+    }
+
+    /// Return the global `delta`.
+    pub fn delta(&self) -> &SecretBlock {
+        &self.delta
+    }
+
+    /// Return the label pairs of all input wires.
+    pub fn input_labels(&self) -> &[[Block; 2]] {
+        &self.input_labels
+    }
+
+    /// Garble `circ` and write the garbled tables to `channel` as they are produced.
+    /// Return the label pairs of the output wires. The generator is consumed, so its labels
+    /// and tweak nonce are garbled only once.
+    pub fn garble<C: AbstractChannel>(
+        self,
+        channel: &mut C,
+        circ: &Circuit,
+    ) -> Result<Vec<[Block; 2]>, GeneratorError> {
+        let gen = HalfGateGenerator::with_session(self.nonce.session);
+        let delta = *self.delta.expose();
+        let mut wire_labels: LiveWires<[Block; 2]> = LiveWires::new(circ);
+
+        for (id, label) in self.input_labels.iter().enumerate() {
+            wire_labels.insert(id, *label, true);
+        }
+
+        self.nonce.write(channel)?;
+        channel.write_block(&self.public_one_label)?;
+
+        let mut gid = 1;
+        for (gate, live) in circ.gates.iter().zip(circ.liveness()) {
+            match *gate {
+                Gate::Inv { lin_id, out_id, .. } => {
+                    let x = wire_labels
+                        .take(lin_id, live.lin_last)
+                        .ok_or(GeneratorError::UninitializedLabel(lin_id))?;
+                    let z = gen.inv_gate(x, self.public_one_label, delta);
+                    wire_labels.insert(out_id, z, live.out_used);
+                }
+                Gate::Xor {
+                    lin_id,
+                    rin_id,
+                    out_id,
+                    ..
+                } => {
+                    let x = wire_labels
+                        .take(lin_id, live.lin_last)
+                        .ok_or(GeneratorError::UninitializedLabel(lin_id))?;
+                    let y = wire_labels
+                        .take(rin_id, live.rin_last)
+                        .ok_or(GeneratorError::UninitializedLabel(rin_id))?;
+                    let z = gen.xor_gate(x, y, delta);
+                    wire_labels.insert(out_id, z, live.out_used);
+                }
+                Gate::And {
+                    lin_id,
+                    rin_id,
+                    out_id,
+                    ..
+                } => {
+                    let x = wire_labels
+                        .take(lin_id, live.lin_last)
+                        .ok_or(GeneratorError::UninitializedLabel(lin_id))?;
+                    let y = wire_labels
+                        .take(rin_id, live.rin_last)
+                        .ok_or(GeneratorError::UninitializedLabel(rin_id))?;
+                    let (z, t) = gen.and_gate(x, y, delta, self.nonce, gid);
+                    channel.write_block(&t[0])?;
+                    channel.write_block(&t[1])?;
+                    wire_labels.insert(out_id, z, live.out_used);
+                    gid += 1;
+                }
+            };
+        }
+
+        let output_labels = ((circ.nwires - circ.noutput_wires)..circ.nwires)
+            .map(|id| {
+                wire_labels
+                    .output(id)
+                    .ok_or(GeneratorError::UninitializedLabel(id))
+            })
+            .collect::<Result<Vec<[Block; 2]>, GeneratorError>>()?;
+
+        let output_bits: BitVec = output_labels.iter().map(|z| z[0].lsb()).collect();
+        channel.write_bits(&output_bits)?;
+        channel.flush()?;
+
+        Ok(output_labels)
+    }
+}
@@ -1,10 +1,15 @@
 pub mod half_gate_gen;
+pub mod half_gate_stream_gen;
+pub mod three_halves_gen;
 
 pub use half_gate_gen::*;
+pub use half_gate_stream_gen::*;
+pub use three_halves_gen::*;
 
 use super::errors::GeneratorError;
-use crate::gc::CompleteGarbledCircuit;
+use crate::gc::{CompleteGarbledCircuit, InputLabelPair};
 use circuit::Circuit;
+use crypto_core::Block;
 use rand::{CryptoRng, Rng};
 
 pub trait GCGenerator {
@@ -13,5 +18,58 @@ pub trait GCGenerator {
         &self,
         rng: &mut R,
         circ: &Circuit,
+    ) -> Result<CompleteGarbledCircuit, GeneratorError> {
+        // Generate random delta and set its lsb to 1
+        let delta = rng.gen::<Block>().set_lsb();
+        self.garble_with_labels(rng, circ, delta, &[])
+    }
+
+    /// Generate a garbled circuit with global `delta` and a subset of input label pairs
+    /// provided by the caller, e.g., fixed by a correlated OT protocol. The remaining input
+    /// labels are sampled from `rng`. The tweaks of the hashes are bound to the session id of the
+    /// generator and its next counter, so circuits garbled under `delta` never share a tweak.
+    fn garble_with_labels<R: Rng + CryptoRng>(
+        &self,
+        rng: &mut R,
+        circ: &Circuit,
+        delta: Block,
+        input_labels: &[InputLabelPair],
     ) -> Result<CompleteGarbledCircuit, GeneratorError>;
 }
+
+/// Build the label pairs of all input wires of `circ` from the provided `input_labels`,
+/// sampling the missing ones. Check that `delta` and the provided labels are consistent.
+pub(crate) fn init_input_labels<R: Rng + CryptoRng>(
+    rng: &mut R,
+    circ: &Circuit,
+    delta: Block,
+    input_labels: &[InputLabelPair],
+) -> Result<Vec<[Block; 2]>, GeneratorError> {
+    if !delta.lsb() {
+        return Err(GeneratorError::InvalidDelta);
+    }
+
+    let mut labels: Vec<Option<[Block; 2]>> = vec![None; circ.ninput_wires];
+    for pair in input_labels.iter() {
+        let label = labels
+            .get_mut(pair.id)
+            .ok_or(GeneratorError::InvalidInputId(pair.id))?;
+        if label.is_some() {
+            return Err(GeneratorError::DuplicateInputLabel(pair.id));
+        }
+        if pair.labels[0] ^ delta != pair.labels[1] {
+            return Err(GeneratorError::InconsistentInputLabel(pair.id));
+        }
+        *label = Some(pair.labels);
+    }
+
+    Ok(labels
+        .into_iter()
+        .map(|label| {
+            label.unwrap_or_else(|| {
+                let z_0 = rng.gen::<Block>();
+                [z_0, z_0 ^ delta]
+            })
+        })
+        .collect())
+}
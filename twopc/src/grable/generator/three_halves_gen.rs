@@ -0,0 +1,176 @@
+//! Generator of the three-halves garbling scheme, whose AND gates take 1.5 blocks and 8 control
+//! bits instead of the 2 blocks of half gates.
+
+use super::{init_input_labels, GCGenerator, GeneratorError};
+use crate::grable::gc::{NonceCounter, TweakNonce};
+use crate::grable::three_halves::{control_mask, hash, row_label, solve, CONTROL_OFFSET};
+use crate::{CompleteGarbledCircuit, GateTable, InputLabelPair};
+use circuit::gate::{Circuit, Gate};
+use crypto_core::{BitVec, Block, SecretBlock};
+use rand::{CryptoRng, Rng};
+
+/// Tweak nonces are issued as by `HalfGateGenerator`.
+#[derive(Debug)]
+pub struct ThreeHalvesGenerator {
+    nonces: NonceCounter,
+}
+
+impl ThreeHalvesGenerator {
+    /// New a generator with a random session id, as `HalfGateGenerator::new`.
+    pub fn new() -> Self {
+        Self::with_session(rand::random::<u64>())
+    }
+
+    /// New a generator with session id `session`, which must be unique among the generators
+    /// using the same delta.
+    pub fn with_session(session: u64) -> Self {
+        Self {
+            nonces: NonceCounter::new(session),
+        }
+    }
+
+    /// Return the session id of the tweak nonces.
+    #[inline]
+    pub fn session(&self) -> u64 {
+        self.nonces.session()
+    }
+
+    /// Garble an AND gate, where `s` selects one of the four consistent control assignments.
+    #[inline]
+    pub fn and_gate(
+        &self,
+        x: [Block; 2],
+        y: [Block; 2],
+        delta: Block,
+        nonce: TweakNonce,
+        gid: usize,
+        s: u8,
+    ) -> ([Block; 2], GateTable) {
+        let pa = x[0].lsb() as usize;
+        let pb = y[0].lsb() as usize;
+
+        let hash_x = [hash(x[0], nonce, gid, 0), hash(x[1], nonce, gid, 0)];
+        let hash_y = [hash(y[0], nonce, gid, 1), hash(y[1], nonce, gid, 1)];
+        let hash_xy = [
+            hash(x[0] ^ y[0], nonce, gid, 2),
+            hash(x[0] ^ y[0] ^ delta, nonce, gid, 2),
+        ];
+
+        // Output label of each row with zero ciphertexts, and the encrypted control bits
+        let mut k = [Block::default(); 4];
+        let mut control = 0u8;
+        for (row, k) in k.iter_mut().enumerate() {
+            // Truth values of the row with colors (i, j)
+            let a = (row >> 1) ^ pa;
+            let b = (row & 1) ^ pb;
+            let r = (s & 3) ^ CONTROL_OFFSET[2 * pa + pb][row];
+            let hashes = [hash_x[a], hash_y[b], hash_xy[a ^ b]];
+            *k = row_label(x[a], y[b], hashes, r, &[0; 3]);
+            if a & b == 1 {
+                *k ^= delta;
+            }
+            control |= (r ^ control_mask(hash_x[a], hash_y[b])) << (2 * row);
+        }
+
+        let (z_0, g) = solve(&k);
+        ([z_0, z_0 ^ delta], GateTable::ThreeHalves(g, control))
+    }
+
+    #[inline]
+    pub fn xor_gate(&self, x: [Block; 2], y: [Block; 2], delta: Block) -> [Block; 2] {
+        let z_0 = x[0] ^ y[0];
+        [z_0, z_0 ^ delta]
+    }
+
+    #[inline]
+    pub fn inv_gate(&self, x: [Block; 2], public_one_label: Block, delta: Block) -> [Block; 2] {
+        self.xor_gate(x, [public_one_label ^ delta, public_one_label], delta)
+    }
+}
+
+impl Default for ThreeHalvesGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GCGenerator for ThreeHalvesGenerator {
+    fn garble_with_labels<R: Rng + CryptoRng>(
+        &self,
+        rng: &mut R,
+        circ: &Circuit,
+        delta: Block,
+        input_labels: &[InputLabelPair],
+    ) -> Result<CompleteGarbledCircuit, GeneratorError> {
+        let input_labels = init_input_labels(rng, circ, delta, input_labels)?;
+        let nonce = self.nonces.next(circ)?;
+
+        // Generate a random label for public 1.
+        let public_one_label = rng.gen::<Block>() ^ delta;
+
+        let mut table: Vec<GateTable> = Vec::with_capacity(circ.nand);
+        let mut wire_labels: Vec<Option<[Block; 2]>> = vec![None; circ.nwires];
+
+        // Initiate input labels
+        for (wire, z) in wire_labels.iter_mut().zip(input_labels.iter()) {
+            *wire = Some(*z);
+        }
+
+        // Process each gate
+        let mut gid = 1;
+        for gate in circ.gates.iter() {
+            match *gate {
+                Gate::Inv { lin_id, out_id, .. } => {
+                    let x =
+                        wire_labels[lin_id].ok_or(GeneratorError::UninitializedLabel(lin_id))?;
+
+                    let z = self.inv_gate(x, public_one_label, delta);
+                    wire_labels[out_id] = Some(z);
+                }
+                Gate::Xor {
+                    lin_id,
+                    rin_id,
+                    out_id,
+                    ..
+                } => {
+                    let x =
+                        wire_labels[lin_id].ok_or(GeneratorError::UninitializedLabel(lin_id))?;
+                    let y =
+                        wire_labels[rin_id].ok_or(GeneratorError::UninitializedLabel(rin_id))?;
+                    let z = self.xor_gate(x, y, delta);
+                    wire_labels[out_id] = Some(z);
+                }
+                Gate::And {
+                    lin_id,
+                    rin_id,
+                    out_id,
+                    ..
+                } => {
+                    let x =
+                        wire_labels[lin_id].ok_or(GeneratorError::UninitializedLabel(lin_id))?;
+                    let y =
+                        wire_labels[rin_id].ok_or(GeneratorError::UninitializedLabel(rin_id))?;
+                    let (z, t) = self.and_gate(x, y, delta, nonce, gid, rng.gen::<u8>());
+                    table.push(t);
+                    wire_labels[out_id] = Some(z);
+                    gid += 1;
+                }
+            };
+        }
+
+        let mut output_bits = BitVec::with_capacity(circ.noutput_wires);
+        for wire in wire_labels.iter().skip(circ.nwires - circ.noutput_wires) {
+            output_bits.push(wire.unwrap()[0].lsb());
+        }
+
+        Ok(CompleteGarbledCircuit::new(
+            input_labels,
+            wire_labels.into_iter().map(|w| w.unwrap()).collect(),
+            table,
+            output_bits,
+            public_one_label,
+            SecretBlock::new(delta),
+            nonce,
+        ))
+    }
+}
@@ -2,8 +2,14 @@ pub mod errors;
 pub mod evaluator;
 pub mod gc;
 pub mod generator;
+pub mod output;
+pub mod session;
+mod three_halves;
+mod wires;
 
 pub use errors::*;
 pub use evaluator::*;
 pub use gc::*;
 pub use generator::*;
+pub use output::*;
+pub use session::*;
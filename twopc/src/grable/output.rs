@@ -0,0 +1,291 @@
+//! Decoding of output wires with per-wire policies.
+//!
+//! The generator only sends the decoding bits of wires revealed to the evaluator. For wires
+//! revealed to the generator, the evaluator sends back the active output labels, and the
+//! generator checks that each label is one of the two labels of the wire, so a cheating
+//! evaluator cannot forge an output. Shared wires are never decoded: the generator's share is
+//! the lsb of the zero label, and the evaluator's share is the lsb of the active label.
+
+use crypto_core::{AbstractChannel, BitVec, Block};
+use subtle::ConstantTimeEq;
+
+use crate::{EvaluatorError, GeneratorError};
+
+/// Decoding policy of an output wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputPolicy {
+    /// The output is revealed to the evaluator only.
+    Evaluator,
+    /// The output is revealed to the generator only.
+    Generator,
+    /// The output is revealed to both parties.
+    Both,
+    /// The output is kept as XOR shares between the two parties.
+    Shared,
+}
+
+impl OutputPolicy {
+    /// Whether the evaluator learns the output.
+    #[inline]
+    pub fn to_evaluator(&self) -> bool {
+        matches!(self, OutputPolicy::Evaluator | OutputPolicy::Both)
+    }
+
+    /// Whether the generator learns the output.
+    #[inline]
+    pub fn to_generator(&self) -> bool {
+        matches!(self, OutputPolicy::Generator | OutputPolicy::Both)
+    }
+}
+
+/// Output label pairs of the generator with their decoding policies.
+pub struct GeneratorOutput {
+    labels: Vec<[Block; 2]>,
+    policies: Vec<OutputPolicy>,
+}
+
+impl GeneratorOutput {
+    pub fn new(labels: Vec<[Block; 2]>, policies: &[OutputPolicy]) -> Result<Self, GeneratorError> {
+        if labels.len() != policies.len() {
+            return Err(GeneratorError::InvalidOutputCount(
+                labels.len(),
+                policies.len(),
+            ));
+        }
+        Ok(Self {
+            labels,
+            policies: policies.to_vec(),
+        })
+    }
+
+    /// Return the decoding bits of the wires revealed to the evaluator.
+    pub fn decoding_bits(&self) -> BitVec {
+        self.labels
+            .iter()
+            .zip(self.policies.iter())
+            .filter(|(_, policy)| policy.to_evaluator())
+            .map(|(z, _)| z[0].lsb())
+            .collect()
+    }
+
+    /// Decode the output with the active labels sent back by the evaluator for the wires
+    /// revealed to the generator. Return the output bit (or the share of a shared wire) of each
+    /// wire, and `None` for wires revealed to the evaluator only.
+    pub fn decode(&self, returned_labels: &[Block]) -> Result<Vec<Option<bool>>, GeneratorError> {
+        let nreturned = self.policies.iter().filter(|p| p.to_generator()).count();
+        if returned_labels.len() != nreturned {
+            return Err(GeneratorError::InvalidOutputCount(
+                nreturned,
+                returned_labels.len(),
+            ));
+        }
+
+        let mut returned = returned_labels.iter();
+        self.labels
+            .iter()
+            .zip(self.policies.iter())
+            .enumerate()
+            .map(|(i, (z, policy))| match policy {
+                OutputPolicy::Evaluator => Ok(None),
+                OutputPolicy::Shared => Ok(Some(z[0].lsb())),
+                OutputPolicy::Generator | OutputPolicy::Both => {
+                    let label = returned.next().unwrap();
+                    let is_zero = label.ct_eq(&z[0]);
+                    let is_one = label.ct_eq(&z[1]);
+                    if bool::from(is_zero | is_one) {
+                        Ok(Some(bool::from(is_one)))
+                    } else {
+                        Err(GeneratorError::InvalidOutputLabel(i))
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Send the decoding bits to the evaluator, receive the returned labels and decode the output.
+    pub fn run<C: AbstractChannel>(
+        &self,
+        channel: &mut C,
+    ) -> Result<Vec<Option<bool>>, GeneratorError> {
+        let decoding_bits = self.decoding_bits();
+        if !decoding_bits.is_empty() {
+            channel.write_bits(&decoding_bits)?;
+            channel.flush()?;
+        }
+
+        let nreturned = self.policies.iter().filter(|p| p.to_generator()).count();
+        let returned_labels = (0..nreturned)
+            .map(|_| channel.read_block())
+            .collect::<std::io::Result<Vec<Block>>>()?;
+        self.decode(&returned_labels)
+    }
+}
+
+/// Active output labels of the evaluator with their decoding policies.
+pub struct EvaluatorOutput {
+    labels: Vec<Block>,
+    policies: Vec<OutputPolicy>,
+}
+
+impl EvaluatorOutput {
+    pub fn new(labels: Vec<Block>, policies: &[OutputPolicy]) -> Result<Self, EvaluatorError> {
+        if labels.len() != policies.len() {
+            return Err(EvaluatorError::InvalidOutputCount(
+                labels.len(),
+                policies.len(),
+            ));
+        }
+        Ok(Self {
+            labels,
+            policies: policies.to_vec(),
+        })
+    }
+
+    /// Return the active labels of the wires revealed to the generator.
+    pub fn returned_labels(&self) -> Vec<Block> {
+        self.labels
+            .iter()
+            .zip(self.policies.iter())
+            .filter(|(_, policy)| policy.to_generator())
+            .map(|(label, _)| *label)
+            .collect()
+    }
+
+    /// Decode the output with the decoding bits of the wires revealed to the evaluator. Return
+    /// the output bit (or the share of a shared wire) of each wire, and `None` for wires
+    /// revealed to the generator only.
+    pub fn decode(&self, decoding_bits: &BitVec) -> Result<Vec<Option<bool>>, EvaluatorError> {
+        let ndecoding = self.policies.iter().filter(|p| p.to_evaluator()).count();
+        if decoding_bits.len() != ndecoding {
+            return Err(EvaluatorError::InvalidOutputCount(
+                ndecoding,
+                decoding_bits.len(),
+            ));
+        }
+
+        let mut bits = decoding_bits.iter();
+        Ok(self
+            .labels
+            .iter()
+            .zip(self.policies.iter())
+            .map(|(label, policy)| match policy {
+                OutputPolicy::Generator => None,
+                OutputPolicy::Shared => Some(label.lsb()),
+                OutputPolicy::Evaluator | OutputPolicy::Both => {
+                    Some(label.lsb() ^ bits.next().unwrap())
+                }
+            })
+            .collect())
+    }
+
+    /// Receive the decoding bits from the generator, send back the labels of the wires revealed
+    /// to the generator and decode the output.
+    pub fn run<C: AbstractChannel>(
+        &self,
+        channel: &mut C,
+    ) -> Result<Vec<Option<bool>>, EvaluatorError> {
+        let ndecoding = self.policies.iter().filter(|p| p.to_evaluator()).count();
+        let decoding_bits = channel.read_bits(ndecoding)?;
+
+        for label in self.returned_labels() {
+            channel.write_block(&label)?;
+        }
+        channel.flush()?;
+        self.decode(&decoding_bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use circuit::Circuit;
+    use crypto_core::{local_channel_pair, AesRng, Block};
+
+    use crate::{
+        EvaluatorOutput, GCEvaluator, GCGenerator, GeneratorError, GeneratorOutput,
+        HalfGateEvaluator, HalfGateGenerator, InputLabel, OutputPolicy,
+    };
+
+    #[test]
+    fn output_policy_test() {
+        // m1 = 2^64 - 1, m2 = 1
+        let mut input = vec![true; 64];
+        let mut m2 = vec![false; 64];
+        m2[0] = true;
+        input.extend(m2);
+        // The sum is 0 with carry discarded, so the adder outputs are all 0.
+        let policies: Vec<OutputPolicy> = (0..64)
+            .map(|i| match i % 4 {
+                0 => OutputPolicy::Evaluator,
+                1 => OutputPolicy::Generator,
+                2 => OutputPolicy::Both,
+                _ => OutputPolicy::Shared,
+            })
+            .collect();
+
+        let mut rng = AesRng::new();
+        let circ = Circuit::load("../circuit/circuit_files/bristol/adder64.txt").unwrap();
+        let complete_gc = HalfGateGenerator::new().garble(&mut rng, &circ).unwrap();
+        let mut gc = complete_gc.to_public(&[]);
+        gc.output_bits.clear();
+
+        let input_labels: Vec<InputLabel> = complete_gc
+            .input_labels
+            .iter()
+            .zip(input)
+            .enumerate()
+            .map(|(id, (label, value))| InputLabel {
+                id,
+                label: label[value as usize],
+            })
+            .collect();
+        let output_labels = HalfGateEvaluator::new()
+            .eval_labels(&circ, &gc, &input_labels)
+            .unwrap();
+
+        let gen_output = GeneratorOutput::new(
+            complete_gc.wire_labels[(circ.nwires - 64)..].to_vec(),
+            &policies,
+        )
+        .unwrap();
+        let ev_output = EvaluatorOutput::new(output_labels, &policies).unwrap();
+
+        let (mut sender, mut receiver) = local_channel_pair();
+        let handle = thread::spawn(move || gen_output.run(&mut sender).unwrap());
+        let ev_res = ev_output.run(&mut receiver).unwrap();
+        let gen_res = handle.join().unwrap();
+
+        for (i, policy) in policies.iter().enumerate() {
+            match policy {
+                OutputPolicy::Evaluator => {
+                    assert_eq!(ev_res[i], Some(false));
+                    assert_eq!(gen_res[i], None);
+                }
+                OutputPolicy::Generator => {
+                    assert_eq!(ev_res[i], None);
+                    assert_eq!(gen_res[i], Some(false));
+                }
+                OutputPolicy::Both => {
+                    assert_eq!(ev_res[i], Some(false));
+                    assert_eq!(gen_res[i], Some(false));
+                }
+                OutputPolicy::Shared => {
+                    assert!(!(ev_res[i].unwrap() ^ gen_res[i].unwrap()));
+                }
+            }
+        }
+
+        // A forged label is rejected by the generator.
+        let gen_output = GeneratorOutput::new(
+            complete_gc.wire_labels[(circ.nwires - 64)..].to_vec(),
+            &policies,
+        )
+        .unwrap();
+        let forged = vec![rand::random::<Block>(); 32];
+        assert!(matches!(
+            gen_output.decode(&forged),
+            Err(GeneratorError::InvalidOutputLabel(1))
+        ));
+    }
+}
@@ -0,0 +1,397 @@
+//! Sessions for reactive garbling, where wire labels are kept across multiple circuits.
+//!
+//! Both parties store named bundles of wire labels under one global `delta`. A circuit takes
+//! its inputs from existing bundles, e.g., the output labels of earlier circuits, so the same
+//! input is never transferred twice and cannot be switched between circuits. The generator of a
+//! session issues a fresh tweak nonce for each circuit, made of its session id and a counter, so
+//! no two circuits under `delta` share a tweak, even when several sessions share `delta`. The
+//! evaluator of a session rejects a circuit reusing a nonce.
+//!
+//! A bundle name is never silently replaced: storing a bundle under the name of an existing one
+//! fails, and the existing one must be removed first.
+
+use std::collections::HashMap;
+
+use circuit::Circuit;
+use crypto_core::{BitVec, Block, SecretBlock};
+use rand::{CryptoRng, Rng};
+use zeroize::Zeroize;
+
+use crate::{
+    CompleteGarbledCircuit, EvaluatorError, GCEvaluator, GCGenerator, GarbledCircuit,
+    GeneratorError, InputLabel, InputLabelPair,
+};
+
+/// Session of the generator, which holds the generator `gen`, `delta` and the label pairs of
+/// each bundle. The label pairs and `delta` are zeroized on drop.
+pub struct GeneratorSession<G> {
+    gen: G,
+    delta: SecretBlock,
+    bundles: HashMap<String, Vec<[Block; 2]>>,
+}
+
+impl<G: GCGenerator> GeneratorSession<G> {
+    /// New a session garbling with `gen` under a random `delta`.
+    pub fn new<R: Rng + CryptoRng>(gen: G, rng: &mut R) -> Self {
+        Self {
+            gen,
+            delta: SecretBlock::new(rng.gen::<Block>().set_lsb()),
+            bundles: HashMap::new(),
+        }
+    }
+
+    /// New a session garbling with `gen` under global `delta`, e.g., fixed by a correlated OT
+    /// protocol.
+    pub fn with_delta(gen: G, delta: Block) -> Result<Self, GeneratorError> {
+        if !delta.lsb() {
+            return Err(GeneratorError::InvalidDelta);
+        }
+        Ok(Self {
+            gen,
+            delta: SecretBlock::new(delta),
+            bundles: HashMap::new(),
+        })
+    }
+
+    /// Return the global `delta`.
+    pub fn delta(&self) -> &SecretBlock {
+        &self.delta
+    }
+
+    /// Return the label pairs of bundle `name`.
+    pub fn get(&self, name: &str) -> Option<&[[Block; 2]]> {
+        self.bundles.get(name).map(|labels| labels.as_slice())
+    }
+
+    /// Sample `n` fresh label pairs as bundle `name`, which must not exist.
+    pub fn fresh<R: Rng + CryptoRng>(
+        &mut self,
+        rng: &mut R,
+        name: &str,
+        n: usize,
+    ) -> Result<&[[Block; 2]], GeneratorError> {
+        self.check_new(name)?;
+        let delta = *self.delta.expose();
+        let labels = (0..n)
+            .map(|_| {
+                let z_0 = rng.gen::<Block>();
+                [z_0, z_0 ^ delta]
+            })
+            .collect();
+        self.bundles.insert(name.to_string(), labels);
+        Ok(&self.bundles[name])
+    }
+
+    /// Store label pairs provided by the caller as bundle `name`, which must not exist, checking
+    /// that they are consistent with `delta`.
+    pub fn insert(&mut self, name: &str, labels: Vec<[Block; 2]>) -> Result<(), GeneratorError> {
+        self.check_new(name)?;
+        if let Some(i) = labels
+            .iter()
+            .position(|z| z[0] ^ *self.delta.expose() != z[1])
+        {
+            return Err(GeneratorError::InconsistentInputLabel(i));
+        }
+        self.bundles.insert(name.to_string(), labels);
+        Ok(())
+    }
+
+    /// Remove bundle `name`, zeroizing its label pairs, and return whether it existed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        match self.bundles.remove(name) {
+            Some(mut labels) => {
+                labels.zeroize();
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[inline]
+    fn check_new(&self, name: &str) -> Result<(), GeneratorError> {
+        if self.bundles.contains_key(name) {
+            return Err(GeneratorError::BundleExists(name.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Return the decoding bits of bundle `name`.
+    pub fn decoding_bits(&self, name: &str) -> Option<BitVec> {
+        self.get(name)
+            .map(|labels| labels.iter().map(|z| z[0].lsb()).collect())
+    }
+
+    /// Garble `circ` whose input wires are the concatenation of bundles `inputs` with the next
+    /// tweak nonce, and store the output label pairs as bundle `output`, which must not exist.
+    pub fn garble<R: Rng + CryptoRng>(
+        &mut self,
+        rng: &mut R,
+        circ: &Circuit,
+        inputs: &[&str],
+        output: &str,
+    ) -> Result<CompleteGarbledCircuit, GeneratorError> {
+        self.check_new(output)?;
+        let mut input_labels = Vec::with_capacity(circ.ninput_wires);
+        for name in inputs {
+            let labels = self
+                .get(name)
+                .ok_or_else(|| GeneratorError::UnknownBundle(name.to_string()))?;
+            let offset = input_labels.len();
+            input_labels.extend(labels.iter().enumerate().map(|(i, z)| InputLabelPair {
+                id: offset + i,
+                labels: *z,
+            }));
+        }
+        if input_labels.len() != circ.ninput_wires {
+            return Err(GeneratorError::InvalidInputCount(
+                circ.ninput_wires,
+                input_labels.len(),
+            ));
+        }
+
+        let complete_gc =
+            self.gen
+                .garble_with_labels(rng, circ, *self.delta.expose(), &input_labels)?;
+        let output_labels = complete_gc.wire_labels[(circ.nwires - circ.noutput_wires)..].to_vec();
+        self.bundles.insert(output.to_string(), output_labels);
+        Ok(complete_gc)
+    }
+}
+
+impl<G> Drop for GeneratorSession<G> {
+    fn drop(&mut self) {
+        for labels in self.bundles.values_mut() {
+            labels.zeroize();
+        }
+    }
+}
+
+/// Session of the evaluator, which holds the evaluator `ev` and the active label of each
+/// bundle.
+pub struct EvaluatorSession<E> {
+    ev: E,
+    bundles: HashMap<String, Vec<Block>>,
+}
+
+impl<E: GCEvaluator> EvaluatorSession<E> {
+    /// New a session evaluating with `ev`.
+    pub fn new(ev: E) -> Self {
+        Self {
+            ev,
+            bundles: HashMap::new(),
+        }
+    }
+
+    /// Return the active labels of bundle `name`.
+    pub fn get(&self, name: &str) -> Option<&[Block]> {
+        self.bundles.get(name).map(|labels| labels.as_slice())
+    }
+
+    /// Store active labels, e.g., received from the generator or through OT, as bundle `name`,
+    /// which must not exist.
+    pub fn insert(&mut self, name: &str, labels: Vec<Block>) -> Result<(), EvaluatorError> {
+        self.check_new(name)?;
+        self.bundles.insert(name.to_string(), labels);
+        Ok(())
+    }
+
+    /// Remove bundle `name` and return whether it existed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.bundles.remove(name).is_some()
+    }
+
+    #[inline]
+    fn check_new(&self, name: &str) -> Result<(), EvaluatorError> {
+        if self.bundles.contains_key(name) {
+            return Err(EvaluatorError::BundleExists(name.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Decode bundle `name` with the decoding bits from the generator.
+    pub fn decode(&self, name: &str, decoding_bits: &BitVec) -> Result<BitVec, EvaluatorError> {
+        let labels = self
+            .get(name)
+            .ok_or_else(|| EvaluatorError::UnknownBundle(name.to_string()))?;
+        if labels.len() != decoding_bits.len() {
+            return Err(EvaluatorError::InvalidInputCount(
+                labels.len(),
+                decoding_bits.len(),
+            ));
+        }
+        Ok(labels
+            .iter()
+            .zip(decoding_bits.iter())
+            .map(|(label, bit)| label.lsb() ^ bit)
+            .collect())
+    }
+
+    /// Evaluate `gc` whose input wires are the concatenation of bundles `inputs`, and store
+    /// the output labels as bundle `output`, which must not exist. All input labels come from
+    /// the bundles, so `gc` must not carry generator input labels. The tweak nonce of `gc` must
+    /// be larger than the nonces of the earlier circuits with the same session id.
+    pub fn eval(
+        &mut self,
+        circ: &Circuit,
+        gc: &GarbledCircuit,
+        inputs: &[&str],
+        output: &str,
+    ) -> Result<(), EvaluatorError> {
+        self.check_new(output)?;
+        if !gc.generator_input_labels.is_empty() {
+            return Err(EvaluatorError::UnexpectedGeneratorInputs(
+                gc.generator_input_labels.len(),
+            ));
+        }
+        let mut input_labels: Vec<InputLabel> = Vec::with_capacity(circ.ninput_wires);
+        for name in inputs {
+            let labels = self
+                .get(name)
+                .ok_or_else(|| EvaluatorError::UnknownBundle(name.to_string()))?;
+            let offset = input_labels.len();
+            input_labels.extend(labels.iter().enumerate().map(|(i, label)| InputLabel {
+                id: offset + i,
+                label: *label,
+            }));
+        }
+        if input_labels.len() != circ.ninput_wires {
+            return Err(EvaluatorError::InvalidInputCount(
+                circ.ninput_wires,
+                input_labels.len(),
+            ));
+        }
+
+        let output_labels = self.ev.eval_labels(circ, gc, &input_labels)?;
+        self.bundles.insert(output.to_string(), output_labels);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use circuit::{Circuit, CircuitInput};
+    use crypto_core::{AesRng, BitVec, Block};
+
+    use crate::{
+        EvaluatorError, EvaluatorSession, GeneratorError, GeneratorSession, HalfGateEvaluator,
+        HalfGateGenerator,
+    };
+
+    fn u64_to_bits(x: u64) -> BitVec {
+        BitVec::from_u64(x, 64)
+    }
+
+    fn encode(labels: &[[Block; 2]], bits: &BitVec) -> Vec<Block> {
+        labels
+            .iter()
+            .zip(bits.iter())
+            .map(|(z, b)| z[b as usize])
+            .collect()
+    }
+
+    #[test]
+    fn reactive_adder64_test() {
+        let a = 0x1234_5678_9abc_def0u64;
+        let b = 0x0fed_cba9_8765_4321u64;
+        let c = 0x1111_2222_3333_4444u64;
+
+        let mut rng = AesRng::new();
+        let circ = Circuit::load("../circuit/circuit_files/bristol/adder64.txt").unwrap();
+        let mut gen_session = GeneratorSession::new(HalfGateGenerator::new(), &mut rng);
+        let mut ev_session = EvaluatorSession::new(HalfGateEvaluator::new());
+
+        // Input labels are transferred once, here directly instead of through OT.
+        for (name, x) in [("a", a), ("b", b), ("c", c)] {
+            let labels = gen_session.fresh(&mut rng, name, 64).unwrap();
+            ev_session
+                .insert(name, encode(labels, &u64_to_bits(x)))
+                .unwrap();
+        }
+
+        // sum = a + b
+        let complete_gc = gen_session
+            .garble(&mut rng, &circ, &["a", "b"], "sum")
+            .unwrap();
+        ev_session
+            .eval(&circ, &complete_gc.to_public(&[]), &["a", "b"], "sum")
+            .unwrap();
+
+        // sum2 = sum + c, reusing the output labels of the first circuit
+        let complete_gc = gen_session
+            .garble(&mut rng, &circ, &["sum", "c"], "sum2")
+            .unwrap();
+        ev_session
+            .eval(&circ, &complete_gc.to_public(&[]), &["sum", "c"], "sum2")
+            .unwrap();
+
+        // A circuit reusing the tweak nonce of an evaluated circuit is rejected.
+        assert_eq!(complete_gc.nonce.counter, 1);
+        let res = ev_session.eval(&circ, &complete_gc.to_public(&[]), &["sum", "c"], "sum3");
+        assert!(matches!(res, Err(EvaluatorError::ReusedNonce(n)) if n == complete_gc.nonce));
+
+        let sum = ev_session
+            .decode("sum", &gen_session.decoding_bits("sum").unwrap())
+            .unwrap();
+        let sum2 = ev_session
+            .decode("sum2", &gen_session.decoding_bits("sum2").unwrap())
+            .unwrap();
+        assert_eq!(sum, u64_to_bits(a.wrapping_add(b)));
+        assert_eq!(sum2, u64_to_bits(a.wrapping_add(b).wrapping_add(c)));
+    }
+
+    #[test]
+    fn session_bundle_test() {
+        let mut rng = AesRng::new();
+        let circ = Circuit::load("../circuit/circuit_files/bristol/adder64.txt").unwrap();
+        let mut gen_session = GeneratorSession::new(HalfGateGenerator::new(), &mut rng);
+        let mut ev_session = EvaluatorSession::new(HalfGateEvaluator::new());
+        for name in ["a", "b"] {
+            let labels = gen_session.fresh(&mut rng, name, 64).unwrap();
+            ev_session
+                .insert(name, encode(labels, &BitVec::zeros(64)))
+                .unwrap();
+        }
+
+        // Existing bundles are not replaced, unless they are removed first.
+        let res = gen_session.fresh(&mut rng, "a", 64);
+        assert!(matches!(res, Err(GeneratorError::BundleExists(name)) if name == "a"));
+        let res = gen_session.garble(&mut rng, &circ, &["a", "b"], "b");
+        assert!(matches!(res, Err(GeneratorError::BundleExists(name)) if name == "b"));
+        let res = ev_session.insert("b", vec![Block::default(); 64]);
+        assert!(matches!(res, Err(EvaluatorError::BundleExists(name)) if name == "b"));
+
+        let complete_gc = gen_session
+            .garble(&mut rng, &circ, &["a", "b"], "sum")
+            .unwrap();
+        let res = ev_session.eval(&circ, &complete_gc.to_public(&[]), &["a", "b"], "a");
+        assert!(matches!(res, Err(EvaluatorError::BundleExists(name)) if name == "a"));
+
+        // A circuit carrying generator input labels is rejected as such.
+        let inputs = [CircuitInput {
+            id: 0,
+            value: Block::default(),
+        }];
+        let res = ev_session.eval(&circ, &complete_gc.to_public(&inputs), &["a", "b"], "sum");
+        assert!(matches!(
+            res,
+            Err(EvaluatorError::UnexpectedGeneratorInputs(1))
+        ));
+
+        ev_session
+            .eval(&circ, &complete_gc.to_public(&[]), &["a", "b"], "sum")
+            .unwrap();
+        assert!(gen_session.remove("sum"));
+        assert!(!gen_session.remove("sum"));
+        assert!(ev_session.remove("sum"));
+        let complete_gc = gen_session
+            .garble(&mut rng, &circ, &["a", "b"], "sum")
+            .unwrap();
+        ev_session
+            .eval(&circ, &complete_gc.to_public(&[]), &["a", "b"], "sum")
+            .unwrap();
+        let sum = ev_session
+            .decode("sum", &gen_session.decoding_bits("sum").unwrap())
+            .unwrap();
+        assert_eq!(sum, BitVec::zeros(64));
+    }
+}
@@ -0,0 +1,192 @@
+//! Parameters shared by the generator and the evaluator of the three-halves garbling scheme
+//! (cf. Rosulek and Roy, <https://eprint.iacr.org/2021/749>).
+//!
+//! Wire labels are sliced into halves `X = (X_L, X_R)`. Given the labels `A` and `B` with
+//! colors `(i, j)`, the evaluator computes
+//!
+//! ```text
+//! C_L = H(A)_L ^ H(A ^ B)_L ^ M_ij[L] * (A_L, A_R, B_L, B_R) ^ R_ij[L] * (G_0, G_1, G_2)
+//! C_R = H(B)_L ^ H(A ^ B)_L ^ M_ij[R] * (A_L, A_R, B_L, B_R) ^ R_ij[R] * (G_0, G_1, G_2)
+//! ```
+//!
+//! where `M_ij = V_ij ^ r_0 * W_0 ^ r_1 * W_1` is selected by the control bits `r` of row
+//! `(i, j)`, and `R_ij` is fixed. The control bits are encrypted with the high halves of `H(A)`
+//! and `H(B)`. The equation has the shape of the paper's, but `V`, `W` and `R_ij` are not the
+//! paper's matrices: they are another solution of the same constraints, derived below.
+//!
+//! # Derivation
+//!
+//! Let `K_ij` be the label row `(i, j)` decodes to with zero ciphertexts, XORed with `Δ` if the
+//! truth values of the row are `(1, 1)`, so that every row has to decode to the zero label
+//! `C_0` once the ciphertexts are added. As `R_00 = 0`, `C_0 = K_00`, and with
+//! `D_ij = K_ij ^ K_00`, rows `(0, 1)` and `(1, 1)` fix `G = (D_01,L, D_01,R, D_11,L)`, which
+//! is `solve`. The three other halves then decode correctly if and only if
+//!
+//! ```text
+//! K_00,L ^ K_01,L ^ K_10,L ^ K_11,L = 0
+//! K_00,L ^ K_01,L ^ K_00,R ^ K_10,R = 0
+//! K_00,L ^ K_01,L ^ K_01,R ^ K_11,R = 0
+//! ```
+//!
+//! Every hash cancels in these equations, as rows `(i, 0)` and `(i, 1)` share `H(A)`, rows
+//! `(0, j)` and `(1, j)` share `H(B)`, and the rows whose truth values have the same XOR share
+//! `H(A ^ B)`. What is left is linear over GF(2) in `(A_L, A_R, B_L, B_R, Δ_L, Δ_R)` of the
+//! zero labels, since a row uses `A = A_0 ^ a * Δ` and `B = B_0 ^ b * Δ` for its truth values
+//! `(a, b)`, and it must vanish for all labels and `Δ`. For the permute bits `(pa, pb)`, this is
+//! an affine system in the eight control bits of the four rows. `V` solves it with all control
+//! bits zero for `(pa, pb) = (1, 0)`, adding `W_0` or `W_1` to all four rows keeps a solution,
+//! and for every `(pa, pb)` the solutions are exactly the four assignments
+//! `r_ij = s ^ CONTROL_OFFSET[2 * pa + pb][2i + j]` with `s` in `0..4`. `tests::matrices_test`
+//! checks this exhaustively.
+//!
+//! # Security
+//!
+//! Fix the row `(i, j)` of the evaluator, with labels `A` and `B`. The hashes of `A ^ Δ`,
+//! `B ^ Δ` and `A ^ B ^ Δ` are under tweaks distinct from all others, so they are
+//! pseudorandom given `A`, `B` and the rest of the circuit, assuming the tweaked hash is
+//! circular correlation robust as for half gates. Then:
+//!
+//! - `G_2`, `G_0` and `G_1` are padded by the low halves of `H(A ^ Δ)`, `H(A ^ B ^ Δ)` and
+//!   `H(B ^ Δ)` respectively, so the ciphertexts are uniformly random.
+//! - The masks of the control bits of the other rows involve the high halves of `H(A ^ Δ)` or
+//!   `H(B ^ Δ)`, and the four masks XOR to zero. The evaluator thus learns its own control
+//!   bits, which are uniformly random as `s` is, and the XOR of the control bits of all rows,
+//!   which is zero for every row of `CONTROL_OFFSET`. Neither depends on the permute bits, so
+//!   the control bits reveal nothing about the truth values.
+//! - The evaluator computes `C` from the above, and the other output label is `C ^ Δ`.
+
+use crate::grable::gc::{gate_tweak, TweakNonce};
+use crypto_core::{Block, AES_HASH};
+
+/// `V_ij` indexed by `2i + j`, as bitmasks over `(A_L, A_R, B_L, B_R)` for the left and right
+/// output halves.
+const V: [[u8; 2]; 4] = [[0b0100, 0], [0b0100, 0b0010], [0, 0], [0, 0b0010]];
+
+/// `W_0` and `W_1` in the same layout as `V`.
+const W: [[u8; 2]; 2] = [[0b1011, 0b0110], [0b1101, 0b1011]];
+
+/// Control bits of row `2i + j` are `s ^ CONTROL_OFFSET[2 * pa + pb][2i + j]`, where `s` is
+/// chosen at random by the generator and `(pa, pb)` are the permute bits of the input wires.
+pub(crate) const CONTROL_OFFSET: [[u8; 4]; 4] =
+    [[0, 1, 2, 3], [0, 3, 1, 2], [0, 0, 0, 0], [0, 2, 3, 1]];
+
+#[inline]
+fn halves(x: Block) -> [u64; 2] {
+    let x = u128::from(x);
+    [x as u64, (x >> 64) as u64]
+}
+
+#[inline]
+fn combine(mask: u8, x: &[u64]) -> u64 {
+    x.iter()
+        .enumerate()
+        .filter(|(k, _)| (mask >> k) & 1 == 1)
+        .fold(0, |acc, (_, x)| acc ^ x)
+}
+
+/// Hash `x` with the `k`-th tweak of AND gate `gid` in the circuit with `nonce`.
+#[inline]
+pub(crate) fn hash(x: Block, nonce: TweakNonce, gid: usize, k: usize) -> Block {
+    AES_HASH.tccr_hash(gate_tweak(nonce, 3 * gid + k), x)
+}
+
+/// Mask of the control bits of a row, given `H(A)` and `H(B)`.
+#[inline]
+pub(crate) fn control_mask(hash_a: Block, hash_b: Block) -> u8 {
+    ((u128::from(hash_a ^ hash_b) >> 64) & 3) as u8
+}
+
+/// Compute the output label of row `(i, j)` from the input labels, their hashes
+/// `[H(A), H(B), H(A ^ B)]`, the control bits `r` and the ciphertexts `g`.
+#[inline]
+pub(crate) fn row_label(a: Block, b: Block, hashes: [Block; 3], r: u8, g: &[u64; 3]) -> Block {
+    let row = 2 * a.lsb() as usize + b.lsb() as usize;
+    let [a_l, a_r] = halves(a);
+    let [b_l, b_r] = halves(b);
+    let x = [a_l, a_r, b_l, b_r];
+    let [h_a, _] = halves(hashes[0]);
+    let [h_b, _] = halves(hashes[1]);
+    let [h_ab, _] = halves(hashes[2]);
+
+    let mut m = V[row];
+    for (t, w) in W.iter().enumerate() {
+        if (r >> t) & 1 == 1 {
+            m[0] ^= w[0];
+            m[1] ^= w[1];
+        }
+    }
+    // R_ij * (G_0, G_1, G_2) for the left and right halves
+    let [g_l, g_r] = match row {
+        0 => [0, 0],
+        1 => [g[0], g[1]],
+        2 => [g[0] ^ g[2], g[0]],
+        _ => [g[2], g[0] ^ g[1]],
+    };
+
+    let c_l = h_a ^ h_ab ^ combine(m[0], &x) ^ g_l;
+    let c_r = h_b ^ h_ab ^ combine(m[1], &x) ^ g_r;
+    Block::from(c_l as u128 | (c_r as u128) << 64)
+}
+
+/// Solve the ciphertexts from the row values `K_ij` computed with zero ciphertexts, such that
+/// every row decodes to `C_0 = K_00`.
+#[inline]
+pub(crate) fn solve(k: &[Block; 4]) -> (Block, [u64; 3]) {
+    let [c_l, c_r] = halves(k[0]);
+    let [k1_l, k1_r] = halves(k[1]);
+    let [k3_l, _] = halves(k[3]);
+    (k[0], [k1_l ^ c_l, k1_r ^ c_r, k3_l ^ c_l])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Linear terms of `M * (A_L, A_R, B_L, B_R)` for truth values `(a, b)`, as a bitmask over
+    /// `(A_L, A_R, B_L, B_R, Δ_L, Δ_R)` of the zero labels.
+    fn linear(mask: u8, a: u8, b: u8) -> u8 {
+        let x = [1 | a << 4, 2 | a << 5, 4 | b << 4, 8 | b << 5];
+        (0..4)
+            .filter(|k| (mask >> k) & 1 == 1)
+            .fold(0, |acc, k| acc ^ x[k])
+    }
+
+    /// Return whether the control bits `r` of the rows solve the system for `(pa, pb)`.
+    fn consistent(pa: usize, pb: usize, r: [u8; 4]) -> bool {
+        let mut k = [[0u8; 2]; 4];
+        for (row, k) in k.iter_mut().enumerate() {
+            let a = ((row >> 1) ^ pa) as u8;
+            let b = ((row & 1) ^ pb) as u8;
+            let mut m = V[row];
+            for (t, w) in W.iter().enumerate() {
+                if (r[row] >> t) & 1 == 1 {
+                    m[0] ^= w[0];
+                    m[1] ^= w[1];
+                }
+            }
+            *k = [
+                linear(m[0], a, b) ^ (a & b) << 4,
+                linear(m[1], a, b) ^ (a & b) << 5,
+            ];
+        }
+        k[0][0] ^ k[1][0] ^ k[2][0] ^ k[3][0] == 0
+            && k[0][0] ^ k[1][0] ^ k[0][1] ^ k[2][1] == 0
+            && k[0][0] ^ k[1][0] ^ k[1][1] ^ k[3][1] == 0
+    }
+
+    #[test]
+    fn matrices_test() {
+        for (p, offsets) in CONTROL_OFFSET.iter().enumerate() {
+            let mut solutions: Vec<[u8; 4]> = (0..256usize)
+                .map(|r| [0, 1, 2, 3].map(|row| ((r >> (2 * row)) & 3) as u8))
+                .filter(|&r| consistent(p >> 1, p & 1, r))
+                .collect();
+            let mut expected: Vec<[u8; 4]> =
+                (0..4).map(|s| offsets.map(|offset| s ^ offset)).collect();
+            solutions.sort();
+            expected.sort();
+            assert_eq!(solutions, expected);
+            assert_eq!(offsets.iter().fold(0, |acc, r| acc ^ r), 0);
+        }
+    }
+}
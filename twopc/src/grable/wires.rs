@@ -0,0 +1,90 @@
+//! Storage of wire labels that only keeps the labels of live wires.
+
+use circuit::Circuit;
+use std::collections::HashMap;
+use zeroize::Zeroize;
+
+/// Wire labels indexed by wire id. A label is dropped once the last gate reading it has been
+/// processed, and the output of a gate is only stored if a later gate reads it, except for
+/// output wires, which are kept until the end of the circuit. The liveness of the wires is
+/// computed from the circuit by the caller (see `Circuit::liveness`), so only the live labels
+/// are in memory.
+///
+/// The labels are kept in slots reused across wires, and the map only holds slot indices, so a
+/// dropped label is zeroized in its slot and no copy of it is left behind when the map or the
+/// slots grow. All slots are zeroized on drop.
+pub(crate) struct LiveWires<T: Copy + Default + Zeroize> {
+    slots: Vec<T>,
+    free: Vec<usize>,
+    index: HashMap<usize, usize>,
+    output_start: usize,
+}
+
+impl<T: Copy + Default + Zeroize> LiveWires<T> {
+    pub(crate) fn new(circ: &Circuit) -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+            index: HashMap::new(),
+            output_start: circ.nwires - circ.noutput_wires,
+        }
+    }
+
+    /// Store the label of wire `id` if it is `used` by a later gate or is an output wire.
+    #[inline]
+    pub(crate) fn insert(&mut self, id: usize, label: T, used: bool) {
+        if !(used || id >= self.output_start) {
+            return;
+        }
+        if let Some(&slot) = self.index.get(&id) {
+            self.slots[slot] = label;
+            return;
+        }
+        let slot = match self.free.pop() {
+            Some(slot) => slot,
+            None => {
+                if self.slots.len() == self.slots.capacity() {
+                    self.grow();
+                }
+                self.slots.push(T::default());
+                self.slots.len() - 1
+            }
+        };
+        self.slots[slot] = label;
+        self.index.insert(id, slot);
+    }
+
+    /// Read the label of wire `id` as a gate input, dropping it if this is its `last` use.
+    #[inline]
+    pub(crate) fn take(&mut self, id: usize, last: bool) -> Option<T> {
+        if last && id < self.output_start {
+            let slot = self.index.remove(&id)?;
+            let label = self.slots[slot];
+            self.slots[slot].zeroize();
+            self.free.push(slot);
+            Some(label)
+        } else {
+            self.output(id)
+        }
+    }
+
+    /// Return the label of output wire `id`.
+    #[inline]
+    pub(crate) fn output(&self, id: usize) -> Option<T> {
+        self.index.get(&id).map(|&slot| self.slots[slot])
+    }
+
+    /// Move the slots to a buffer twice as large, zeroizing the old one.
+    fn grow(&mut self) {
+        let mut slots = Vec::with_capacity((2 * self.slots.capacity()).max(64));
+        slots.extend_from_slice(&self.slots);
+        self.slots.zeroize();
+        self.slots = slots;
+    }
+}
+
+impl<T: Copy + Default + Zeroize> Drop for LiveWires<T> {
+    fn drop(&mut self) {
+        self.slots.zeroize();
+    }
+}
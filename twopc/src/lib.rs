@@ -1,5 +1,10 @@
 pub mod grable;
 pub mod ot;
+pub mod yao;
 
+// Both `grable` and `ot` have a public `errors` module, which is reached through its parent
+// module; the error types themselves are re-exported without ambiguity.
+#[allow(ambiguous_glob_reexports)]
 pub use grable::*;
 pub use ot::*;
+pub use yao::*;
@@ -1,147 +1,334 @@
 //! Implementation of Chou-Orlandi oblivious transfer protocol (cf. <https://eprint.iacr.org/2015/267>)
 
-use crypto_core::{AbstractChannel, Block};
+use crypto_core::{AbstractChannel, AesRng, BitVec, Block, RandomOracle, SecretBlock};
 use curve25519_dalek::constants;
 use curve25519_dalek::constants::RISTRETTO_BASEPOINT_TABLE;
 use curve25519_dalek::ristretto::RistrettoBasepointTable;
 use curve25519_dalek::ristretto::RistrettoPoint;
 use curve25519_dalek::traits::Identity;
 use rand::{CryptoRng, Rng};
+use subtle::{Choice, ConditionallySelectable};
+use zeroize::Zeroize;
 
 use super::errors::{OTReceiverError, OTSenderError};
-use crate::OtReceiver;
-use crate::OtSender;
+use crate::{CotReceiver, CotSender, OtReceiver, OtSender, RotReceiver, RotSender};
 use curve25519_dalek::scalar::Scalar;
 
+/// Domain tag of the key derivation.
+const KDF_TAG: &str = "zkOracles/CO15/KDF";
+
+/// Random oracle with the session id and `S` absorbed, to be completed by `derive_key`.
+#[inline]
+fn kdf(session: Block, s: &RistrettoPoint) -> RandomOracle {
+    let mut ro = RandomOracle::new(KDF_TAG);
+    ro.update_block(&session).update_point(s);
+    ro
+}
+
+/// Derive the key `H(sid, S, i, R_i, p)` of OT `index`.
+#[inline]
+fn derive_key(kdf: &RandomOracle, index: u128, r: &RistrettoPoint, p: &RistrettoPoint) -> Block {
+    let mut ro = kdf.clone();
+    ro.update_u128(index).update_point(r).update_point(p);
+    ro.finalize_block()
+}
+
 pub struct ChouOrlandiSender {
+    session: Block,
     counter: u128,
+    delta: SecretBlock,
 }
 
 impl ChouOrlandiSender {
+    /// New a sender with a global correlation sampled from a fresh `AesRng`.
     pub fn new() -> Self {
-        Self { counter: 0 }
+        Self::new_with_rng(&mut AesRng::new())
     }
-}
 
-impl OtSender for ChouOrlandiSender {
-    type Msg = Block;
+    /// New a sender with a global correlation sampled from `rng`.
+    pub fn new_with_rng<R: CryptoRng + Rng>(rng: &mut R) -> Self {
+        Self::with_delta(rng.gen::<Block>())
+    }
 
-    fn send<C: AbstractChannel, R: CryptoRng + Rng>(
+    /// New a sender with global correlation `delta` for correlated OT.
+    pub fn with_delta(delta: Block) -> Self {
+        Self {
+            session: Block::default(),
+            counter: 0,
+            delta: SecretBlock::new(delta),
+        }
+    }
+
+    /// Bind the derived keys to session id `session`, which must be the same for the receiver.
+    pub fn with_session(mut self, session: Block) -> Self {
+        self.session = session;
+        self
+    }
+
+    /// Run the base protocol and derive `len` pairs of random keys.
+    fn send_keys<C: AbstractChannel, R: CryptoRng + Rng>(
         &mut self,
         channel: &mut C,
-        inputs: &[(Block, Block)],
+        len: usize,
         mut rng: &mut R,
-    ) -> Result<(), OTSenderError> {
-        let y = Scalar::random(&mut rng);
+    ) -> Result<Vec<(Block, Block)>, OTSenderError> {
+        let mut y = Scalar::random(&mut rng);
         let s = &y * &constants::RISTRETTO_BASEPOINT_TABLE;
         channel.write_point(&s)?;
         channel.flush()?;
 
         let t = y * s;
+        let kdf = kdf(self.session, &s);
 
-        let keys: Vec<(Block, Block)> = (0..inputs.len())
+        let keys = (0..len)
             .map(|i| {
-                let r = channel.read_point().unwrap();
+                let r = channel.read_point()?;
                 let yr = y * r;
-                let k0 = Block::hash_point(self.counter + i as u128, &yr);
-                let k1 = Block::hash_point(self.counter + i as u128, &(yr - t));
-                (k0, k1)
+                let index = self.counter + i as u128;
+                let k0 = derive_key(&kdf, index, &r, &yr);
+                let k1 = derive_key(&kdf, index, &r, &(yr - t));
+                Ok((k0, k1))
             })
-            .collect();
+            .collect::<Result<_, OTSenderError>>();
+        y.zeroize();
+        let keys = keys?;
 
-        for (input, k) in inputs.iter().zip(keys.into_iter()) {
+        self.counter += len as u128;
+        Ok(keys)
+    }
+}
+
+impl Default for ChouOrlandiSender {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OtSender for ChouOrlandiSender {
+    type Msg = Block;
+
+    fn send<C: AbstractChannel, R: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &[(Block, Block)],
+        rng: &mut R,
+    ) -> Result<(), OTSenderError> {
+        let mut keys = self.send_keys(channel, inputs.len(), rng)?;
+
+        for (input, k) in inputs.iter().zip(keys.iter_mut()) {
             let c0 = input.0 ^ k.0;
             let c1 = input.1 ^ k.1;
+            k.0.zeroize();
+            k.1.zeroize();
             channel.write_block(&c0)?;
             channel.write_block(&c1)?;
         }
         channel.flush()?;
-
-        self.counter += inputs.len() as u128;
         Ok(())
     }
 }
 
+impl CotSender for ChouOrlandiSender {
+    type Msg = Block;
+
+    fn delta(&self) -> Block {
+        *self.delta.expose()
+    }
+
+    fn send_correlated<C: AbstractChannel, R: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        len: usize,
+        rng: &mut R,
+    ) -> Result<Vec<(Block, Block)>, OTSenderError> {
+        let mut keys = self.send_keys(channel, len, rng)?;
+        let mut delta = *self.delta.expose();
+
+        let out = keys
+            .iter_mut()
+            .map(|(k0, k1)| {
+                channel.write_block(&(*k0 ^ *k1 ^ delta))?;
+                Ok((*k0, *k0 ^ delta))
+            })
+            .collect::<Result<_, OTSenderError>>();
+        keys.iter_mut().for_each(|(k0, k1)| {
+            k0.zeroize();
+            k1.zeroize();
+        });
+        delta.zeroize();
+        let out = out?;
+        channel.flush()?;
+        Ok(out)
+    }
+}
+
+impl RotSender for ChouOrlandiSender {
+    type Msg = Block;
+
+    fn send_random<C: AbstractChannel, R: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        len: usize,
+        rng: &mut R,
+    ) -> Result<Vec<(Block, Block)>, OTSenderError> {
+        self.send_keys(channel, len, rng)
+    }
+}
+
 pub struct ChouOrlandiReceiver {
+    session: Block,
     counter: u128,
 }
 
 impl ChouOrlandiReceiver {
     pub fn new() -> Self {
-        Self { counter: 0 }
+        Self {
+            session: Block::default(),
+            counter: 0,
+        }
     }
-}
 
-impl OtReceiver for ChouOrlandiReceiver {
-    type Msg = Block;
+    /// Bind the derived keys to session id `session`, which must be the same for the sender.
+    pub fn with_session(mut self, session: Block) -> Self {
+        self.session = session;
+        self
+    }
 
-    fn receive<C: AbstractChannel, R: CryptoRng + Rng>(
+    /// Run the base protocol and derive the keys selected by `inputs`.
+    fn receive_keys<C: AbstractChannel, R: CryptoRng + Rng>(
         &mut self,
         channel: &mut C,
-        inputs: &[bool],
+        inputs: &BitVec,
         mut rng: &mut R,
     ) -> Result<Vec<Block>, OTReceiverError> {
         let iden = RistrettoPoint::identity();
         let s = channel.read_point()?;
         let s_table = RistrettoBasepointTable::create(&s);
+        let kdf = kdf(self.session, &s);
 
-        let key: Vec<Block> = inputs
+        let keys = inputs
             .iter()
             .enumerate()
             .map(|(i, input)| {
-                let x = Scalar::random(&mut rng);
-                let cs = if *input { s } else { iden };
+                let mut x = Scalar::random(&mut rng);
+                let cs = RistrettoPoint::conditional_select(&iden, &s, Choice::from(input as u8));
                 let r = cs + &x * &RISTRETTO_BASEPOINT_TABLE;
-                channel.write_point(&r).unwrap();
-                Block::hash_point(self.counter + i as u128, &(&x * &s_table))
+                let p = &x * &s_table;
+                x.zeroize();
+                channel.write_point(&r)?;
+                Ok(derive_key(&kdf, self.counter + i as u128, &r, &p))
             })
-            .collect();
+            .collect::<Result<_, OTReceiverError>>()?;
         channel.flush()?;
 
         self.counter += inputs.len() as u128;
+        Ok(keys)
+    }
+}
+
+impl Default for ChouOrlandiReceiver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OtReceiver for ChouOrlandiReceiver {
+    type Msg = Block;
+
+    fn receive<C: AbstractChannel, R: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &BitVec,
+        rng: &mut R,
+    ) -> Result<Vec<Block>, OTReceiverError> {
+        let keys = self.receive_keys(channel, inputs, rng)?;
 
         inputs
             .iter()
-            .zip(key.into_iter())
+            .zip(keys)
             .map(|(input, k)| {
                 let c0 = channel.read_block()?;
                 let c1 = channel.read_block()?;
-                let c = k ^ if *input { c1 } else { c0 };
+                let c = k ^ if input { c1 } else { c0 };
                 Ok(c)
             })
             .collect()
     }
 }
 
+impl CotReceiver for ChouOrlandiReceiver {
+    type Msg = Block;
+
+    fn receive_correlated<C: AbstractChannel, R: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &BitVec,
+        rng: &mut R,
+    ) -> Result<Vec<Block>, OTReceiverError> {
+        let keys = self.receive_keys(channel, inputs, rng)?;
+
+        inputs
+            .iter()
+            .zip(keys)
+            .map(|(input, k)| {
+                let d = channel.read_block()?;
+                Ok(if input { k ^ d } else { k })
+            })
+            .collect()
+    }
+}
+
+impl RotReceiver for ChouOrlandiReceiver {
+    type Msg = Block;
+
+    fn receive_random<C: AbstractChannel, R: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        len: usize,
+        rng: &mut R,
+    ) -> Result<(BitVec, Vec<Block>), OTReceiverError> {
+        let choices = BitVec::random(rng, len);
+        let keys = self.receive_keys(channel, &choices, rng)?;
+        Ok((choices, keys))
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::io::ErrorKind;
     use std::thread;
+    use std::time::Duration;
 
-    use crypto_core::{local_channel_pair, AesRng, Block};
+    use crypto_core::{
+        local_channel_pair, AesRng, BitVec, Block, Fault, FaultChannel, LocalChannel,
+    };
 
-    use crate::{ChouOrlandiReceiver, ChouOrlandiSender, OtReceiver, OtSender};
+    use crate::{
+        ChouOrlandiReceiver, ChouOrlandiSender, CotReceiver, CotSender, OTReceiverError,
+        OTSenderError, OtReceiver, OtSender, RotReceiver, RotSender,
+    };
 
     fn rand_block_vec(size: usize) -> Vec<Block> {
         (0..size).map(|_| rand::random::<Block>()).collect()
     }
 
-    fn rand_bool_vec(size: usize) -> Vec<bool> {
-        (0..size).map(|_| rand::random::<bool>()).collect()
+    fn rand_bit_vec(size: usize) -> BitVec {
+        BitVec::random(&mut rand::thread_rng(), size)
     }
 
     #[test]
     fn local_co_ot_test() {
         let m0 = rand_block_vec(128);
         let m1 = rand_block_vec(128);
-        let m_inside: Vec<(Block, Block)> = m0.into_iter().zip(m1.into_iter()).collect();
+        let m_inside: Vec<(Block, Block)> = m0.into_iter().zip(m1).collect();
         let m = m_inside.clone();
-        let select = rand_bool_vec(128);
+        let select = rand_bit_vec(128);
 
         let (mut sender, mut receiver) = local_channel_pair();
 
         let handle = thread::spawn(move || {
-            let mut ot = ChouOrlandiSender::new();
             let mut rng = AesRng::new();
+            let mut ot = ChouOrlandiSender::new_with_rng(&mut rng);
             ot.send(&mut sender, &m_inside, &mut rng).unwrap();
             ot.send(&mut sender, &m_inside, &mut rng).unwrap();
         });
@@ -160,4 +347,158 @@ mod tests {
         }
         handle.join().unwrap();
     }
+
+    #[test]
+    fn local_co_cot_test() {
+        let select = rand_bit_vec(128);
+        let delta = rand::random::<Block>();
+
+        let (mut sender, mut receiver) = local_channel_pair();
+
+        let handle = thread::spawn(move || {
+            let mut ot = ChouOrlandiSender::with_delta(delta);
+            let mut rng = AesRng::new();
+            ot.send_correlated(&mut sender, 128, &mut rng).unwrap()
+        });
+
+        let mut rng = AesRng::new();
+        let mut ot = ChouOrlandiReceiver::new();
+        let result = ot
+            .receive_correlated(&mut receiver, &select, &mut rng)
+            .unwrap();
+
+        let m = handle.join().unwrap();
+        for i in 0..128 {
+            assert_eq!(m[i].1, m[i].0 ^ delta);
+            assert_eq!(result[i], if select[i] { m[i].1 } else { m[i].0 });
+        }
+    }
+
+    #[test]
+    fn local_co_rot_test() {
+        let (mut sender, mut receiver) = local_channel_pair();
+
+        let handle = thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let mut ot = ChouOrlandiSender::new_with_rng(&mut rng);
+            ot.send_random(&mut sender, 128, &mut rng).unwrap()
+        });
+
+        let mut rng = AesRng::new();
+        let mut ot = ChouOrlandiReceiver::new();
+        let (select, result) = ot.receive_random(&mut receiver, 128, &mut rng).unwrap();
+
+        let m = handle.join().unwrap();
+        for i in 0..128 {
+            assert_eq!(result[i], if select[i] { m[i].1 } else { m[i].0 });
+        }
+    }
+
+    #[test]
+    fn co_session_test() {
+        let (mut sender, mut receiver) = local_channel_pair();
+
+        let handle = thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let mut ot = ChouOrlandiSender::new_with_rng(&mut rng).with_session(Block::from(1u128));
+            let m0 = ot.send_random(&mut sender, 128, &mut rng).unwrap();
+            let mut ot = ChouOrlandiSender::new_with_rng(&mut rng).with_session(Block::from(1u128));
+            let m1 = ot.send_random(&mut sender, 128, &mut rng).unwrap();
+            (m0, m1)
+        });
+
+        let mut rng = AesRng::new();
+        let mut ot = ChouOrlandiReceiver::new().with_session(Block::from(1u128));
+        let (select0, result0) = ot.receive_random(&mut receiver, 128, &mut rng).unwrap();
+        let mut ot = ChouOrlandiReceiver::new().with_session(Block::from(2u128));
+        let (select1, result1) = ot.receive_random(&mut receiver, 128, &mut rng).unwrap();
+
+        // Keys derived under different session ids are unrelated.
+        let (m0, m1) = handle.join().unwrap();
+        for i in 0..128 {
+            assert_eq!(result0[i], if select0[i] { m0[i].1 } else { m0[i].0 });
+            assert_ne!(result1[i], if select1[i] { m1[i].1 } else { m1[i].0 });
+        }
+    }
+
+    /// Run 128 OTs over faulty channels. Each party drops its channel when it returns, so that
+    /// the peer reads the end of the stream instead of waiting.
+    fn co_with_faults(
+        sender: FaultChannel<LocalChannel>,
+        receiver: FaultChannel<LocalChannel>,
+    ) -> (
+        Result<(), OTSenderError>,
+        Result<Vec<Block>, OTReceiverError>,
+    ) {
+        let m: Vec<(Block, Block)> = rand_block_vec(128)
+            .into_iter()
+            .zip(rand_block_vec(128))
+            .collect();
+        let select = rand_bit_vec(128);
+
+        let handle = thread::spawn(move || {
+            let mut sender = sender;
+            let mut rng = AesRng::new();
+            ChouOrlandiSender::new_with_rng(&mut rng).send(&mut sender, &m, &mut rng)
+        });
+        let result = {
+            let mut receiver = receiver;
+            ChouOrlandiReceiver::new().receive(&mut receiver, &select, &mut AesRng::new())
+        };
+        (handle.join().unwrap(), result)
+    }
+
+    #[test]
+    fn co_fault_test() {
+        let kind_of_send = |r: &Result<(), OTSenderError>| match r {
+            Err(OTSenderError::IoError(e)) => Some(e.kind()),
+            _ => None,
+        };
+        let kind_of_receive = |r: &Result<Vec<Block>, OTReceiverError>| match r {
+            Err(OTReceiverError::IoError(e)) => Some(e.kind()),
+            _ => None,
+        };
+
+        // Setting the top bit of the encoding of S makes it non-canonical.
+        let (sender, receiver) = local_channel_pair();
+        let sender = FaultChannel::new(sender).fault_write(31, Fault::Corrupt(0x80));
+        let (s, r) = co_with_faults(sender, FaultChannel::new(receiver));
+        assert_eq!(kind_of_receive(&r), Some(ErrorKind::InvalidData));
+        assert!(kind_of_send(&s).is_some());
+
+        // Same for a point R of the receiver.
+        let (sender, receiver) = local_channel_pair();
+        let receiver = FaultChannel::new(receiver).fault_write(32 * 5 + 31, Fault::Corrupt(0x80));
+        let (s, r) = co_with_faults(FaultChannel::new(sender), receiver);
+        assert_eq!(kind_of_send(&s), Some(ErrorKind::InvalidData));
+        assert!(kind_of_receive(&r).is_some());
+
+        // The sender closes the connection in the middle of the ciphertexts.
+        let (sender, receiver) = local_channel_pair();
+        let sender = FaultChannel::new(sender).fault_write(32 + 100, Fault::Close);
+        let (s, r) = co_with_faults(sender, FaultChannel::new(receiver));
+        assert_eq!(kind_of_send(&s), Some(ErrorKind::ConnectionAborted));
+        assert_eq!(kind_of_receive(&r), Some(ErrorKind::UnexpectedEof));
+
+        // The receiver misses the end of the ciphertexts, or some bytes in the middle of them.
+        let (sender, receiver) = local_channel_pair();
+        let receiver = FaultChannel::new(receiver).fault_read(32 + 1000, Fault::Truncate);
+        let (_, r) = co_with_faults(FaultChannel::new(sender), receiver);
+        assert_eq!(kind_of_receive(&r), Some(ErrorKind::UnexpectedEof));
+        let (sender, receiver) = local_channel_pair();
+        let sender = FaultChannel::new(sender).fault_write(32 + 1000, Fault::Drop(1));
+        let (s, r) = co_with_faults(sender, FaultChannel::new(receiver));
+        assert!(s.is_ok());
+        assert_eq!(kind_of_receive(&r), Some(ErrorKind::UnexpectedEof));
+
+        // Delays only slow the protocol down.
+        let (sender, receiver) = local_channel_pair();
+        let sender =
+            FaultChannel::new(sender).fault_write(32, Fault::Delay(Duration::from_millis(10)));
+        let receiver =
+            FaultChannel::new(receiver).fault_read(0, Fault::Delay(Duration::from_millis(10)));
+        let (s, r) = co_with_faults(sender, receiver);
+        assert!(s.is_ok());
+        assert_eq!(r.unwrap().len(), 128);
+    }
 }
@@ -0,0 +1,142 @@
+//! Adapters that turn random OTs into chosen-message OTs by derandomization (cf. Beaver's
+//! OT precomputation <https://link.springer.com/chapter/10.1007/3-540-44750-4_8>).
+//!
+//! The receiver sends `e = b ⊕ r`, where `b` is its choice bit and `r` is the random choice
+//! bit of the random OT. The sender then encrypts `m_0` and `m_1` with `k_e` and `k_{1⊕e}`.
+
+use crypto_core::{AbstractChannel, BitVec, Block};
+use rand::{CryptoRng, Rng};
+
+use super::errors::{OTReceiverError, OTSenderError};
+use crate::{OtReceiver, OtSender, RotReceiver, RotSender};
+
+/// Chosen-message OT sender built on top of a random OT sender.
+pub struct RotToOtSender<S: RotSender<Msg = Block>> {
+    ot: S,
+}
+
+impl<S: RotSender<Msg = Block>> RotToOtSender<S> {
+    pub fn new(ot: S) -> Self {
+        Self { ot }
+    }
+
+    /// Return the underlying random OT sender.
+    pub fn into_inner(self) -> S {
+        self.ot
+    }
+}
+
+impl<S: RotSender<Msg = Block>> OtSender for RotToOtSender<S> {
+    type Msg = Block;
+
+    fn send<C: AbstractChannel, R: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &[(Block, Block)],
+        rng: &mut R,
+    ) -> Result<(), OTSenderError> {
+        if inputs.is_empty() {
+            return Ok(());
+        }
+        let keys = self.ot.send_random(channel, inputs.len(), rng)?;
+        let flips = channel.read_bits(inputs.len())?;
+
+        for ((input, k), e) in inputs.iter().zip(keys).zip(flips.iter()) {
+            let (k0, k1) = if e { (k.1, k.0) } else { k };
+            channel.write_block(&(input.0 ^ k0))?;
+            channel.write_block(&(input.1 ^ k1))?;
+        }
+        channel.flush()?;
+        Ok(())
+    }
+}
+
+/// Chosen-message OT receiver built on top of a random OT receiver.
+pub struct RotToOtReceiver<R: RotReceiver<Msg = Block>> {
+    ot: R,
+}
+
+impl<R: RotReceiver<Msg = Block>> RotToOtReceiver<R> {
+    pub fn new(ot: R) -> Self {
+        Self { ot }
+    }
+
+    /// Return the underlying random OT receiver.
+    pub fn into_inner(self) -> R {
+        self.ot
+    }
+}
+
+impl<T: RotReceiver<Msg = Block>> OtReceiver for RotToOtReceiver<T> {
+    type Msg = Block;
+
+    fn receive<C: AbstractChannel, R: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &BitVec,
+        rng: &mut R,
+    ) -> Result<Vec<Block>, OTReceiverError> {
+        if inputs.is_empty() {
+            return Ok(Vec::new());
+        }
+        let (choices, keys) = self.ot.receive_random(channel, inputs.len(), rng)?;
+        let flips = inputs ^ &choices;
+        channel.write_bits(&flips)?;
+        channel.flush()?;
+
+        inputs
+            .iter()
+            .zip(keys)
+            .map(|(input, k)| {
+                let c0 = channel.read_block()?;
+                let c1 = channel.read_block()?;
+                Ok(k ^ if input { c1 } else { c0 })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use crypto_core::{local_channel_pair, AesRng, BitVec, Block};
+
+    use crate::{
+        ChouOrlandiReceiver, ChouOrlandiSender, OtReceiver, OtSender, RotToOtReceiver,
+        RotToOtSender,
+    };
+
+    fn rand_block_vec(size: usize) -> Vec<Block> {
+        (0..size).map(|_| rand::random::<Block>()).collect()
+    }
+
+    fn rand_bit_vec(size: usize) -> BitVec {
+        BitVec::random(&mut rand::thread_rng(), size)
+    }
+
+    #[test]
+    fn local_derand_ot_test() {
+        let m0 = rand_block_vec(128);
+        let m1 = rand_block_vec(128);
+        let m_inside: Vec<(Block, Block)> = m0.into_iter().zip(m1).collect();
+        let m = m_inside.clone();
+        let select = rand_bit_vec(128);
+
+        let (mut sender, mut receiver) = local_channel_pair();
+
+        let handle = thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let mut ot = RotToOtSender::new(ChouOrlandiSender::new_with_rng(&mut rng));
+            ot.send(&mut sender, &m_inside, &mut rng).unwrap();
+        });
+
+        let mut rng = AesRng::new();
+        let mut ot = RotToOtReceiver::new(ChouOrlandiReceiver::new());
+        let result = ot.receive(&mut receiver, &select, &mut rng).unwrap();
+        for i in 0..128 {
+            assert_eq!(result[i], if select[i] { m[i].1 } else { m[i].0 });
+        }
+        handle.join().unwrap();
+    }
+}
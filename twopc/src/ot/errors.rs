@@ -1,3 +1,5 @@
+use crypto_core::cointoss::CointossError;
+
 #[derive(Debug, thiserror::Error)]
 pub enum OTSenderError {
     #[error("Sender Invalid Input Length")]
@@ -5,6 +7,15 @@ pub enum OTSenderError {
 
     #[error("Sender IO Error")]
     IoError(std::io::Error),
+
+    #[error("Sender Base OT Error")]
+    BaseOtError(Box<OTReceiverError>),
+
+    #[error("Sender Commitment Check Failed")]
+    CommitmentCheckFailed,
+
+    #[error("Sender Consistency Check Failed")]
+    ConsistencyCheckFailed,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -14,6 +25,12 @@ pub enum OTReceiverError {
 
     #[error("Receiver IO Error")]
     IoError(std::io::Error),
+
+    #[error("Receiver Base OT Error")]
+    BaseOtError(Box<OTSenderError>),
+
+    #[error("Receiver Commitment Check Failed")]
+    CommitmentCheckFailed,
 }
 
 impl From<std::io::Error> for OTSenderError {
@@ -26,4 +43,22 @@ impl From<std::io::Error> for OTReceiverError {
     fn from(e: std::io::Error) -> OTReceiverError {
         OTReceiverError::IoError(e)
     }
-}
\ No newline at end of file
+}
+
+impl From<CointossError> for OTSenderError {
+    fn from(e: CointossError) -> OTSenderError {
+        match e {
+            CointossError::IoError(e) => OTSenderError::IoError(e),
+            CointossError::CommitmentCheckFailed => OTSenderError::CommitmentCheckFailed,
+        }
+    }
+}
+
+impl From<CointossError> for OTReceiverError {
+    fn from(e: CointossError) -> OTReceiverError {
+        match e {
+            CointossError::IoError(e) => OTReceiverError::IoError(e),
+            CointossError::CommitmentCheckFailed => OTReceiverError::CommitmentCheckFailed,
+        }
+    }
+}
@@ -0,0 +1,460 @@
+//! Implementation of KOS15 correlated OT extension (cf. <https://eprint.iacr.org/2015/546>)
+//! The protocol follows the description in `doc/src/primitives/ote.md`.
+
+use crypto_core::{
+    cointoss::{challenges, cointoss_receive, cointoss_send},
+    transpose::transpose_to_blocks,
+    utils::xor_inplace,
+    AbstractChannel, AesRng, BitVec, Block, SecretBlock, AES_HASH,
+};
+use rand::{CryptoRng, Rng, RngCore, SeedableRng};
+use zeroize::{Zeroize, Zeroizing};
+
+use super::errors::{OTReceiverError, OTSenderError};
+use crate::{
+    ChouOrlandiReceiver, ChouOrlandiSender, CotReceiver, CotSender, OtReceiver, OtSender,
+    RotReceiver, RotSender,
+};
+
+/// Computational security parameter.
+const LAMBDA: usize = 128;
+/// Statistical security parameter.
+const SSP: usize = 40;
+
+/// Number of extended rows needed for `len` OTs, rounded up to a multiple of 128.
+#[inline]
+fn extended_len(len: usize) -> usize {
+    (len + LAMBDA + SSP).div_ceil(128) * 128
+}
+
+pub struct KosSender {
+    delta: SecretBlock,
+    rngs: Vec<AesRng>,
+    counter: u128,
+}
+
+impl KosSender {
+    /// Run the base OTs with global correlation `delta`, where the KOS sender acts as
+    /// the receiver of Chou-Orlandi OT. The session id of the base OTs is tossed with the
+    /// receiver, so that the base OT keys of two instances are never derived under the same id.
+    pub fn init<C: AbstractChannel, R: CryptoRng + Rng>(
+        channel: &mut C,
+        delta: Block,
+        rng: &mut R,
+    ) -> Result<Self, OTSenderError> {
+        let session = cointoss_send(channel, rng)?;
+        Self::init_with_session(channel, session, delta, rng)
+    }
+
+    /// Run the base OTs as `init` does, under session id `session` agreed with the receiver,
+    /// e.g., by a protocol running several OT extensions. The session id must be unique among the
+    /// instances of the sender and the receiver.
+    pub fn init_with_session<C: AbstractChannel, R: CryptoRng + Rng>(
+        channel: &mut C,
+        session: Block,
+        delta: Block,
+        rng: &mut R,
+    ) -> Result<Self, OTSenderError> {
+        let mut choices = BitVec::from_blocks(&[delta], LAMBDA);
+
+        let mut ot = ChouOrlandiReceiver::new().with_session(session);
+        let seeds = ot.receive(channel, &choices, rng);
+        choices.zeroize();
+        let mut seeds = seeds.map_err(|e| OTSenderError::BaseOtError(Box::new(e)))?;
+        let rngs = seeds.iter().copied().map(AesRng::from_seed).collect();
+        seeds.zeroize();
+
+        Ok(Self {
+            delta: SecretBlock::new(delta),
+            rngs,
+            counter: 0,
+        })
+    }
+
+    /// Extend the base OTs to `len` rows `q_j` that pass the correlation check, where
+    /// `q_j = t_j ⊕ x_j · Δ`.
+    fn extend<C: AbstractChannel, R: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        len: usize,
+        rng: &mut R,
+    ) -> Result<Vec<Block>, OTSenderError> {
+        let nrows = extended_len(len);
+        let ncol_bytes = nrows / 8;
+        let mut delta_bytes: [u8; 16] = (*self.delta.expose()).into();
+
+        // q^i = Δ_i · u^i ⊕ t^i_{Δ_i}
+        let mut qs = vec![0u8; LAMBDA * ncol_bytes];
+        let mut u = vec![0u8; ncol_bytes];
+        for (i, (q, rng)) in qs
+            .chunks_exact_mut(ncol_bytes)
+            .zip(self.rngs.iter_mut())
+            .enumerate()
+        {
+            rng.fill_bytes(q);
+            channel.read_bytes(&mut u)?;
+            // Add u^i if Δ_i is set, without branching on Δ.
+            let mask = 0u8.wrapping_sub((delta_bytes[i / 8] >> (i % 8)) & 1);
+            for (q, u) in q.iter_mut().zip(u.iter()) {
+                *q ^= u & mask;
+            }
+        }
+        delta_bytes.zeroize();
+        let mut qs = transpose_to_blocks(&qs, nrows);
+
+        // Correlation check.
+        let seed = cointoss_send(channel, rng)?;
+        let chis = challenges(seed, nrows);
+
+        let x = channel.read_block()?;
+        let t = channel.read_block()?;
+
+        let q = x.gf_mul(*self.delta.expose()) ^ Block::gf_inner_product(&qs, &chis);
+        if q != t {
+            return Err(OTSenderError::ConsistencyCheckFailed);
+        }
+
+        qs.truncate(len);
+        Ok(qs)
+    }
+}
+
+impl CotSender for KosSender {
+    type Msg = Block;
+
+    fn delta(&self) -> Block {
+        *self.delta.expose()
+    }
+
+    fn send_correlated<C: AbstractChannel, R: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        len: usize,
+        rng: &mut R,
+    ) -> Result<Vec<(Block, Block)>, OTSenderError> {
+        let mut qs = self.extend(channel, len, rng)?;
+        let delta = *self.delta.expose();
+
+        // v_j = H(j, q_j) ⊕ H(j, q_j ⊕ Δ) ⊕ Δ
+        let out = qs
+            .iter()
+            .enumerate()
+            .map(|(j, q)| {
+                let tweak = Block::from(self.counter + j as u128);
+                let h0 = AES_HASH.tccr_hash(tweak, *q);
+                let h1 = AES_HASH.tccr_hash(tweak, *q ^ delta);
+                let v = h0 ^ h1 ^ delta;
+                channel.write_block(&v)?;
+                Ok((h0, h0 ^ delta))
+            })
+            .collect::<Result<_, OTSenderError>>();
+        qs.zeroize();
+        let out = out?;
+        channel.flush()?;
+
+        self.counter += len as u128;
+        Ok(out)
+    }
+}
+
+impl RotSender for KosSender {
+    type Msg = Block;
+
+    fn send_random<C: AbstractChannel, R: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        len: usize,
+        rng: &mut R,
+    ) -> Result<Vec<(Block, Block)>, OTSenderError> {
+        let mut qs = self.extend(channel, len, rng)?;
+        let delta = *self.delta.expose();
+
+        let out = qs
+            .iter()
+            .enumerate()
+            .map(|(j, q)| {
+                let tweak = Block::from(self.counter + j as u128);
+                (
+                    AES_HASH.tccr_hash(tweak, *q),
+                    AES_HASH.tccr_hash(tweak, *q ^ delta),
+                )
+            })
+            .collect();
+        qs.zeroize();
+
+        self.counter += len as u128;
+        Ok(out)
+    }
+}
+
+pub struct KosReceiver {
+    rngs: Vec<(AesRng, AesRng)>,
+    counter: u128,
+}
+
+impl KosReceiver {
+    /// Run the base OTs with random seeds, where the KOS receiver acts as the sender
+    /// of Chou-Orlandi OT. The session id of the base OTs is tossed with the sender.
+    pub fn init<C: AbstractChannel, R: CryptoRng + Rng>(
+        channel: &mut C,
+        rng: &mut R,
+    ) -> Result<Self, OTReceiverError> {
+        let session = cointoss_receive(channel, rng)?;
+        Self::init_with_session(channel, session, rng)
+    }
+
+    /// Run the base OTs as `init` does, under session id `session` agreed with the sender.
+    pub fn init_with_session<C: AbstractChannel, R: CryptoRng + Rng>(
+        channel: &mut C,
+        session: Block,
+        rng: &mut R,
+    ) -> Result<Self, OTReceiverError> {
+        let seeds: Vec<(Block, Block)> = (0..LAMBDA)
+            .map(|_| (rng.gen::<Block>(), rng.gen::<Block>()))
+            .collect();
+
+        let mut ot = ChouOrlandiSender::new_with_rng(rng).with_session(session);
+        ot.send(channel, &seeds, rng)
+            .map_err(|e| OTReceiverError::BaseOtError(Box::new(e)))?;
+        let rngs = seeds
+            .into_iter()
+            .map(|(k0, k1)| (AesRng::from_seed(k0), AesRng::from_seed(k1)))
+            .collect();
+
+        Ok(Self { rngs, counter: 0 })
+    }
+
+    /// Extend the base OTs to rows `t_j` with choice bits `inputs`.
+    fn extend<C: AbstractChannel, R: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &BitVec,
+        rng: &mut R,
+    ) -> Result<Vec<Block>, OTReceiverError> {
+        let len = inputs.len();
+        let nrows = extended_len(len);
+        let ncol_bytes = nrows / 8;
+
+        // x = x_1 || ... || x_ℓ || x', with x' uniformly random.
+        let mut xs = Zeroizing::new(inputs.clone());
+        xs.extend_from_bitvec(&BitVec::random(rng, nrows - len));
+        let x_bytes = xs.as_bytes();
+
+        // u^i = t_0^i ⊕ t_1^i ⊕ x
+        let mut ts = vec![0u8; LAMBDA * ncol_bytes];
+        let mut u = vec![0u8; ncol_bytes];
+        for (t, (rng0, rng1)) in ts.chunks_exact_mut(ncol_bytes).zip(self.rngs.iter_mut()) {
+            rng0.fill_bytes(t);
+            rng1.fill_bytes(&mut u);
+            xor_inplace(&mut u, t);
+            xor_inplace(&mut u, x_bytes);
+            channel.write_bytes(&u)?;
+        }
+        channel.flush()?;
+        let mut ts = transpose_to_blocks(&ts, nrows);
+
+        // Correlation check.
+        let seed = cointoss_receive(channel, rng)?;
+        let chis = challenges(seed, nrows);
+
+        let mut x = Block::default();
+        for (chi, b) in chis.iter().zip(xs.iter()) {
+            if b {
+                x ^= *chi;
+            }
+        }
+        let t = Block::gf_inner_product(&ts, &chis);
+        channel.write_block(&x)?;
+        channel.write_block(&t)?;
+        channel.flush()?;
+
+        ts.truncate(len);
+        Ok(ts)
+    }
+}
+
+impl CotReceiver for KosReceiver {
+    type Msg = Block;
+
+    fn receive_correlated<C: AbstractChannel, R: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &BitVec,
+        rng: &mut R,
+    ) -> Result<Vec<Block>, OTReceiverError> {
+        let ts = self.extend(channel, inputs, rng)?;
+
+        let out = ts
+            .iter()
+            .zip(inputs.iter())
+            .enumerate()
+            .map(|(j, (t, b))| {
+                let tweak = Block::from(self.counter + j as u128);
+                let h = AES_HASH.tccr_hash(tweak, *t);
+                let v = channel.read_block()?;
+                Ok(if b { h ^ v } else { h })
+            })
+            .collect::<Result<_, OTReceiverError>>()?;
+
+        self.counter += inputs.len() as u128;
+        Ok(out)
+    }
+}
+
+impl RotReceiver for KosReceiver {
+    type Msg = Block;
+
+    fn receive_random<C: AbstractChannel, R: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        len: usize,
+        rng: &mut R,
+    ) -> Result<(BitVec, Vec<Block>), OTReceiverError> {
+        let choices = BitVec::random(rng, len);
+        let ts = self.extend(channel, &choices, rng)?;
+
+        let out = ts
+            .iter()
+            .enumerate()
+            .map(|(j, t)| AES_HASH.tccr_hash(Block::from(self.counter + j as u128), *t))
+            .collect();
+
+        self.counter += len as u128;
+        Ok((choices, out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use crypto_core::{local_channel_pair, AesRng, BitVec, Block, Fault, FaultChannel};
+    use rand::Rng;
+
+    use super::{extended_len, LAMBDA};
+    use crate::{
+        CotReceiver, CotSender, KosReceiver, KosSender, OTSenderError, OtReceiver, OtSender,
+        RotToOtReceiver, RotToOtSender,
+    };
+
+    fn rand_bit_vec(size: usize) -> BitVec {
+        BitVec::random(&mut rand::thread_rng(), size)
+    }
+
+    #[test]
+    fn local_kos_cot_test() {
+        let select = rand_bit_vec(1000);
+        let select_inside = select.clone();
+
+        let (mut sender, mut receiver) = local_channel_pair();
+
+        let handle = thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let delta = rng.gen::<Block>().set_lsb();
+            let mut ot = KosSender::init(&mut sender, delta, &mut rng).unwrap();
+            let mut out = ot.send_correlated(&mut sender, 1000, &mut rng).unwrap();
+            out.extend(ot.send_correlated(&mut sender, 1000, &mut rng).unwrap());
+            (delta, out)
+        });
+
+        let mut rng = AesRng::new();
+        let mut ot = KosReceiver::init(&mut receiver, &mut rng).unwrap();
+        let mut result = ot
+            .receive_correlated(&mut receiver, &select_inside, &mut rng)
+            .unwrap();
+        result.extend(
+            ot.receive_correlated(&mut receiver, &select_inside, &mut rng)
+                .unwrap(),
+        );
+
+        let (delta, out) = handle.join().unwrap();
+        let select = [select.to_vec(), select.to_vec()].concat();
+        for ((r, m), b) in result.iter().zip(out.iter()).zip(select.iter()) {
+            assert_eq!(m.1, m.0 ^ delta);
+            assert_eq!(*r, if *b { m.1 } else { m.0 });
+        }
+    }
+
+    #[test]
+    fn local_kos_derand_ot_test() {
+        let m: Vec<(Block, Block)> = (0..1000)
+            .map(|_| (rand::random::<Block>(), rand::random::<Block>()))
+            .collect();
+        let m_inside = m.clone();
+        let select = rand_bit_vec(1000);
+
+        let (mut sender, mut receiver) = local_channel_pair();
+
+        let handle = thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let delta = rng.gen::<Block>();
+            let ot = KosSender::init(&mut sender, delta, &mut rng).unwrap();
+            let mut ot = RotToOtSender::new(ot);
+            ot.send(&mut sender, &m_inside, &mut rng).unwrap();
+        });
+
+        let mut rng = AesRng::new();
+        let ot = KosReceiver::init(&mut receiver, &mut rng).unwrap();
+        let mut ot = RotToOtReceiver::new(ot);
+        let result = ot.receive(&mut receiver, &select, &mut rng).unwrap();
+        for i in 0..1000 {
+            assert_eq!(result[i], if select[i] { m[i].1 } else { m[i].0 });
+        }
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn kos_session_test() {
+        // The base OTs of parties with different session ids yield unrelated keys, so the
+        // correlation check fails.
+        let (mut sender, mut receiver) = local_channel_pair();
+
+        let handle = thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let delta = rng.gen::<Block>().set_lsb();
+            let mut ot =
+                KosSender::init_with_session(&mut sender, Block::from(1u128), delta, &mut rng)
+                    .unwrap();
+            ot.send_correlated(&mut sender, 100, &mut rng)
+        });
+
+        let mut rng = AesRng::new();
+        let mut ot =
+            KosReceiver::init_with_session(&mut receiver, Block::from(2u128), &mut rng).unwrap();
+        // The sender aborts, so the receiver fails to read the output.
+        assert!(ot
+            .receive_correlated(&mut receiver, &rand_bit_vec(100), &mut rng)
+            .is_err());
+        assert!(matches!(
+            handle.join().unwrap(),
+            Err(OTSenderError::ConsistencyCheckFailed)
+        ));
+    }
+
+    #[test]
+    fn kos_fault_test() {
+        // The receiver corrupts the first byte of `t` in the correlation check, after the columns
+        // `u^i` and the 64 bytes it writes in the coin-tossing.
+        let (mut sender, mut receiver) = local_channel_pair();
+
+        let handle = thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let delta = rng.gen::<Block>().set_lsb();
+            let mut ot = KosSender::init(&mut sender, delta, &mut rng).unwrap();
+            ot.send_correlated(&mut sender, 100, &mut rng)
+        });
+
+        let mut rng = AesRng::new();
+        let mut ot = KosReceiver::init(&mut receiver, &mut rng).unwrap();
+        let offset = LAMBDA * extended_len(100) / 8 + 64 + 16;
+        let mut receiver = FaultChannel::new(receiver).fault_write(offset, Fault::Corrupt(1));
+        // The sender aborts, so the receiver fails to read the output.
+        assert!(ot
+            .receive_correlated(&mut receiver, &rand_bit_vec(100), &mut rng)
+            .is_err());
+        assert!(matches!(
+            handle.join().unwrap(),
+            Err(OTSenderError::ConsistencyCheckFailed)
+        ));
+    }
+}
@@ -3,11 +3,15 @@
 
 pub mod errors;
 pub mod co;
+pub mod derand;
+pub mod kos;
 
 pub use errors::{OTReceiverError, OTSenderError};
 pub use co::*;
+pub use derand::*;
+pub use kos::*;
 
-use crypto_core::AbstractChannel;
+use crypto_core::{AbstractChannel, BitVec};
 use rand::{CryptoRng, Rng};
 
 /// Sender of OT
@@ -39,7 +43,80 @@ where
     fn receive<C: AbstractChannel, R: CryptoRng + Rng>(
         &mut self,
         channel: &mut C,
-        inputs: &[bool],
+        inputs: &BitVec,
         rng: &mut R,
     ) -> Result<Vec<Self::Msg>, OTReceiverError>;
 }
+
+/// Sender of correlated OT
+pub trait CotSender
+where
+    Self: Sized,
+{
+    /// Message type.
+    type Msg: Sized + AsMut<[u8]>;
+
+    /// Return the global correlation `Δ`.
+    fn delta(&self) -> Self::Msg;
+
+    /// Send `len` correlated OTs, and output pairs `(X, X ⊕ Δ)`.
+    #[allow(clippy::type_complexity)]
+    fn send_correlated<C: AbstractChannel, R: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        len: usize,
+        rng: &mut R,
+    ) -> Result<Vec<(Self::Msg, Self::Msg)>, OTSenderError>;
+}
+
+/// Receiver of correlated OT
+pub trait CotReceiver
+where
+    Self: Sized,
+{
+    /// Message type.
+    type Msg: Sized + AsMut<[u8]>;
+
+    /// Receive correlated OTs, and output `X ⊕ b · Δ` for each choice bit `b`.
+    fn receive_correlated<C: AbstractChannel, R: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &BitVec,
+        rng: &mut R,
+    ) -> Result<Vec<Self::Msg>, OTReceiverError>;
+}
+
+/// Sender of random OT
+pub trait RotSender
+where
+    Self: Sized,
+{
+    /// Message type.
+    type Msg: Sized + AsMut<[u8]>;
+
+    /// Send `len` random OTs, and output random message pairs.
+    #[allow(clippy::type_complexity)]
+    fn send_random<C: AbstractChannel, R: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        len: usize,
+        rng: &mut R,
+    ) -> Result<Vec<(Self::Msg, Self::Msg)>, OTSenderError>;
+}
+
+/// Receiver of random OT
+pub trait RotReceiver
+where
+    Self: Sized,
+{
+    /// Message type.
+    type Msg: Sized + AsMut<[u8]>;
+
+    /// Receive `len` random OTs, and output random choice bits with the selected messages.
+    fn receive_random<C: AbstractChannel, R: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        len: usize,
+        rng: &mut R,
+    ) -> Result<(BitVec, Vec<Self::Msg>), OTReceiverError>;
+}
@@ -0,0 +1,67 @@
+use crate::{EvaluatorError, GeneratorError, OTReceiverError, OTSenderError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum YaoGarblerError {
+    #[error("Garbler Invalid Input Length")]
+    InvalidInputLength,
+
+    #[error("Garbler IO Error")]
+    IoError(std::io::Error),
+
+    #[error("Garbler Generator Error")]
+    GeneratorError(GeneratorError),
+
+    #[error("Garbler OT Error")]
+    OtError(OTSenderError),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum YaoEvaluatorError {
+    #[error("Evaluator Invalid Input Length")]
+    InvalidInputLength,
+
+    #[error("Evaluator IO Error")]
+    IoError(std::io::Error),
+
+    #[error("Evaluator Evaluation Error")]
+    EvaluatorError(EvaluatorError),
+
+    #[error("Evaluator OT Error")]
+    OtError(OTReceiverError),
+}
+
+impl From<std::io::Error> for YaoGarblerError {
+    fn from(e: std::io::Error) -> YaoGarblerError {
+        YaoGarblerError::IoError(e)
+    }
+}
+
+impl From<GeneratorError> for YaoGarblerError {
+    fn from(e: GeneratorError) -> YaoGarblerError {
+        YaoGarblerError::GeneratorError(e)
+    }
+}
+
+impl From<OTSenderError> for YaoGarblerError {
+    fn from(e: OTSenderError) -> YaoGarblerError {
+        YaoGarblerError::OtError(e)
+    }
+}
+
+impl From<std::io::Error> for YaoEvaluatorError {
+    fn from(e: std::io::Error) -> YaoEvaluatorError {
+        YaoEvaluatorError::IoError(e)
+    }
+}
+
+impl From<EvaluatorError> for YaoEvaluatorError {
+    fn from(e: EvaluatorError) -> YaoEvaluatorError {
+        YaoEvaluatorError::EvaluatorError(e)
+    }
+}
+
+impl From<OTReceiverError> for YaoEvaluatorError {
+    fn from(e: OTReceiverError) -> YaoEvaluatorError {
+        YaoEvaluatorError::OtError(e)
+    }
+}
@@ -0,0 +1,340 @@
+use circuit::Circuit;
+use crypto_core::{AbstractChannel, BitVec, Block};
+use rand::{CryptoRng, Rng};
+
+use super::errors::YaoEvaluatorError;
+use crate::{EvaluatorOutput, GCEvaluator, GarbledCircuit, InputLabel, OtReceiver, OutputPolicy};
+
+/// The evaluator of Yao's protocol. The evaluator's inputs are the last input wires of the
+/// circuit, and the garbler's inputs are the remaining input wires.
+pub struct YaoEvaluator<E: GCEvaluator, OT: OtReceiver<Msg = Block>> {
+    ev: E,
+    ot: OT,
+}
+
+impl<E: GCEvaluator, OT: OtReceiver<Msg = Block>> YaoEvaluator<E, OT> {
+    pub fn new(ev: E, ot: OT) -> Self {
+        Self { ev, ot }
+    }
+
+    /// Receive the garbled circuit of `circ`, obtain the labels of `input` through OT, and
+    /// evaluate the garbled circuit to get the output bits. All outputs are revealed to the
+    /// evaluator only.
+    pub fn run<C: AbstractChannel, R: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        circ: &Circuit,
+        input: &BitVec,
+        rng: &mut R,
+    ) -> Result<BitVec, YaoEvaluatorError> {
+        let policies = vec![OutputPolicy::Evaluator; circ.noutput_wires];
+        let outputs = self.run_with_policy(channel, circ, input, &policies, rng)?;
+        Ok(outputs.into_iter().flatten().collect())
+    }
+
+    /// Same as `run`, but the output wires are decoded according to `policies`. Return the
+    /// output bit (or the share of a shared wire) of each output wire, and `None` for wires
+    /// revealed to the garbler only.
+    pub fn run_with_policy<C: AbstractChannel, R: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        circ: &Circuit,
+        input: &BitVec,
+        policies: &[OutputPolicy],
+        rng: &mut R,
+    ) -> Result<Vec<Option<bool>>, YaoEvaluatorError> {
+        if input.len() > circ.ninput_wires {
+            return Err(YaoEvaluatorError::InvalidInputLength);
+        }
+        let ngenerator_inputs = circ.ninput_wires - input.len();
+
+        let gc = GarbledCircuit::receive(channel, circ, ngenerator_inputs, 0)?;
+
+        let evaluator_input_labels: Vec<InputLabel> = self
+            .ot
+            .receive(channel, input, rng)?
+            .into_iter()
+            .enumerate()
+            .map(|(i, label)| InputLabel {
+                id: ngenerator_inputs + i,
+                label,
+            })
+            .collect();
+
+        let output_labels = self.ev.eval_labels(circ, &gc, &evaluator_input_labels)?;
+        let output = EvaluatorOutput::new(output_labels, policies)?;
+        Ok(output.run(channel)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+    use std::thread;
+
+    use circuit::{Circuit, CircuitInput};
+    use crypto_core::{
+        local_channel_pair, AesRng, BitVec, Block, Fault, FaultChannel, LocalChannel,
+    };
+    use rand::Rng;
+
+    use crate::{
+        ChouOrlandiReceiver, ChouOrlandiSender, CotReceiver, CotSender, GCEvaluator, GCGenerator,
+        GarbledCircuit, HalfGateEvaluator, HalfGateGenerator, InputLabel, InputLabelPair,
+        KosReceiver, KosSender, OTReceiverError, OTSenderError, OutputPolicy, RotToOtReceiver,
+        RotToOtSender, YaoEvaluator, YaoEvaluatorError, YaoGarbler, YaoGarblerError,
+    };
+
+    #[test]
+    fn yao_adder64_test() {
+        // m1 = 2^64 - 1
+        let m1 = BitVec::from(vec![true; 64]);
+        // m2 = 1
+        let m2 = BitVec::from_u64(1, 64);
+
+        let (mut sender, mut receiver) = local_channel_pair();
+
+        let handle = thread::spawn(move || {
+            let circ = Circuit::load("../circuit/circuit_files/bristol/adder64.txt").unwrap();
+            let mut rng = AesRng::new();
+            let mut garbler = YaoGarbler::new(
+                HalfGateGenerator::new(),
+                ChouOrlandiSender::new_with_rng(&mut rng),
+            );
+            garbler.run(&mut sender, &circ, &m1, &mut rng).unwrap();
+        });
+
+        let circ = Circuit::load("../circuit/circuit_files/bristol/adder64.txt").unwrap();
+        let mut rng = AesRng::new();
+        let mut evaluator = YaoEvaluator::new(HalfGateEvaluator::new(), ChouOrlandiReceiver::new());
+        let outputs = evaluator.run(&mut receiver, &circ, &m2, &mut rng).unwrap();
+        assert_eq!(outputs, BitVec::zeros(64));
+
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn yao_output_policy_test() {
+        let a = 0x1234_5678_9abc_def0u64;
+        let b = 0x0fed_cba9_8765_4321u64;
+        let m1 = BitVec::from_u64(a, 64);
+        let m2 = BitVec::from_u64(b, 64);
+        let sum = BitVec::from_u64(a.wrapping_add(b), 64);
+        let policies: Vec<OutputPolicy> = (0..64)
+            .map(|i| match i / 16 {
+                0 => OutputPolicy::Evaluator,
+                1 => OutputPolicy::Generator,
+                2 => OutputPolicy::Both,
+                _ => OutputPolicy::Shared,
+            })
+            .collect();
+        let garbler_policies = policies.clone();
+
+        let (mut sender, mut receiver) = local_channel_pair();
+
+        let handle = thread::spawn(move || {
+            let circ = Circuit::load("../circuit/circuit_files/bristol/adder64.txt").unwrap();
+            let mut rng = AesRng::new();
+            let mut garbler = YaoGarbler::new(
+                HalfGateGenerator::new(),
+                ChouOrlandiSender::new_with_rng(&mut rng),
+            );
+            garbler
+                .run_with_policy(&mut sender, &circ, &m1, &garbler_policies, &mut rng)
+                .unwrap()
+        });
+
+        let circ = Circuit::load("../circuit/circuit_files/bristol/adder64.txt").unwrap();
+        let mut rng = AesRng::new();
+        let mut evaluator = YaoEvaluator::new(HalfGateEvaluator::new(), ChouOrlandiReceiver::new());
+        let ev_outputs = evaluator
+            .run_with_policy(&mut receiver, &circ, &m2, &policies, &mut rng)
+            .unwrap();
+        let gen_outputs = handle.join().unwrap();
+
+        for i in 0..64 {
+            let (ev, gen) = (ev_outputs[i], gen_outputs[i]);
+            match policies[i] {
+                OutputPolicy::Evaluator => assert_eq!((ev, gen), (Some(sum[i]), None)),
+                OutputPolicy::Generator => assert_eq!((ev, gen), (None, Some(sum[i]))),
+                OutputPolicy::Both => assert_eq!((ev, gen), (Some(sum[i]), Some(sum[i]))),
+                OutputPolicy::Shared => assert_eq!(ev.unwrap() ^ gen.unwrap(), sum[i]),
+            }
+        }
+    }
+
+    #[test]
+    fn yao_aes_kos_test() {
+        let input = BitVec::zeros(128);
+        let key = BitVec::zeros(128);
+
+        let (mut sender, mut receiver) = local_channel_pair();
+
+        let handle = thread::spawn(move || {
+            let circ =
+                Circuit::load("../circuit/circuit_files/bristol/aes_128_reverse.txt").unwrap();
+            let mut rng = AesRng::new();
+            let delta = rng.gen::<Block>();
+            let ot = KosSender::init(&mut sender, delta, &mut rng).unwrap();
+            let mut garbler = YaoGarbler::new(HalfGateGenerator::new(), RotToOtSender::new(ot));
+            garbler.run(&mut sender, &circ, &input, &mut rng).unwrap();
+        });
+
+        let circ = Circuit::load("../circuit/circuit_files/bristol/aes_128_reverse.txt").unwrap();
+        let mut rng = AesRng::new();
+        let ot = KosReceiver::init(&mut receiver, &mut rng).unwrap();
+        let mut evaluator = YaoEvaluator::new(HalfGateEvaluator::new(), RotToOtReceiver::new(ot));
+        let mut outputs = evaluator
+            .run(&mut receiver, &circ, &key, &mut rng)
+            .unwrap()
+            .to_vec();
+        outputs.reverse();
+        assert_eq!(outputs.into_iter().map(|i| (i as u8).to_string()).collect::<String>(),
+            "01100110111010010100101111010100111011111000101000101100001110111000100001001100111110100101100111001010001101000010101100101110");
+
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn yao_aes_cot_test() {
+        // The evaluator's input labels are fixed by correlated OT under the garbler's delta,
+        // and the circuit is garbled around them, without a second round of OT.
+        let key = BitVec::zeros(128);
+
+        let (mut sender, mut receiver) = local_channel_pair();
+
+        let handle = thread::spawn(move || {
+            let circ =
+                Circuit::load("../circuit/circuit_files/bristol/aes_128_reverse.txt").unwrap();
+            let mut rng = AesRng::new();
+            let delta = rng.gen::<Block>().set_lsb();
+            let mut ot = KosSender::init(&mut sender, delta, &mut rng).unwrap();
+            let input_labels: Vec<InputLabelPair> = ot
+                .send_correlated(&mut sender, 128, &mut rng)
+                .unwrap()
+                .into_iter()
+                .enumerate()
+                .map(|(i, (z_0, z_1))| InputLabelPair {
+                    id: 128 + i,
+                    labels: [z_0, z_1],
+                })
+                .collect();
+            let complete_gc = HalfGateGenerator::new()
+                .garble_with_labels(&mut rng, &circ, delta, &input_labels)
+                .unwrap();
+            let input: Vec<CircuitInput> = (0..128)
+                .map(|id| CircuitInput {
+                    id,
+                    value: Block::default(),
+                })
+                .collect();
+            complete_gc.to_public(&input).send(&mut sender).unwrap();
+        });
+
+        let circ = Circuit::load("../circuit/circuit_files/bristol/aes_128_reverse.txt").unwrap();
+        let mut rng = AesRng::new();
+        let mut ot = KosReceiver::init(&mut receiver, &mut rng).unwrap();
+        let key_labels: Vec<InputLabel> = ot
+            .receive_correlated(&mut receiver, &key, &mut rng)
+            .unwrap()
+            .into_iter()
+            .enumerate()
+            .map(|(i, label)| InputLabel { id: 128 + i, label })
+            .collect();
+        let gc = GarbledCircuit::receive(&mut receiver, &circ, 128, circ.noutput_wires).unwrap();
+        let mut outputs = HalfGateEvaluator::new()
+            .eval(&circ, &gc, &key_labels)
+            .unwrap()
+            .to_vec();
+        outputs.reverse();
+        assert_eq!(outputs.into_iter().map(|i| (i as u8).to_string()).collect::<String>(),
+            "01100110111010010100101111010100111011111000101000101100001110111000100001001100111110100101100111001010001101000010101100101110");
+
+        handle.join().unwrap();
+    }
+
+    /// Run Yao's protocol on adder64 over faulty channels, each party dropping its channel when
+    /// it returns.
+    fn yao_with_faults(
+        sender: FaultChannel<LocalChannel>,
+        receiver: FaultChannel<LocalChannel>,
+    ) -> (
+        Result<(), YaoGarblerError>,
+        Result<BitVec, YaoEvaluatorError>,
+    ) {
+        let handle = thread::spawn(move || {
+            let mut sender = sender;
+            let circ = Circuit::load("../circuit/circuit_files/bristol/adder64.txt").unwrap();
+            let input = BitVec::from_u64(1, 64);
+            let mut rng = AesRng::new();
+            let mut garbler = YaoGarbler::new(
+                HalfGateGenerator::new(),
+                ChouOrlandiSender::new_with_rng(&mut rng),
+            );
+            garbler.run(&mut sender, &circ, &input, &mut rng)
+        });
+        let result = {
+            let mut receiver = receiver;
+            let circ = Circuit::load("../circuit/circuit_files/bristol/adder64.txt").unwrap();
+            let input = BitVec::from_u64(2, 64);
+            let mut evaluator =
+                YaoEvaluator::new(HalfGateEvaluator::new(), ChouOrlandiReceiver::new());
+            evaluator.run(&mut receiver, &circ, &input, &mut AesRng::new())
+        };
+        (handle.join().unwrap(), result)
+    }
+
+    #[test]
+    fn yao_fault_test() {
+        let garbler_io_error = |r: &Result<(), YaoGarblerError>| {
+            matches!(
+                r,
+                Err(YaoGarblerError::IoError(_))
+                    | Err(YaoGarblerError::OtError(OTSenderError::IoError(_)))
+            )
+        };
+
+        // The garbled circuit starts with a 12-byte nonce and the 64 labels of the garbler's
+        // input, followed by the tag of the garbling scheme.
+        let tag_offset = 12 + 64 * 16;
+        let (sender, receiver) = local_channel_pair();
+        let sender = FaultChannel::new(sender).fault_write(tag_offset, Fault::Corrupt(0x02));
+        let (g, e) = yao_with_faults(sender, FaultChannel::new(receiver));
+        assert!(matches!(
+            e,
+            Err(YaoEvaluatorError::IoError(ref e)) if e.kind() == ErrorKind::InvalidData
+        ));
+        assert!(garbler_io_error(&g));
+
+        // The garbled circuit is cut in the middle of the tables.
+        let (sender, receiver) = local_channel_pair();
+        let sender = FaultChannel::new(sender).fault_write(tag_offset + 1000, Fault::Truncate);
+        let (g, e) = yao_with_faults(sender, FaultChannel::new(receiver));
+        assert!(matches!(
+            e,
+            Err(YaoEvaluatorError::IoError(ref e)) if e.kind() == ErrorKind::UnexpectedEof
+        ));
+        assert!(garbler_io_error(&g));
+
+        // The evaluator closes the connection during the OTs.
+        let (sender, receiver) = local_channel_pair();
+        let receiver = FaultChannel::new(receiver).fault_write(100, Fault::Close);
+        let (g, e) = yao_with_faults(FaultChannel::new(sender), receiver);
+        assert!(matches!(
+            e,
+            Err(YaoEvaluatorError::OtError(OTReceiverError::IoError(ref e)))
+                if e.kind() == ErrorKind::ConnectionAborted
+        ));
+        assert!(matches!(
+            g,
+            Err(YaoGarblerError::OtError(OTSenderError::IoError(ref e)))
+                if e.kind() == ErrorKind::UnexpectedEof
+        ));
+
+        // Without faults, 1 + 2 = 3.
+        let (sender, receiver) = local_channel_pair();
+        let (g, e) = yao_with_faults(FaultChannel::new(sender), FaultChannel::new(receiver));
+        assert!(g.is_ok());
+        assert_eq!(e.unwrap(), BitVec::from_u64(3, 64));
+    }
+}
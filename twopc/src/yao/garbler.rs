@@ -0,0 +1,77 @@
+use circuit::{Circuit, CircuitInput};
+use crypto_core::{AbstractChannel, BitVec, Block};
+use rand::{CryptoRng, Rng};
+
+use super::errors::YaoGarblerError;
+use crate::{GCGenerator, GeneratorOutput, OtSender, OutputPolicy};
+
+/// The garbler of Yao's protocol. The garbler's inputs are the first input wires of the circuit,
+/// and the evaluator's inputs are the remaining input wires.
+pub struct YaoGarbler<G: GCGenerator, OT: OtSender<Msg = Block>> {
+    gen: G,
+    ot: OT,
+}
+
+impl<G: GCGenerator, OT: OtSender<Msg = Block>> YaoGarbler<G, OT> {
+    pub fn new(gen: G, ot: OT) -> Self {
+        Self { gen, ot }
+    }
+
+    /// Garble `circ`, send the garbled circuit with the encoded `input` to the evaluator, and
+    /// transfer the evaluator's input labels through OT. All outputs are revealed to the
+    /// evaluator only.
+    pub fn run<C: AbstractChannel, R: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        circ: &Circuit,
+        input: &BitVec,
+        rng: &mut R,
+    ) -> Result<(), YaoGarblerError> {
+        let policies = vec![OutputPolicy::Evaluator; circ.noutput_wires];
+        self.run_with_policy(channel, circ, input, &policies, rng)?;
+        Ok(())
+    }
+
+    /// Same as `run`, but the output wires are decoded according to `policies`. Return the
+    /// output bit (or the share of a shared wire) of each output wire, and `None` for wires
+    /// revealed to the evaluator only.
+    pub fn run_with_policy<C: AbstractChannel, R: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        circ: &Circuit,
+        input: &BitVec,
+        policies: &[OutputPolicy],
+        rng: &mut R,
+    ) -> Result<Vec<Option<bool>>, YaoGarblerError> {
+        if input.len() > circ.ninput_wires {
+            return Err(YaoGarblerError::InvalidInputLength);
+        }
+
+        let complete_gc = self.gen.garble(rng, circ)?;
+        let output = GeneratorOutput::new(
+            complete_gc.wire_labels[(circ.nwires - circ.noutput_wires)..].to_vec(),
+            policies,
+        )?;
+
+        let generator_inputs: Vec<CircuitInput> = input
+            .iter()
+            .enumerate()
+            .map(|(id, value)| CircuitInput {
+                id,
+                value: Block::from(value as u128),
+            })
+            .collect();
+        // The decoding bits are sent by `output` according to the policies.
+        let mut gc = complete_gc.to_public(&generator_inputs);
+        gc.output_bits.clear();
+        gc.send(channel)?;
+
+        let evaluator_labels: Vec<(Block, Block)> = complete_gc.input_labels[input.len()..]
+            .iter()
+            .map(|label| (label[0], label[1]))
+            .collect();
+        self.ot.send(channel, &evaluator_labels, rng)?;
+
+        Ok(output.run(channel)?)
+    }
+}
@@ -0,0 +1,10 @@
+//! Yao's two-party computation protocol built on garbled circuits and oblivious transfer
+//! (cf. `doc/src/primitives/2pc.md`).
+
+mod errors;
+mod evaluator;
+mod garbler;
+
+pub use errors::*;
+pub use evaluator::*;
+pub use garbler::*;